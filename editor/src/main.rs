@@ -2,6 +2,9 @@
 
 use fyroxed_base::{fyrox::event_loop::EventLoop, Editor, StartupData};
 
+#[cfg(not(feature = "dylib"))]
+mod trajectory;
+
 fn main() {
     let event_loop = EventLoop::new().unwrap();
     let mut editor = Editor::new(Some(StartupData {
@@ -25,6 +28,7 @@ fn main() {
     {
         use fish_fall::Game;
         editor.add_game_plugin(Game::new());
+        editor.add_editor_plugin(trajectory::TrajectoryPreviewPlugin);
     }
 
     editor.run(event_loop)
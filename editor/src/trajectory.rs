@@ -0,0 +1,49 @@
+//! Draws flight trajectories of launch pads in the editor, so a level designer can see where the
+//! actors will land.
+
+use fish_fall::jumper::Jumper;
+use fyroxed_base::{
+    fyrox::{core::color::Color, scene::debug::Line},
+    plugin::EditorPlugin,
+    scene::GameScene,
+    Editor,
+};
+
+#[derive(Default)]
+pub struct TrajectoryPreviewPlugin;
+
+impl EditorPlugin for TrajectoryPreviewPlugin {
+    fn on_update(&mut self, editor: &mut Editor) {
+        let Some(entry) = editor.scenes.current_scene_entry_mut() else {
+            return;
+        };
+        let Some(game_scene) = entry.controller.downcast_mut::<GameScene>() else {
+            return;
+        };
+        let scene = &mut editor.engine.scenes[game_scene.scene];
+
+        for (_, node) in scene.graph.pair_iter() {
+            let Some(jumper) = node.try_get_script::<Jumper>() else {
+                continue;
+            };
+            if !jumper.is_launch_pad() {
+                continue;
+            }
+            let Some(points) = jumper.trajectory(node.global_position(), &scene.graph, 32) else {
+                continue;
+            };
+            for segment in points.windows(2) {
+                scene.drawing_context.add_line(Line {
+                    begin: segment[0],
+                    end: segment[1],
+                    color: Color::GREEN,
+                });
+            }
+            if let Some(landing_point) = points.last() {
+                scene
+                    .drawing_context
+                    .draw_sphere(*landing_point, 10, 10, 0.25, Color::GREEN);
+            }
+        }
+    }
+}
@@ -1,31 +1,253 @@
 //! Object marker components.
 
-use crate::{utils, Game};
+use crate::{
+    explosive,
+    hazard::Hazard,
+    level::Level,
+    pickup::{ActiveEffect, PickupEffect},
+    surface::{Surface, SurfaceZone},
+    team, utils, Game,
+};
 use fyrox::plugin::error::{GameError, GameResult};
 use fyrox::{
     core::{
-        algebra::Vector3, math::Vector3Ext, pool::Handle, pool::MultiBorrowContext,
-        reflect::prelude::*, variable::InheritableVariable, visitor::prelude::*,
+        algebra::{UnitQuaternion, Vector3},
+        math::Vector3Ext,
+        pool::Handle,
+        pool::MultiBorrowContext,
+        reflect::prelude::*,
+        variable::InheritableVariable,
+        visitor::prelude::*,
     },
     graph::SceneGraph,
+    material::MaterialResource,
     rand::{prelude::SliceRandom, thread_rng},
     resource::model::{ModelResource, ModelResourceExtension},
     scene::{
         animation::absm::prelude::*,
+        base::BaseBuilder,
         collider::Collider,
         graph::Graph,
+        joint::{JointBuilder, JointParams},
+        mesh::Mesh,
         node::{container::NodeContainer, Node},
         ragdoll::Ragdoll,
-        rigidbody::RigidBody,
+        rigidbody::{RigidBody, RigidBodyType},
         sound::Sound,
+        transform::TransformBuilder,
     },
     script::{ScriptContext, ScriptMessageContext, ScriptMessagePayload},
 };
 use serde::{Deserialize, Serialize};
+use strum_macros::AsRefStr;
 
 #[derive(Debug, ScriptMessagePayload)]
 pub enum ActorMessage {
     RespawnAt(Vector3<f32>),
+    /// The actor is out of the match and becomes a spectator.
+    Eliminate,
+    /// The actor is pushed away from the center of an explosion.
+    Explosion {
+        center: Vector3<f32>,
+        radius: f32,
+        impulse: f32,
+    },
+    /// The actor is inside a force volume.
+    Force {
+        force: Vector3<f32>,
+        max_speed: f32,
+    },
+    /// The actor is held by another actor.
+    Grabbed {
+        by: Handle<Node>,
+    },
+    /// The actor is not held anymore.
+    Released,
+    /// The actor is shoved or thrown by another actor.
+    Impulse {
+        impulse: Vector3<f32>,
+        knock_down: bool,
+    },
+}
+
+/// High-level state of an actor. The state is derived from the physics and the match state on
+/// the server, every transition is published as [`ActorStateEvent`].
+#[derive(
+    Default, Clone, Copy, Debug, PartialEq, Eq, Visit, Reflect, Serialize, Deserialize, AsRefStr,
+)]
+pub enum ActorState {
+    #[default]
+    Idle,
+    Running,
+    Jumping,
+    Falling,
+    Ragdoll,
+    GettingUp,
+    Finished,
+    Eliminated,
+    Spectating,
+}
+
+impl ActorState {
+    /// Clients show knockdowns and hide eliminated actors, other states are local to the server.
+    pub fn is_replicated(self) -> bool {
+        use ActorState::*;
+        matches!(self, Ragdoll | GettingUp | Eliminated | Spectating)
+    }
+
+    /// Checks whether the transition to the given state is allowed.
+    pub fn can_change_to(self, next: ActorState) -> bool {
+        use ActorState::*;
+        match (self, next) {
+            _ if self == next => false,
+            // Nobody comes back to the match from these states.
+            (Eliminated | Spectating, _) => false,
+            (_, Eliminated | Spectating) => true,
+            // Finished actors stay on the level, so they can be knocked down too.
+            (_, Ragdoll) => true,
+            // A ragdoll must get up first.
+            (Ragdoll, next) => next == GettingUp,
+            (_, GettingUp) => false,
+            // Finished actors only wait for the end of the match.
+            (Finished, _) => false,
+            _ => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ActorStateEvent {
+    pub actor: Handle<Node>,
+    pub from: ActorState,
+    pub to: ActorState,
+}
+
+impl ActorStateEvent {
+    /// Movement states change every few frames, so they are sent to clients only when the actor
+    /// leaves or enters one of the replicated states.
+    pub fn is_replicated(&self) -> bool {
+        self.from.is_replicated() || self.to.is_replicated()
+    }
+}
+
+/// Minimal strength of an impact, that knocks an actor down.
+#[derive(Clone, Debug, Visit, Reflect)]
+#[visit(optional)]
+pub struct ImpactThreshold {
+    /// Relative speed of the colliding bodies.
+    pub relative_speed: f32,
+    /// Contact impulse.
+    pub impulse: f32,
+}
+
+impl ImpactThreshold {
+    /// Returns how many times the impact exceeds the threshold, values below 1.0 mean that the
+    /// impact is too weak.
+    pub fn strength(&self, relative_speed: f32, impulse: f32) -> f32 {
+        (relative_speed / self.relative_speed).max(impulse / self.impulse)
+    }
+}
+
+/// Defines when an actor turns into a ragdoll and for how long.
+#[derive(Clone, Debug, Visit, Reflect)]
+#[visit(optional)]
+pub struct KnockdownProfile {
+    /// Collisions with hazards: spikes, blades, cannon balls, bombs and pendulums.
+    pub hazard: ImpactThreshold,
+    /// Collisions with any body of other actors, including their ragdolls.
+    pub actor: ImpactThreshold,
+    /// Collisions with the rest of the level (floor, walls, obstacles).
+    pub world: ImpactThreshold,
+    /// Time (in seconds) that the actor lies on the ground after the weakest knockdown.
+    pub stun_time: f32,
+    /// Extra stun time for every unit of impact strength above the threshold.
+    pub stun_time_per_strength: f32,
+    pub max_stun_time: f32,
+    /// Time (in seconds) after getting up, during which the actor cannot be knocked down.
+    pub immunity_time: f32,
+}
+
+impl Default for KnockdownProfile {
+    fn default() -> Self {
+        Self {
+            hazard: ImpactThreshold {
+                relative_speed: 10.0,
+                impulse: 2.0,
+            },
+            actor: ImpactThreshold {
+                relative_speed: 12.0,
+                impulse: 4.0,
+            },
+            world: ImpactThreshold {
+                relative_speed: 14.0,
+                impulse: 5.0,
+            },
+            stun_time: 1.0,
+            stun_time_per_strength: 0.5,
+            max_stun_time: 3.0,
+            immunity_time: 1.0,
+        }
+    }
+}
+
+impl KnockdownProfile {
+    pub fn threshold(&self, source: ImpactSource) -> &ImpactThreshold {
+        match source {
+            ImpactSource::World => &self.world,
+            ImpactSource::Actor => &self.actor,
+            ImpactSource::Hazard => &self.hazard,
+        }
+    }
+
+    pub fn stun_time(&self, strength: f32) -> f32 {
+        (self.stun_time + (strength - 1.0).max(0.0) * self.stun_time_per_strength)
+            .min(self.max_stun_time)
+    }
+}
+
+/// Defines how an actor gets up after the ragdoll mode.
+#[derive(Clone, Debug, Visit, Reflect)]
+#[visit(optional)]
+pub struct GetUpProfile {
+    /// Time (in seconds) that the actor stands still after the ragdoll mode, while its pose is
+    /// blended back to the animated one.
+    pub time: f32,
+    /// Time (in seconds) of the blending from the ragdoll pose to the animated pose.
+    pub pose_blend_time: f32,
+    /// The capsule is placed this much above the pelvis, so it does not start inside the floor.
+    pub lift: f32,
+}
+
+impl Default for GetUpProfile {
+    fn default() -> Self {
+        Self {
+            time: 0.6,
+            pose_blend_time: 0.3,
+            lift: 0.3,
+        }
+    }
+}
+
+/// A kind of the body, that hits an actor.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, AsRefStr)]
+#[strum(serialize_all = "lowercase")]
+pub enum ImpactSource {
+    #[default]
+    World,
+    /// Any body of another actor.
+    Actor,
+    /// A body marked as a hazard (see [`Hazard::is_hazard_body`]).
+    Hazard,
+}
+
+/// The strongest impact, that an actor has received recently.
+#[derive(Default, Clone, Copy, Debug)]
+pub struct Impact {
+    pub relative_speed: f32,
+    pub impulse: f32,
+    pub source: ImpactSource,
+    pub strength: f32,
+    pub knocked_down: bool,
 }
 
 #[derive(PartialEq, Eq, Clone, Copy, Debug, Visit, Serialize, Deserialize)]
@@ -46,6 +268,10 @@ pub struct Actor {
     pub in_air_time: f32,
     /// Amount of time that the bot will be lying on the ground with active ragdoll
     pub max_in_air_time: f32,
+    /// Impact thresholds and stun times.
+    pub knockdown: KnockdownProfile,
+    /// Timings of the get-up sequence after a knockdown.
+    pub get_up: GetUpProfile,
     #[reflect(hidden)]
     pub stand_up_timer: f32,
     #[reflect(hidden)]
@@ -62,6 +288,36 @@ pub struct Actor {
     pub speed: f32,
     /// Jump speed of the actor.
     pub jump_vel: f32,
+    /// Time (in seconds) after leaving the ground, during which the actor is still able to jump.
+    pub coyote_time: f32,
+    /// Time (in seconds) for which a jump request is kept, if the actor cannot jump right away.
+    pub jump_buffer_time: f32,
+    /// How fast (0..1) the velocity of the actor follows the input in the air.
+    pub air_control: f32,
+    /// How fast (0..1) the actor loses its horizontal speed in the air without any input.
+    pub air_drag: f32,
+    /// Horizontal speed of the dive lunge.
+    pub dive_speed: f32,
+    /// Time (in seconds) of the belly slide after a dive, the actor cannot move during it.
+    pub dive_recovery: f32,
+    /// Vertical speed, that is added at the start of a dive.
+    pub dive_lift: f32,
+    /// How fast (0..1) the actor loses its speed during the belly slide.
+    pub slide_friction: f32,
+    /// Max distance to an actor, that can be grabbed.
+    pub grab_distance: f32,
+    /// Time (in seconds) after a release, during which the actor cannot grab again.
+    pub grab_cooldown_time: f32,
+    /// Both the holding and the held actors are slowed down by this factor.
+    pub grab_speed_factor: f32,
+    /// Impulse, that is given to a thrown actor.
+    pub throw_impulse: f32,
+    /// Impulse, that is given to a shoved actor.
+    pub shove_impulse: f32,
+    /// Time (in seconds) between two shoves.
+    pub shove_cooldown_time: f32,
+    /// Time (in seconds) for which a shoved actor flies without turning into a ragdoll.
+    pub shove_flight_time: f32,
     #[reflect(hidden)]
     pub target_desired_velocity: Vector3<f32>,
     #[reflect(hidden)]
@@ -70,6 +326,108 @@ pub struct Actor {
     pub absm: Handle<AnimationBlendingStateMachine>,
     #[reflect(hidden)]
     pub jump_interval: f32,
+    /// Index of the participant of a tournament, that controls the actor.
+    #[reflect(hidden)]
+    pub participant: usize,
+    /// Spectators cannot move, and they're invisible for other actors.
+    #[reflect(hidden)]
+    pub spectator: bool,
+    /// Team of the actor, if the match is played in teams.
+    #[reflect(hidden)]
+    pub team: Option<usize>,
+    /// Power-ups, that were given to the actor by pickups.
+    #[reflect(hidden)]
+    pub effects: Vec<ActiveEffect>,
+    /// Velocity of a moving platform, that carries the actor.
+    #[reflect(hidden)]
+    pub platform_velocity: Vector3<f32>,
+    /// Velocity, that is gained from force volumes. It is added to the movement velocity, because
+    /// the movement overwrites horizontal velocity of the capsule every frame.
+    #[reflect(hidden)]
+    pub external_velocity: Vector3<f32>,
+    /// Names of the keys, that were collected by the actor.
+    #[reflect(hidden)]
+    pub keys: Vec<String>,
+    /// The actor uses a nearby interactable object while this timer is positive.
+    #[reflect(hidden)]
+    pub interaction_timer: f32,
+    /// The actor flies along a ballistic arc while this timer is positive.
+    #[reflect(hidden)]
+    pub launch_timer: f32,
+    /// A surface under the actor, it is updated every frame.
+    #[reflect(hidden)]
+    #[visit(skip)]
+    pub surface: Option<Surface>,
+    #[reflect(hidden)]
+    #[visit(skip)]
+    pub prev_vertical_velocity: f32,
+    /// The actor tries to grab (or keeps holding) another actor while this flag is set.
+    #[reflect(hidden)]
+    #[visit(skip)]
+    pub grab_requested: bool,
+    /// An actor, that is held by this actor.
+    #[reflect(hidden)]
+    #[visit(skip)]
+    pub grabbed: Handle<Node>,
+    /// An actor, that holds this actor.
+    #[reflect(hidden)]
+    #[visit(skip)]
+    pub grabbed_by: Handle<Node>,
+    #[reflect(hidden)]
+    #[visit(skip)]
+    pub grab_joint: Handle<Node>,
+    #[reflect(hidden)]
+    #[visit(skip)]
+    pub grab_cooldown: f32,
+    #[reflect(hidden)]
+    #[visit(skip)]
+    pub shove_requested: bool,
+    #[reflect(hidden)]
+    #[visit(skip)]
+    pub shove_cooldown: f32,
+    #[reflect(hidden)]
+    #[visit(skip)]
+    pub state: ActorState,
+    #[reflect(hidden)]
+    #[visit(skip)]
+    pub get_up_timer: f32,
+    /// Local rotations of the bones of the ragdoll at the moment it was disabled.
+    #[reflect(hidden)]
+    #[visit(skip)]
+    pub ragdoll_pose: Vec<(Handle<Node>, UnitQuaternion<f32>)>,
+    /// The ragdoll pose is blended with the animation while this timer is positive.
+    #[reflect(hidden)]
+    #[visit(skip)]
+    pub pose_blend_timer: f32,
+    /// The actor cannot be knocked down while this timer is positive.
+    #[reflect(hidden)]
+    #[visit(skip)]
+    pub knockdown_immunity_timer: f32,
+    /// It is used only for debugging.
+    #[reflect(hidden)]
+    #[visit(skip)]
+    pub last_impact: Option<Impact>,
+    /// Rigid bodies of the actor (the capsule and the ragdoll limbs), they're collected at start.
+    #[reflect(hidden)]
+    #[visit(skip)]
+    pub own_bodies: Vec<Handle<Node>>,
+    #[reflect(hidden)]
+    #[visit(skip)]
+    pub coyote_timer: f32,
+    #[reflect(hidden)]
+    #[visit(skip)]
+    pub jump_buffer_timer: f32,
+    #[reflect(hidden)]
+    #[visit(skip)]
+    pub dive_requested: bool,
+    /// The actor lunges forward in the air while this flag is set.
+    #[reflect(hidden)]
+    #[visit(skip)]
+    pub diving: bool,
+    /// The actor slides on its belly after a dive while this timer is positive.
+    #[reflect(hidden)]
+    #[visit(skip)]
+    pub slide_timer: f32,
     pub footsteps: InheritableVariable<Vec<Handle<Sound>>>,
     pub disappear_effect: InheritableVariable<Option<ModelResource>>,
     pub appear_effect: InheritableVariable<Option<ModelResource>>,
@@ -82,6 +440,8 @@ impl Default for Actor {
             kind: ActorKind::Player,
             in_air_time: 0.0,
             max_in_air_time: 1.1,
+            knockdown: Default::default(),
+            get_up: Default::default(),
             stand_up_timer: 0.0,
             stand_up_interval: 1.0,
             ragdoll: Default::default(),
@@ -90,10 +450,55 @@ impl Default for Actor {
             rigid_body: Default::default(),
             speed: 4.0,
             jump_vel: 6.5,
+            coyote_time: 0.12,
+            jump_buffer_time: 0.15,
+            air_control: 0.1,
+            air_drag: 0.02,
+            dive_speed: 8.0,
+            dive_recovery: 0.8,
+            dive_lift: 2.0,
+            slide_friction: 0.05,
+            grab_distance: 1.5,
+            grab_cooldown_time: 1.0,
+            grab_speed_factor: 0.6,
+            throw_impulse: 8.0,
+            shove_impulse: 6.0,
+            shove_cooldown_time: 1.5,
+            shove_flight_time: 0.3,
             target_desired_velocity: Default::default(),
             desired_velocity: Default::default(),
             absm: Default::default(),
             jump_interval: 0.0,
+            participant: 0,
+            spectator: false,
+            team: None,
+            effects: Default::default(),
+            platform_velocity: Default::default(),
+            external_velocity: Default::default(),
+            keys: Default::default(),
+            interaction_timer: 0.0,
+            launch_timer: 0.0,
+            surface: None,
+            prev_vertical_velocity: 0.0,
+            grab_requested: false,
+            grabbed: Default::default(),
+            grabbed_by: Default::default(),
+            grab_joint: Default::default(),
+            grab_cooldown: 0.0,
+            shove_requested: false,
+            shove_cooldown: 0.0,
+            state: Default::default(),
+            get_up_timer: 0.0,
+            ragdoll_pose: Default::default(),
+            pose_blend_timer: 0.0,
+            knockdown_immunity_timer: 0.0,
+            last_impact: None,
+            own_bodies: Default::default(),
+            coyote_timer: 0.0,
+            jump_buffer_timer: 0.0,
+            dive_requested: false,
+            diving: false,
+            slide_timer: 0.0,
             footsteps: Default::default(),
             disappear_effect: Default::default(),
             appear_effect: Default::default(),
@@ -123,6 +528,24 @@ impl Actor {
             || self.is_ragdoll_has_ground_contact(graph)?)
     }
 
+    /// Searches for a surface zone among the colliders, that the actor stands on.
+    pub fn ground_surface(&self, graph: &Graph) -> Result<Option<Surface>, GameError> {
+        for collider in utils::ground_contacts(self.collider, graph)? {
+            let Ok(collider_node) = graph.try_get(collider) else {
+                continue;
+            };
+            for handle in [collider.to_base(), collider_node.parent()] {
+                let Ok(node) = graph.try_get(handle) else {
+                    continue;
+                };
+                if let Some(zone) = node.try_get_script_component::<SurfaceZone>() {
+                    return Ok(Some(zone.surface(node.global_rotation())));
+                }
+            }
+        }
+        Ok(None)
+    }
+
     pub fn set_ragdoll_enabled(&mut self, graph: &mut Graph, enabled: bool) -> GameResult {
         graph
             .try_get_mut(self.ragdoll)?
@@ -131,10 +554,416 @@ impl Actor {
         Ok(())
     }
 
+    /// Starts the get-up sequence. The capsule is moved to the pelvis of the ragdoll, so the
+    /// animated pose appears where the ragdoll lies.
+    fn begin_get_up(&mut self, graph: &mut Graph) -> GameResult {
+        let ragdoll = graph.try_get(self.ragdoll)?;
+        let pelvis_position = graph
+            .try_get(ragdoll.root_limb.physical_bone)?
+            .global_position();
+
+        let mut ragdoll_pose = Vec::new();
+        ragdoll.root_limb.iterate_recursive(&mut |limb| {
+            let rotation = **graph.try_get(limb.bone)?.local_transform().rotation();
+            ragdoll_pose.push((limb.bone, rotation));
+            Ok(())
+        })?;
+        self.ragdoll_pose = ragdoll_pose;
+        self.pose_blend_timer = self.get_up.pose_blend_time;
+
+        self.set_ragdoll_enabled(graph, false)?;
+
+        let rigid_body = graph.try_get_mut(self.rigid_body)?;
+        rigid_body.set_lin_vel(Vector3::default());
+        rigid_body.set_ang_vel(Vector3::default());
+        rigid_body
+            .local_transform_mut()
+            .set_position(pelvis_position + Vector3::new(0.0, self.get_up.lift, 0.0));
+
+        Ok(())
+    }
+
+    /// Blends the remembered ragdoll pose with the animated pose, otherwise the actor snaps from
+    /// one pose to another.
+    fn blend_ragdoll_pose(&mut self, graph: &mut Graph, dt: f32) -> GameResult {
+        if self.pose_blend_timer <= 0.0 {
+            return Ok(());
+        }
+        self.pose_blend_timer -= dt;
+        let t = 1.0
+            - (self.pose_blend_timer / self.get_up.pose_blend_time.max(f32::EPSILON))
+                .clamp(0.0, 1.0);
+        for (bone, ragdoll_rotation) in self.ragdoll_pose.iter() {
+            let transform = graph.try_get_mut(*bone)?.local_transform_mut();
+            let animated_rotation = **transform.rotation();
+            transform.set_rotation(ragdoll_rotation.slerp(&animated_rotation, t));
+        }
+        Ok(())
+    }
+
+    pub fn is_getting_up(&self) -> bool {
+        self.state == ActorState::GettingUp
+    }
+
+    /// Turns the actor into a ragdoll. Stronger impacts make the actor lie on the ground longer.
+    pub fn knock_down(&mut self, graph: &mut Graph, strength: f32) -> GameResult {
+        self.set_ragdoll_enabled(graph, true)?;
+        self.stand_up_timer = 0.0;
+        self.stand_up_interval = self.knockdown.stun_time(strength);
+        Ok(())
+    }
+
+    pub fn is_knockdown_immune(&self) -> bool {
+        self.knockdown_immunity_timer > 0.0 || self.has_effect(PickupEffect::RagdollImmunity)
+    }
+
     pub fn is_ragdoll_enabled(&self, graph: &Graph) -> Result<bool, GameError> {
         Ok(*graph.try_get(self.ragdoll)?.is_active)
     }
 
+    /// Turns the actor into a ghost, that does not interact with anything on the level.
+    pub fn make_spectator(&mut self, graph: &mut Graph) -> GameResult {
+        self.spectator = true;
+        Self::make_ghost(self.rigid_body, self.ragdoll, graph)
+    }
+
+    /// Turns the bodies of an actor into sensors. It takes handles instead of the actor, so it
+    /// can be used while the actor's script is borrowed.
+    pub fn make_ghost(
+        rigid_body: Handle<RigidBody>,
+        ragdoll: Handle<Ragdoll>,
+        graph: &mut Graph,
+    ) -> GameResult {
+        graph
+            .try_get_mut(ragdoll)?
+            .is_active
+            .set_value_and_mark_modified(false);
+
+        let mut rigid_bodies = vec![rigid_body];
+        graph
+            .try_get(ragdoll)?
+            .root_limb
+            .iterate_recursive(&mut |limb| {
+                rigid_bodies.push(limb.physical_bone);
+                Ok(())
+            })?;
+
+        for rigid_body in rigid_bodies {
+            let rigid_body = graph.try_get_mut(rigid_body)?;
+            rigid_body.set_body_type(RigidBodyType::KinematicPositionBased);
+            for child in rigid_body.children().to_vec() {
+                if let Ok(collider) = graph.try_get_mut_of_type::<Collider>(child) {
+                    collider.set_is_sensor(true);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Paints every mesh in the given hierarchy in the color of the actor's team. Materials are
+    /// copied, so other instances of the same model are left untouched.
+    pub fn apply_team_color(&self, root: Handle<Node>, graph: &mut Graph) {
+        let Some(team) = self.team else {
+            return;
+        };
+        let color = team::team_color(team);
+
+        let meshes = graph
+            .traverse_handle_iter(root)
+            .filter(|h| graph[*h].is_mesh())
+            .collect::<Vec<_>>();
+        for handle in meshes {
+            let Ok(mesh) = graph.try_get_mut_of_type::<Mesh>(handle) else {
+                continue;
+            };
+            for surface in mesh.surfaces_mut() {
+                let mut material = surface.material().data_ref().clone();
+                material.set_property("diffuseColor", color);
+                surface.set_material(MaterialResource::new_embedded(material));
+            }
+        }
+    }
+
+    /// Gives the effect to the actor, the effect replaces the same effect, that could be given
+    /// earlier.
+    pub fn set_effect(&mut self, effect: PickupEffect, expires_at: f32) {
+        self.effects.retain(|e| e.effect != effect);
+        self.effects.push(ActiveEffect { effect, expires_at });
+    }
+
+    pub fn has_effect(&self, effect: PickupEffect) -> bool {
+        self.effects.iter().any(|e| e.effect == effect)
+    }
+
+    /// Running speed with respect to the active effects.
+    pub fn current_speed(&self) -> f32 {
+        let speed = if self.has_effect(PickupEffect::SpeedBoost) {
+            self.speed * PickupEffect::SPEED_BOOST_FACTOR
+        } else {
+            self.speed
+        };
+        if self.grabbed.is_some() || self.grabbed_by.is_some() {
+            speed * self.grab_speed_factor
+        } else {
+            speed
+        }
+    }
+
+    /// Jump speed with respect to the active effects.
+    pub fn current_jump_vel(&self) -> f32 {
+        if self.has_effect(PickupEffect::SuperJump) {
+            self.jump_vel * PickupEffect::SUPER_JUMP_FACTOR
+        } else {
+            self.jump_vel
+        }
+    }
+
+    /// Makes the actor to use an interactable object, if there's any nearby.
+    pub fn interact(&mut self) {
+        self.interaction_timer = 0.25;
+    }
+
+    pub fn wants_to_interact(&self) -> bool {
+        self.interaction_timer > 0.0
+    }
+
+    pub fn consume_interaction(&mut self) {
+        self.interaction_timer = 0.0;
+    }
+
+    /// Makes the actor to shove an actor in front of it, if there's any.
+    pub fn request_shove(&mut self) {
+        self.shove_requested = true;
+    }
+
+    /// Searches for the closest actor in front of this one within the grab distance. Returns a
+    /// handle of the actor and its rigid body, that is closest to this actor (a ragdoll limb, if
+    /// the ragdoll is active).
+    pub fn find_actor_in_front(
+        &self,
+        self_handle: Handle<Node>,
+        ctx: &ScriptContext,
+    ) -> Result<Option<(Handle<Node>, Handle<RigidBody>)>, GameError> {
+        let graph = &ctx.scene.graph;
+        let self_body = graph.try_get(self.rigid_body)?;
+        let self_position = self_body.global_position();
+        let look = self_body.look_vector();
+
+        let mut closest = None;
+        let mut closest_distance = self.grab_distance;
+        for actor_handle in ctx.plugins.get::<Game>().level.actors.iter() {
+            if *actor_handle == self_handle {
+                continue;
+            }
+            let actor = graph.try_get_script_component_of::<Actor>(*actor_handle)?;
+            if actor.spectator {
+                continue;
+            }
+
+            let mut bodies = vec![actor.rigid_body];
+            if actor.is_ragdoll_enabled(graph)? {
+                graph
+                    .try_get(actor.ragdoll)?
+                    .root_limb
+                    .iterate_recursive(&mut |limb| {
+                        bodies.push(limb.physical_bone);
+                        Ok(())
+                    })?;
+            }
+
+            for body in bodies {
+                let offset = graph.try_get(body)?.global_position() - self_position;
+                let distance = offset.norm();
+                let in_front = offset
+                    .try_normalize(f32::EPSILON)
+                    .is_some_and(|dir| dir.dot(&look) > 0.5);
+                if in_front && distance < closest_distance {
+                    closest_distance = distance;
+                    closest = Some((*actor_handle, body));
+                }
+            }
+        }
+        Ok(closest)
+    }
+
+    fn grab(
+        &mut self,
+        self_handle: Handle<Node>,
+        target: Handle<Node>,
+        body: Handle<RigidBody>,
+        ctx: &mut ScriptContext,
+    ) -> GameResult {
+        let graph = &mut ctx.scene.graph;
+        let position = graph.try_get(body)?.global_position();
+        self.grab_joint = JointBuilder::new(
+            BaseBuilder::new().with_local_transform(
+                TransformBuilder::new()
+                    .with_local_position(position)
+                    .build(),
+            ),
+        )
+        .with_params(JointParams::BallJoint(Default::default()))
+        .with_body1(self.rigid_body)
+        .with_body2(body)
+        .with_contacts_enabled(false)
+        .build(graph);
+        self.grabbed = target;
+
+        ctx.message_sender
+            .send_to_target(target, ActorMessage::Grabbed { by: self_handle });
+        Ok(())
+    }
+
+    /// Releases the grabbed actor and optionally throws it.
+    fn release(&mut self, throw: Option<Vector3<f32>>, ctx: &mut ScriptContext) -> GameResult {
+        if ctx.scene.graph.try_get(self.grab_joint).is_ok() {
+            ctx.scene.graph.remove_node(self.grab_joint);
+        }
+        if let Some(impulse) = throw {
+            ctx.message_sender.send_to_target(
+                self.grabbed,
+                ActorMessage::Impulse {
+                    impulse,
+                    knock_down: true,
+                },
+            );
+        }
+        ctx.message_sender
+            .send_to_target(self.grabbed, ActorMessage::Released);
+
+        self.grabbed = Handle::NONE;
+        self.grab_joint = Handle::NONE;
+        self.grab_cooldown = self.grab_cooldown_time;
+        Ok(())
+    }
+
+    fn update_grab(&mut self, ctx: &mut ScriptContext) -> GameResult {
+        self.grab_cooldown -= ctx.dt;
+
+        // The actor that held this one could be gone without releasing it.
+        if self.grabbed_by.is_some()
+            && ctx
+                .scene
+                .graph
+                .try_get_script_component_of::<Actor>(self.grabbed_by)
+                .map_or(true, |grabber| grabber.grabbed != ctx.handle)
+        {
+            self.grabbed_by = Handle::NONE;
+        }
+
+        if self.grabbed.is_some() {
+            let graph = &ctx.scene.graph;
+            let self_body = graph.try_get(self.rigid_body)?;
+            let self_position = self_body.global_position();
+            let look = self_body.look_vector();
+            let lost = match graph.try_get_script_component_of::<Actor>(self.grabbed) {
+                Ok(target) => {
+                    target.spectator
+                        || graph
+                            .try_get(target.rigid_body)?
+                            .global_position()
+                            .metric_distance(&self_position)
+                            > 2.0 * self.grab_distance
+                }
+                Err(_) => true,
+            };
+
+            if lost || self.is_ragdoll_enabled(graph)? {
+                self.release(None, ctx)?;
+            } else if !self.grab_requested {
+                // Throw the grabbed actor forward and up.
+                let direction = (look + Vector3::y())
+                    .try_normalize(f32::EPSILON)
+                    .unwrap_or_else(Vector3::y);
+                self.release(Some(direction.scale(self.throw_impulse)), ctx)?;
+            }
+        } else if self.grab_requested && self.grab_cooldown <= 0.0 {
+            if let Some((target, body)) = self.find_actor_in_front(ctx.handle, ctx)? {
+                self.grab(ctx.handle, target, body, ctx)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn update_shove(&mut self, ctx: &mut ScriptContext) -> GameResult {
+        self.shove_cooldown -= ctx.dt;
+
+        if !std::mem::take(&mut self.shove_requested)
+            || self.shove_cooldown > 0.0
+            || self.grabbed.is_some()
+        {
+            return Ok(());
+        }
+
+        self.shove_cooldown = self.shove_cooldown_time;
+
+        if let Some((target, _)) = self.find_actor_in_front(ctx.handle, ctx)? {
+            let look = ctx.scene.graph.try_get(self.rigid_body)?.look_vector();
+            let direction = (look + Vector3::y().scale(0.3))
+                .try_normalize(f32::EPSILON)
+                .unwrap_or(look);
+            ctx.message_sender.send_to_target(
+                target,
+                ActorMessage::Impulse {
+                    impulse: direction.scale(self.shove_impulse),
+                    knock_down: false,
+                },
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Makes the actor to dive forward, if it is in the air.
+    pub fn request_dive(&mut self) {
+        self.dive_requested = true;
+    }
+
+    /// Checks whether the actor is diving or sliding after a dive.
+    pub fn is_diving(&self) -> bool {
+        self.diving || self.slide_timer > 0.0
+    }
+
+    fn update_dive(&mut self, graph: &mut Graph, has_ground_contact: bool, dt: f32) -> GameResult {
+        if std::mem::take(&mut self.dive_requested)
+            && !self.is_diving()
+            && !has_ground_contact
+            && !self.is_ragdoll_enabled(graph)?
+        {
+            self.diving = true;
+
+            let rigid_body = graph.try_get_mut(self.rigid_body)?;
+            let lunge = rigid_body.look_vector().scale(self.dive_speed);
+            let y_vel = rigid_body.lin_vel().y.max(0.0) + self.dive_lift;
+            rigid_body.set_lin_vel(Vector3::new(lunge.x, y_vel, lunge.z));
+            self.desired_velocity = Vector3::new(lunge.x, 0.0, lunge.z);
+        }
+
+        if self.diving {
+            // A dive must not turn the actor into a ragdoll.
+            self.in_air_time = 0.0;
+            if has_ground_contact {
+                self.diving = false;
+                self.slide_timer = self.dive_recovery;
+            }
+        } else {
+            self.slide_timer -= dt;
+        }
+
+        Ok(())
+    }
+
+    /// Makes the actor to fly freely (without any control) for the given amount of time.
+    pub fn launch(&mut self, flight_time: f32) {
+        self.launch_timer = flight_time;
+    }
+
+    pub fn is_launched(&self) -> bool {
+        self.launch_timer > 0.0
+    }
+
     pub fn on_message(
         &mut self,
         message: &mut dyn ScriptMessagePayload,
@@ -157,6 +986,7 @@ impl Actor {
                 }
 
                 self.set_ragdoll_enabled(&mut ctx.scene.graph, false)?;
+                self.external_velocity = Vector3::default();
 
                 self.for_each_rigid_body(&mut ctx.scene.graph, |rb| {
                     rb.local_transform_mut().set_position(*position);
@@ -166,11 +996,90 @@ impl Actor {
                     appear_effect.instantiate_at(ctx.scene, *position, Default::default());
                 }
             }
+            ActorMessage::Eliminate => {
+                if let Some(disappear_effect) = self.disappear_effect.as_ref() {
+                    let current_position =
+                        ctx.scene.graph.try_get(self.rigid_body)?.global_position();
+                    disappear_effect.instantiate_at(
+                        ctx.scene,
+                        current_position,
+                        Default::default(),
+                    );
+                }
+
+                self.make_spectator(&mut ctx.scene.graph)?;
+                self.set_state(
+                    ActorState::Eliminated,
+                    ctx.handle,
+                    &ctx.plugins.get::<Game>().level,
+                );
+            }
+            ActorMessage::Explosion {
+                center,
+                radius,
+                impulse,
+            } => {
+                self.apply_radial_impulse(*center, *radius, *impulse, &mut ctx.scene.graph)?;
+            }
+            ActorMessage::Force { force, max_speed } => {
+                self.add_force(*force, *max_speed, ctx.dt, &mut ctx.scene.graph)?;
+            }
+            ActorMessage::Grabbed { by } => {
+                self.grabbed_by = *by;
+            }
+            ActorMessage::Released => {
+                self.grabbed_by = Handle::NONE;
+            }
+            ActorMessage::Impulse {
+                impulse,
+                knock_down,
+            } => {
+                if *knock_down && !self.is_knockdown_immune() {
+                    self.knock_down(&mut ctx.scene.graph, 1.0)?;
+                } else {
+                    // Do not let the movement code cancel a shove.
+                    self.launch(self.shove_flight_time);
+                }
+                self.for_each_rigid_body(&mut ctx.scene.graph, |rigid_body| {
+                    rigid_body.apply_impulse(*impulse);
+                })?;
+            }
         }
 
         Ok(())
     }
 
+    /// Returns the capsule and every limb of the ragdoll.
+    pub fn rigid_bodies(&self, graph: &Graph) -> Result<Vec<Handle<RigidBody>>, GameError> {
+        let mut rigid_bodies = vec![self.rigid_body];
+        graph
+            .try_get(self.ragdoll)?
+            .root_limb
+            .iterate_recursive(&mut |limb| {
+                rigid_bodies.push(limb.physical_bone);
+                Ok(())
+            })?;
+        Ok(rigid_bodies)
+    }
+
+    /// Remembers the rigid bodies of the actor and registers them on the level, so impacts with
+    /// other actors can be told apart without going over every actor each frame.
+    pub fn register_bodies(&mut self, graph: &Graph, level: &mut Level) -> GameResult {
+        self.own_bodies = self
+            .rigid_bodies(graph)?
+            .into_iter()
+            .map(|body| body.to_base())
+            .collect();
+        level.actor_bodies.extend(self.own_bodies.iter().cloned());
+        Ok(())
+    }
+
+    pub fn unregister_bodies(&self, level: &mut Level) {
+        for body in self.own_bodies.iter() {
+            level.actor_bodies.remove(body);
+        }
+    }
+
     pub fn for_each_rigid_body<F>(&mut self, graph: &mut Graph, mut func: F) -> GameResult
     where
         F: FnMut(&mut RigidBody),
@@ -189,28 +1098,87 @@ impl Actor {
         Ok(())
     }
 
+    /// Pushes every body of the actor away from the given point. The impulse falls off linearly
+    /// with distance and it is zero at the given radius.
+    pub fn apply_radial_impulse(
+        &mut self,
+        center: Vector3<f32>,
+        radius: f32,
+        impulse: f32,
+        graph: &mut Graph,
+    ) -> GameResult {
+        let position = graph.try_get(self.rigid_body)?.global_position();
+        if position.metric_distance(&center) >= radius {
+            return Ok(());
+        }
+
+        if !self.is_knockdown_immune() {
+            self.knock_down(graph, 1.0)?;
+        }
+
+        self.for_each_rigid_body(graph, |rigid_body| {
+            if let Some(impulse) =
+                explosive::radial_impulse(center, radius, impulse, rigid_body.global_position())
+            {
+                rigid_body.apply_impulse(impulse);
+            }
+        })
+    }
+
     pub fn set_velocity(&mut self, velocity: Vector3<f32>, graph: &mut Graph) -> GameResult {
         let rigid_body = graph.try_get_mut(self.rigid_body)?;
         let y_vel = rigid_body.lin_vel().y + velocity.y;
-        rigid_body.set_lin_vel(Vector3::new(velocity.x, y_vel, velocity.z));
+        let surface_velocity = match self.surface {
+            Some(Surface::Conveyor { velocity }) => velocity,
+            _ => Vector3::default(),
+        };
+        let external_velocity = self.platform_velocity + surface_velocity + self.external_velocity;
+        rigid_body.set_lin_vel(Vector3::new(
+            velocity.x + external_velocity.x,
+            y_vel,
+            velocity.z + external_velocity.z,
+        ));
         Ok(())
     }
 
+    /// Returns the body, that is pushed by forces: the pelvis of the ragdoll while it is active,
+    /// and the capsule otherwise.
+    pub fn force_body(&self, graph: &Graph) -> Result<Handle<RigidBody>, GameError> {
+        let ragdoll = graph.try_get(self.ragdoll)?;
+        Ok(if *ragdoll.is_active {
+            ragdoll.root_limb.physical_bone
+        } else {
+            self.rigid_body
+        })
+    }
+
     pub fn add_force(
         &mut self,
         force: Vector3<f32>,
         max_speed: f32,
+        dt: f32,
         graph: &mut Graph,
     ) -> GameResult {
-        self.for_each_rigid_body(graph, &mut |rigid_body: &mut RigidBody| {
-            if rigid_body.lin_vel().xz().norm() < max_speed {
-                rigid_body.apply_force(force);
-            }
-        })
+        let force_body = self.force_body(graph)?;
+        let rigid_body = graph.try_get_mut(force_body)?;
+        if rigid_body.lin_vel().xz().norm() >= max_speed {
+            return Ok(());
+        }
+        if force_body == self.rigid_body {
+            // Vertical velocity of the capsule is kept by the movement, so only the horizontal
+            // part of the force has to be accumulated.
+            let acceleration = force.scale(dt / rigid_body.mass().max(f32::EPSILON));
+            self.external_velocity.x += acceleration.x;
+            self.external_velocity.z += acceleration.z;
+            rigid_body.apply_force(Vector3::new(0.0, force.y, 0.0));
+        } else {
+            rigid_body.apply_force(force);
+        }
+        Ok(())
     }
 
     pub fn do_move(&mut self, velocity: Vector3<f32>, graph: &mut Graph) -> GameResult {
-        if !self.is_ragdoll_enabled(graph)? {
+        if !self.is_launched() && !self.is_ragdoll_enabled(graph)? {
             self.set_velocity(velocity, graph)?;
         }
         Ok(())
@@ -223,23 +1191,135 @@ impl Actor {
         }
     }
 
-    fn has_serious_impact(&mut self, ctx: &mut ScriptContext) -> Result<bool, GameError> {
+    /// Changes the state of the actor, if the transition is allowed, and notifies the listeners.
+    /// Returns `true` if the state has changed.
+    pub fn set_state(
+        &mut self,
+        state: ActorState,
+        self_handle: Handle<Node>,
+        level: &Level,
+    ) -> bool {
+        if !self.state.can_change_to(state) {
+            return false;
+        }
+        if state == ActorState::GettingUp {
+            self.get_up_timer = self.get_up.time;
+        } else if self.state == ActorState::GettingUp && state != ActorState::Ragdoll {
+            self.knockdown_immunity_timer = self.knockdown.immunity_time;
+        }
+        let from = self.state;
+        self.state = state;
+        level.publish_actor_state(ActorStateEvent {
+            actor: self_handle,
+            from,
+            to: state,
+        });
+        true
+    }
+
+    fn next_state(&self, has_ground_contact: bool, is_ragdoll: bool, finished: bool) -> ActorState {
+        if is_ragdoll {
+            ActorState::Ragdoll
+        } else if self.state == ActorState::Ragdoll
+            || (self.state == ActorState::GettingUp && self.get_up_timer > 0.0)
+        {
+            ActorState::GettingUp
+        } else if finished {
+            ActorState::Finished
+        } else if !has_ground_contact {
+            if self.prev_vertical_velocity > 0.0 {
+                ActorState::Jumping
+            } else {
+                ActorState::Falling
+            }
+        } else if self.desired_velocity.xz().norm() > 0.1 {
+            ActorState::Running
+        } else {
+            ActorState::Idle
+        }
+    }
+
+    /// Remembers a jump request for the jump buffer time.
+    pub fn request_jump(&mut self) {
+        self.jump_buffer_timer = self.jump_buffer_time;
+    }
+
+    /// Performs a buffered jump, if the actor stands on the ground or has left it just now.
+    /// Returns `true` if the actor has jumped.
+    pub fn try_jump(&mut self, graph: &mut Graph) -> Result<bool, GameError> {
+        if self.jump_buffer_timer <= 0.0
+            || self.coyote_timer <= 0.0
+            || self.jump_interval > 0.0
+            || self.is_diving()
+            || self.is_getting_up()
+            || self.is_in_jump_state(graph)?
+        {
+            return Ok(false);
+        }
+
+        self.jump_buffer_timer = 0.0;
+        self.coyote_timer = 0.0;
+        self.jump();
+
+        // Falling speed must not eat a late jump.
+        let rigid_body = graph.try_get_mut(self.rigid_body)?;
+        let lin_vel = rigid_body.lin_vel();
+        if lin_vel.y < 0.0 {
+            rigid_body.set_lin_vel(Vector3::new(lin_vel.x, 0.0, lin_vel.z));
+        }
+
+        Ok(true)
+    }
+
+    /// Returns the strongest impact of the current frame.
+    fn find_strongest_impact(&self, ctx: &ScriptContext) -> Result<Option<Impact>, GameError> {
         let graph = &ctx.scene.graph;
-        let collider = ctx.scene.graph.try_get(self.collider)?;
-        for contact in collider.contacts(&ctx.scene.graph.physics) {
-            if contact.has_any_active_contact {
-                for manifold in contact.manifolds.iter() {
-                    let rb1 = graph.try_get(manifold.rigid_body1)?;
-                    let rb2 = graph.try_get(manifold.rigid_body2)?;
-                    if (rb1.lin_vel() - rb2.lin_vel()).norm() > 10.0
-                        || manifold.points.iter().any(|p| p.impulse > 2.0)
-                    {
-                        return Ok(true);
-                    }
+        let level = &ctx.plugins.get::<Game>().level;
+        let collider = graph.try_get(self.collider)?;
+        let mut strongest: Option<Impact> = None;
+        for contact in collider.contacts(&graph.physics) {
+            if !contact.has_any_active_contact {
+                continue;
+            }
+            for manifold in contact.manifolds.iter() {
+                let rb1 = graph.try_get(manifold.rigid_body1)?;
+                let rb2 = graph.try_get(manifold.rigid_body2)?;
+                let other = if manifold.rigid_body1 == self.rigid_body.to_base() {
+                    manifold.rigid_body2
+                } else {
+                    manifold.rigid_body1
+                };
+                if self.own_bodies.contains(&other) {
+                    continue;
+                }
+                let source = if level.actor_bodies.contains(&other) {
+                    ImpactSource::Actor
+                } else if Hazard::is_hazard_body(other, graph) {
+                    ImpactSource::Hazard
+                } else {
+                    ImpactSource::World
+                };
+                let relative_speed = (rb1.lin_vel() - rb2.lin_vel()).norm();
+                let impulse = manifold
+                    .points
+                    .iter()
+                    .fold(0.0f32, |max, p| max.max(p.impulse));
+                let strength = self
+                    .knockdown
+                    .threshold(source)
+                    .strength(relative_speed, impulse);
+                if strongest.is_none_or(|impact| strength > impact.strength) {
+                    strongest = Some(Impact {
+                        relative_speed,
+                        impulse,
+                        source,
+                        strength,
+                        knocked_down: false,
+                    });
                 }
             }
         }
-        Ok(false)
+        Ok(strongest)
     }
 
     pub fn is_in_jump_state(&self, graph: &Graph) -> Result<bool, GameError> {
@@ -310,40 +1390,118 @@ impl Actor {
     }
 
     pub fn on_update(&mut self, ctx: &mut ScriptContext) -> GameResult {
+        if self.spectator {
+            return Ok(());
+        }
+
         let game = ctx.plugins.get::<Game>();
+        let match_time = game.level.match_time;
+        self.effects.retain(|e| e.expires_at > match_time);
+        let ragdoll_immune = game.debug_settings.disable_ragdoll || self.is_knockdown_immune();
+
+        if self.is_launched() {
+            // Long flights must not turn the actor into a ragdoll.
+            self.launch_timer -= ctx.dt;
+            self.in_air_time = 0.0;
+        }
+
         let has_ground_contact = self.has_ground_contact(&ctx.scene.graph)?;
         if has_ground_contact {
+            self.coyote_timer = self.coyote_time;
             self.in_air_time = 0.0;
             self.stand_up_timer += ctx.dt;
-            if self.stand_up_timer >= self.stand_up_interval {
-                self.set_ragdoll_enabled(&mut ctx.scene.graph, false)?;
+            if self.stand_up_timer >= self.stand_up_interval
+                && self.is_ragdoll_enabled(&ctx.scene.graph)?
+            {
+                self.begin_get_up(&mut ctx.scene.graph)?;
             }
         } else {
+            self.coyote_timer -= ctx.dt;
             self.in_air_time += ctx.dt;
             self.stand_up_timer = 0.0;
-            if !game.debug_settings.disable_ragdoll && self.in_air_time >= self.max_in_air_time {
-                self.set_ragdoll_enabled(&mut ctx.scene.graph, true)?;
+            if !ragdoll_immune
+                && self.in_air_time >= self.max_in_air_time
+                && !self.is_ragdoll_enabled(&ctx.scene.graph)?
+            {
+                self.knock_down(&mut ctx.scene.graph, 1.0)?;
             }
         }
         let finished = game.level.leaderboard.is_finished(ctx.handle);
-        if finished {
+        if finished || game.level.is_movement_locked() || self.is_getting_up() {
             // Stand still.
             self.target_desired_velocity.x = 0.0;
             self.target_desired_velocity.z = 0.0;
         }
 
-        if self.has_serious_impact(ctx)? {
-            self.in_air_time = 999.0;
+        if let Some(mut impact) = self.find_strongest_impact(ctx)? {
+            if impact.strength >= 1.0 {
+                // The shield protects only from cannon balls and other hazards.
+                let shielded =
+                    impact.source == ImpactSource::Hazard && self.has_effect(PickupEffect::Shield);
+                impact.knocked_down =
+                    !ragdoll_immune && !shielded && !self.is_ragdoll_enabled(&ctx.scene.graph)?;
+                if impact.knocked_down {
+                    self.knock_down(&mut ctx.scene.graph, impact.strength)?;
+                }
+                self.last_impact = Some(impact);
+            }
         }
 
-        let y_vel = self.target_desired_velocity.y;
-        self.desired_velocity.follow(
-            &self.target_desired_velocity,
-            if has_ground_contact { 0.2 } else { 0.1 },
-        );
+        self.update_grab(ctx)?;
+        self.update_shove(ctx)?;
+        self.update_dive(&mut ctx.scene.graph, has_ground_contact, ctx.dt)?;
+
+        self.surface = self.ground_surface(&ctx.scene.graph)?;
+
+        let mut target_desired_velocity = self.target_desired_velocity;
+        let mut grip = if has_ground_contact {
+            0.2
+        } else if target_desired_velocity.xz().norm() > f32::EPSILON {
+            self.air_control
+        } else {
+            // Keep momentum in the air.
+            self.air_drag
+        };
+        if self.diving {
+            // No steering during a lunge.
+            target_desired_velocity = self.desired_velocity;
+            target_desired_velocity.y = 0.0;
+        } else if self.slide_timer > 0.0 {
+            target_desired_velocity = Vector3::default();
+            grip = self.slide_friction;
+        }
+        match self.surface {
+            Some(Surface::Ice { grip: ice_grip }) => grip = ice_grip,
+            Some(Surface::Mud { speed_factor }) => {
+                target_desired_velocity.x *= speed_factor;
+                target_desired_velocity.z *= speed_factor;
+            }
+            Some(Surface::Bounce {
+                restitution,
+                min_speed,
+            }) => {
+                let bounce_speed = -self.prev_vertical_velocity * restitution;
+                if bounce_speed >= min_speed {
+                    let rigid_body = ctx.scene.graph.try_get_mut(self.rigid_body)?;
+                    let lin_vel = rigid_body.lin_vel();
+                    rigid_body.set_lin_vel(Vector3::new(lin_vel.x, bounce_speed, lin_vel.z));
+                }
+            }
+            Some(Surface::Conveyor { .. }) | None => (),
+        }
+
+        let y_vel = target_desired_velocity.y;
+        self.desired_velocity.follow(&target_desired_velocity, grip);
         self.desired_velocity.y = y_vel;
 
         self.do_move(self.desired_velocity, &mut ctx.scene.graph)?;
+        // Force volumes push the actor again on the next frame, so the velocity gained from them
+        // fades out only outside of the volumes.
+        self.external_velocity.follow(&Vector3::default(), grip);
+
+        // Falling speed is remembered before the physics step, because ground contact stops the
+        // body.
+        self.prev_vertical_velocity = ctx.scene.graph.try_get(self.rigid_body)?.lin_vel().y;
 
         ctx.scene
             .graph
@@ -352,15 +1510,188 @@ impl Actor {
             .get_value_mut_silent()
             .set_parameter(
                 "Run",
-                Parameter::Rule(self.desired_velocity.xz().norm() >= 0.75 * self.speed),
+                Parameter::Rule(self.desired_velocity.xz().norm() >= 0.75 * self.current_speed()),
             )
             .set_parameter("Jump", Parameter::Rule(self.jump));
 
+        self.blend_ragdoll_pose(&mut ctx.scene.graph, ctx.dt)?;
         self.process_animation_events(ctx, has_ground_contact)?;
 
+        let next_state = self.next_state(
+            has_ground_contact,
+            self.is_ragdoll_enabled(&ctx.scene.graph)?,
+            finished,
+        );
+        self.set_state(next_state, ctx.handle, &ctx.plugins.get::<Game>().level);
+        self.get_up_timer -= ctx.dt;
+        self.knockdown_immunity_timer -= ctx.dt;
+
         self.jump_interval -= ctx.dt;
+        self.jump_buffer_timer -= ctx.dt;
+        self.interaction_timer -= ctx.dt;
 
         self.jump = false;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn event(from: ActorState, to: ActorState) -> ActorStateEvent {
+        ActorStateEvent {
+            actor: Handle::new(1, 1),
+            from,
+            to,
+        }
+    }
+
+    #[test]
+    fn movement_states_are_not_replicated() {
+        use ActorState::*;
+        assert!(!event(Idle, Running).is_replicated());
+        assert!(!event(Running, Jumping).is_replicated());
+        assert!(!event(Jumping, Falling).is_replicated());
+        assert!(!event(Running, Finished).is_replicated());
+    }
+
+    #[test]
+    fn knockdowns_and_eliminations_are_replicated() {
+        use ActorState::*;
+        assert!(event(Running, Ragdoll).is_replicated());
+        assert!(event(Ragdoll, GettingUp).is_replicated());
+        // Clients must know, when the actor can move again.
+        assert!(event(GettingUp, Idle).is_replicated());
+        assert!(event(Falling, Eliminated).is_replicated());
+        assert!(event(Finished, Spectating).is_replicated());
+    }
+
+    const ALL_STATES: [ActorState; 9] = [
+        ActorState::Idle,
+        ActorState::Running,
+        ActorState::Jumping,
+        ActorState::Falling,
+        ActorState::Ragdoll,
+        ActorState::GettingUp,
+        ActorState::Finished,
+        ActorState::Eliminated,
+        ActorState::Spectating,
+    ];
+
+    /// Returns every state, that the given state can change to.
+    fn transitions(from: ActorState) -> Vec<ActorState> {
+        ALL_STATES
+            .into_iter()
+            .filter(|to| from.can_change_to(*to))
+            .collect()
+    }
+
+    #[test]
+    fn movement_states_change_freely() {
+        use ActorState::*;
+        for from in [Idle, Running, Jumping, Falling] {
+            let expected = [
+                Idle, Running, Jumping, Falling, Ragdoll, Finished, Eliminated, Spectating,
+            ]
+            .into_iter()
+            .filter(|to| *to != from)
+            .collect::<Vec<_>>();
+            assert_eq!(transitions(from), expected, "from {from:?}");
+        }
+    }
+
+    #[test]
+    fn ragdoll_must_get_up() {
+        use ActorState::*;
+        assert_eq!(transitions(Ragdoll), [GettingUp, Eliminated, Spectating]);
+        // Getting up could be interrupted by another knockdown.
+        assert_eq!(
+            transitions(GettingUp),
+            [Idle, Running, Jumping, Falling, Ragdoll, Finished, Eliminated, Spectating]
+        );
+        // Only a ragdoll gets up.
+        for from in [Idle, Running, Jumping, Falling, Finished] {
+            assert!(!from.can_change_to(GettingUp), "from {from:?}");
+        }
+    }
+
+    #[test]
+    fn finished_actor_can_be_knocked_down() {
+        use ActorState::*;
+        assert_eq!(transitions(Finished), [Ragdoll, Eliminated, Spectating]);
+    }
+
+    #[test]
+    fn elimination_is_terminal() {
+        use ActorState::*;
+        for from in ALL_STATES {
+            if from != Eliminated && from != Spectating {
+                assert!(from.can_change_to(Eliminated), "from {from:?}");
+                assert!(from.can_change_to(Spectating), "from {from:?}");
+            }
+        }
+        assert!(transitions(Eliminated).is_empty());
+        assert!(transitions(Spectating).is_empty());
+    }
+
+    #[test]
+    fn finished_actor_gets_up_before_finishing() {
+        let mut actor = Actor {
+            state: ActorState::Ragdoll,
+            ..Default::default()
+        };
+        assert_eq!(actor.next_state(true, true, true), ActorState::Ragdoll);
+        assert_eq!(actor.next_state(true, false, true), ActorState::GettingUp);
+
+        actor.state = ActorState::GettingUp;
+        actor.get_up_timer = 0.1;
+        assert_eq!(actor.next_state(true, false, true), ActorState::GettingUp);
+        actor.get_up_timer = 0.0;
+        assert_eq!(actor.next_state(true, false, true), ActorState::Finished);
+        assert_eq!(actor.next_state(true, false, false), ActorState::Idle);
+    }
+
+    #[test]
+    fn impact_sources_have_own_thresholds() {
+        let profile = KnockdownProfile::default();
+        let strength = |source| profile.threshold(source).strength(11.0, 1.0);
+        // The same hit knocks down on a hazard, but not on the floor or by another actor.
+        assert!(strength(ImpactSource::Hazard) >= 1.0);
+        assert!(strength(ImpactSource::World) < 1.0);
+        assert!(strength(ImpactSource::Actor) < 1.0);
+    }
+
+    #[test]
+    fn stun_time_is_clamped() {
+        let profile = KnockdownProfile::default();
+        // Weakest knockdowns still stun for the base time.
+        assert_eq!(profile.stun_time(0.5), 1.0);
+        assert_eq!(profile.stun_time(1.0), 1.0);
+        assert_eq!(profile.stun_time(3.0), 2.0);
+        assert_eq!(profile.stun_time(10.0), 3.0);
+    }
+
+    #[test]
+    fn actor_is_immune_after_getting_up() {
+        let level = Level::default();
+        let mut actor = Actor {
+            state: ActorState::GettingUp,
+            ..Default::default()
+        };
+        assert!(!actor.is_knockdown_immune());
+        assert!(actor.set_state(ActorState::Idle, Handle::new(1, 1), &level));
+        assert_eq!(
+            actor.knockdown_immunity_timer,
+            actor.knockdown.immunity_time
+        );
+        assert!(actor.is_knockdown_immune());
+        actor.knockdown_immunity_timer = 0.0;
+        assert!(!actor.is_knockdown_immune());
+
+        // Another knockdown while getting up gives no immunity.
+        actor.state = ActorState::GettingUp;
+        assert!(actor.set_state(ActorState::Ragdoll, Handle::new(1, 1), &level));
+        assert!(!actor.is_knockdown_immune());
+    }
+}
@@ -2,13 +2,17 @@
 
 use crate::{
     actor::{Actor, ActorKind, ActorMessage},
+    crumble::CrumblePlatform,
+    force_volume::ForceVolume,
+    interactable::Interactable,
+    jumper::Jumper,
     respawn::Respawner,
-    utils, Game,
+    Game,
 };
 use fyrox::plugin::error::{GameError, GameResult};
 use fyrox::{
     core::{
-        algebra::{Matrix4, Point3, UnitQuaternion, Vector3},
+        algebra::{Matrix4, Point3, UnitQuaternion, Vector2, Vector3},
         arrayvec::ArrayVec,
         color::Color,
         log::Log,
@@ -21,6 +25,7 @@ use fyrox::{
         visitor::prelude::*,
     },
     graph::SceneGraph,
+    rand::{thread_rng, Rng},
     scene::{
         collider::{Collider, ColliderShape},
         debug::Line,
@@ -95,6 +100,11 @@ pub struct Bot {
     target_orientation: UnitQuaternion<f32>,
     #[reflect(hidden)]
     orientation: UnitQuaternion<f32>,
+    /// How often (attacks per second) the bot grabs or shoves actors in front of it.
+    aggression: f32,
+    #[reflect(hidden)]
+    #[visit(skip)]
+    grab_timer: f32,
 }
 
 impl Default for Bot {
@@ -115,6 +125,8 @@ impl Default for Bot {
             backwards_movement_timer: 0.0,
             target_orientation: Default::default(),
             orientation: Default::default(),
+            aggression: 0.3,
+            grab_timer: 0.0,
         }
     }
 }
@@ -142,6 +154,18 @@ fn probe_ground(begin: Vector3<f32>, max_height: f32, graph: &Graph) -> Option<V
 
     for intersection in buffer {
         if let Ok(collider) = graph.try_get(intersection.collider) {
+            // Tiles that are about to fall are not the ground anymore.
+            let tile = [intersection.collider.to_base(), collider.parent()]
+                .into_iter()
+                .filter_map(|handle| graph.try_get(handle).ok())
+                .find_map(|node| node.try_get_script_component::<CrumblePlatform>());
+            if let Some(tile) = tile {
+                if tile.is_safe() {
+                    return Some(intersection.position.coords);
+                }
+                continue;
+            }
+
             if let ColliderShape::Trimesh(_) = collider.shape() {
                 return Some(intersection.position.coords);
             }
@@ -248,6 +272,83 @@ impl Bot {
         }
     }
 
+    /// Grabs or shoves actors in front of the bot. A grabbed actor is thrown after a short time.
+    fn attack(&mut self, ctx: &ScriptContext) -> GameResult {
+        if self.actor.grabbed.is_some() {
+            self.grab_timer -= ctx.dt;
+            self.actor.grab_requested = self.grab_timer > 0.0;
+            return Ok(());
+        }
+        self.actor.grab_requested = false;
+
+        if self.aggression <= 0.0
+            || ctx.plugins.get::<Game>().level.is_movement_locked()
+            || self.actor.find_actor_in_front(ctx.handle, ctx)?.is_none()
+        {
+            return Ok(());
+        }
+
+        let mut rng = thread_rng();
+        if rng.gen_bool((self.aggression * ctx.dt).clamp(0.0, 1.0) as f64) {
+            if rng.gen_bool(0.5) {
+                self.actor.grab_requested = true;
+                self.grab_timer = rng.gen_range(0.5..1.5);
+            } else {
+                self.actor.request_shove();
+            }
+        }
+        Ok(())
+    }
+
+    /// Pulls nearby levers and opens doors with collected keys. Bots do not turn anything off.
+    fn use_interactables(&mut self, ctx: &ScriptContext) -> GameResult {
+        let graph = &ctx.scene.graph;
+        let self_position = graph.try_get(self.actor.rigid_body)?.global_position();
+        for interactable in ctx.plugins.get::<Game>().level.interactables.iter() {
+            let interactable_position = graph.try_get(*interactable)?.global_position();
+            let interactable = graph.try_get_script_component_of::<Interactable>(*interactable)?;
+            if !interactable.is_active()
+                && interactable.can_be_used_by(&self.actor)
+                && self_position.metric_distance(&interactable_position)
+                    <= interactable.interaction_radius()
+            {
+                self.actor.interact();
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Searches for the closest launch pad, that sends the bot closer to the given target.
+    fn find_launch_pad(
+        &self,
+        ctx: &ScriptContext,
+        target: Vector3<f32>,
+    ) -> Result<Option<Vector3<f32>>, GameError> {
+        let graph = &ctx.scene.graph;
+        let self_position = graph.try_get(self.actor.rigid_body)?.global_position();
+        let distance_to_target = self_position.metric_distance(&target);
+
+        let mut closest = None;
+        let mut closest_distance = f32::MAX;
+        for launch_pad in ctx.plugins.get::<Game>().level.launch_pads.iter() {
+            let jumper = graph.try_get_script_component_of::<Jumper>(*launch_pad)?;
+            let Ok(landing_point) = graph.try_get(jumper.landing_point()) else {
+                continue;
+            };
+            if landing_point.global_position().metric_distance(&target) >= distance_to_target {
+                continue;
+            }
+            let position = graph.try_get(*launch_pad)?.global_position();
+            let distance = position.metric_distance(&self_position);
+            if distance < closest_distance {
+                closest_distance = distance;
+                closest = Some(position);
+            }
+        }
+        Ok(closest)
+    }
+
     // Checks if there are a gap on the way, that can be jumped over.
     fn gap_test(&self, ctx: &ScriptContext) -> Result<GapTestResult, GameError> {
         let graph = &ctx.scene.graph;
@@ -255,6 +356,17 @@ impl Bot {
         let begin = graph.try_get(self.probe_begin)?.global_position();
         let end = graph.try_get(self.probe_end)?.global_position();
 
+        let game = ctx.plugins.get::<Game>();
+
+        // Launch pads are safe to step on, even if there's a gap under the pad.
+        for launch_pad in game.level.launch_pads.iter() {
+            let position = graph.try_get(*launch_pad)?.global_position();
+            if Vector2::new(position.x - end.x, position.z - end.z).norm() < 1.5 {
+                self.debug_data.add_line(end, position, Color::GREEN);
+                return Ok(GapTestResult::Run);
+            }
+        }
+
         let middle = (begin + end).scale(0.5);
 
         let max_height = 20.0;
@@ -277,10 +389,24 @@ impl Bot {
         if is_safe_height_difference(end, max_height, graph, |p| {
             self.debug_data.add_line(end, p, Color::ORANGE);
         }) {
-            Ok(GapTestResult::JumpOver)
-        } else {
-            Ok(GapTestResult::Stop)
+            return Ok(GapTestResult::JumpOver);
         }
+
+        // An updraft over the gap carries the bot, so it can jump into it.
+        for force_volume in game.level.force_volumes.iter() {
+            let node = graph.try_get(*force_volume)?;
+            let Some(volume) = node.try_get_script_component::<ForceVolume>() else {
+                continue;
+            };
+            if volume.is_active(game.level.match_time)
+                && volume.lifts(node)
+                && ForceVolume::bounds(node).is_contains_point(end)
+            {
+                return Ok(GapTestResult::JumpOver);
+            }
+        }
+
+        Ok(GapTestResult::Stop)
     }
 
     fn is_any_obstacle_in_front(&self, ctx: &ScriptContext) -> Result<bool, GameError> {
@@ -348,15 +474,18 @@ impl ScriptTrait for Bot {
         self.agent
             .set_position(ctx.scene.graph[ctx.handle].global_position());
 
+        self.actor
+            .apply_team_color(ctx.handle, &mut ctx.scene.graph);
+        self.actor
+            .register_bodies(&ctx.scene.graph, &mut ctx.plugins.get_mut::<Game>().level)?;
+
         Ok(())
     }
 
     fn on_deinit(&mut self, ctx: &mut ScriptDeinitContext) -> GameResult {
-        ctx.plugins
-            .get_mut::<Game>()
-            .level
-            .actors
-            .remove(&ctx.node_handle);
+        let level = &mut ctx.plugins.get_mut::<Game>().level;
+        level.actors.remove(&ctx.node_handle);
+        self.actor.unregister_bodies(level);
         Log::info(format!("Bot {:?} destroyed!", ctx.node_handle));
 
         Ok(())
@@ -366,14 +495,12 @@ impl ScriptTrait for Bot {
         self.debug_data.clear();
 
         let game = ctx.plugins.get::<Game>();
-        if game.is_client() {
+        if game.is_client() || self.actor.spectator {
             return Ok(());
         }
 
-        let is_in_jump_state = self.actor.is_in_jump_state(&ctx.scene.graph)?;
-
         // Dead-simple AI - run straight to target.
-        let target_pos = game
+        let mut target_pos = game
             .level
             .targets
             .iter()
@@ -381,9 +508,20 @@ impl ScriptTrait for Bot {
             .cloned()
             .map(|t| ctx.scene.graph[t].global_position());
 
-        let gap_test_result = self.gap_test(ctx)?;
+        let mut gap_test_result = self.gap_test(ctx)?;
+
+        // A gap, that cannot be jumped over, could be crossed using a launch pad.
+        if gap_test_result == GapTestResult::Stop {
+            if let Some(launch_pad) = target_pos
+                .map(|target| self.find_launch_pad(ctx, target))
+                .transpose()?
+                .flatten()
+            {
+                target_pos = Some(launch_pad);
+                gap_test_result = GapTestResult::Run;
+            }
+        }
         let is_any_obstacle_in_front = self.is_any_obstacle_in_front(ctx)?;
-        let has_ground_contact = utils::has_ground_contact(self.actor.collider, &ctx.scene.graph)?;
 
         self.actor.target_desired_velocity = Vector3::new(0.0, 0.0, 0.0);
 
@@ -391,12 +529,12 @@ impl ScriptTrait for Bot {
             self.backwards_movement_timer = 0.1;
         }
 
-        let speed = if gap_test_result == GapTestResult::Stop {
+        let speed = if gap_test_result == GapTestResult::Stop || game.level.is_movement_locked() {
             0.0
         } else if self.backwards_movement_timer > 0.0 {
-            -self.actor.speed
+            -self.actor.current_speed()
         } else {
-            self.actor.speed
+            self.actor.current_speed()
         };
 
         self.backwards_movement_timer -= ctx.dt;
@@ -409,9 +547,9 @@ impl ScriptTrait for Bot {
             if let Some(navmesh) = self.navmesh.as_ref() {
                 let navmesh = navmesh.read();
                 let agent_speed = if self.backwards_movement_timer > 0.0 {
-                    -self.actor.speed
+                    -self.actor.current_speed()
                 } else {
-                    self.actor.speed
+                    self.actor.current_speed()
                 };
                 self.agent.set_speed(agent_speed);
                 self.agent.set_target(target_pos);
@@ -438,14 +576,13 @@ impl ScriptTrait for Bot {
                 vel
             };
 
+            if speed != 0.0 && gap_test_result == GapTestResult::JumpOver {
+                self.actor.request_jump();
+            }
+
             let mut jump_y_vel = 0.0;
-            if has_ground_contact
-                && !is_in_jump_state
-                && self.actor.jump_interval <= 0.0
-                && gap_test_result == GapTestResult::JumpOver
-            {
-                self.actor.jump();
-                jump_y_vel = self.actor.jump_vel;
+            if self.actor.try_jump(&mut ctx.scene.graph)? {
+                jump_y_vel = self.actor.current_jump_vel();
             }
 
             self.actor.target_desired_velocity =
@@ -467,6 +604,9 @@ impl ScriptTrait for Bot {
                 .set_rotation(self.orientation);
         }
 
+        self.use_interactables(ctx)?;
+        self.attack(ctx)?;
+
         self.actor.on_update(ctx)?;
 
         self.debug_draw(ctx);
@@ -487,6 +627,14 @@ impl ScriptTrait for Bot {
             ActorMessage::RespawnAt(position) => {
                 self.agent.set_position(*position);
             }
+            ActorMessage::Eliminate => {
+                ctx.scene.graph[ctx.handle].set_visibility(false);
+            }
+            ActorMessage::Explosion { .. }
+            | ActorMessage::Force { .. }
+            | ActorMessage::Grabbed { .. }
+            | ActorMessage::Released
+            | ActorMessage::Impulse { .. } => (),
         }
 
         Ok(())
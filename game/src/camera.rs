@@ -9,6 +9,7 @@ use fyrox::{
     core::{
         algebra::{Point3, Vector3},
         arrayvec::ArrayVec,
+        math::Rect,
         pool::Handle,
         reflect::prelude::*,
         type_traits::prelude::*,
@@ -56,6 +57,21 @@ impl Default for CameraController {
     }
 }
 
+/// Returns a viewport (in normalized screen coordinates) of a local player. Two players split the
+/// screen horizontally, three and four players get a quarter of the screen each.
+pub fn split_screen_viewport(local_player: usize, local_players: usize) -> Rect<f32> {
+    match local_players {
+        0 | 1 => Rect::new(0.0, 0.0, 1.0, 1.0),
+        2 => Rect::new(0.0, 0.5 * local_player.min(1) as f32, 1.0, 0.5),
+        _ => Rect::new(
+            0.5 * (local_player % 2) as f32,
+            0.5 * (local_player.min(3) / 2) as f32,
+            0.5,
+            0.5,
+        ),
+    }
+}
+
 impl CameraController {
     fn check_for_obstacles(
         &self,
@@ -132,3 +148,39 @@ impl ScriptTrait for CameraController {
         )
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn single_player_takes_whole_screen() {
+        assert_eq!(split_screen_viewport(0, 0), Rect::new(0.0, 0.0, 1.0, 1.0));
+        assert_eq!(split_screen_viewport(0, 1), Rect::new(0.0, 0.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn two_players_split_screen_horizontally() {
+        assert_eq!(split_screen_viewport(0, 2), Rect::new(0.0, 0.0, 1.0, 0.5));
+        assert_eq!(split_screen_viewport(1, 2), Rect::new(0.0, 0.5, 1.0, 0.5));
+    }
+
+    #[test]
+    fn three_and_four_players_get_quarters() {
+        let quarters = [
+            Rect::new(0.0, 0.0, 0.5, 0.5),
+            Rect::new(0.5, 0.0, 0.5, 0.5),
+            Rect::new(0.0, 0.5, 0.5, 0.5),
+            Rect::new(0.5, 0.5, 0.5, 0.5),
+        ];
+        for local_players in [3, 4] {
+            for (local_player, viewport) in quarters.iter().take(local_players).enumerate() {
+                assert_eq!(
+                    split_screen_viewport(local_player, local_players),
+                    *viewport,
+                    "player {local_player} of {local_players}"
+                );
+            }
+        }
+    }
+}
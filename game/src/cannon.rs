@@ -1,20 +1,61 @@
 //! Cannon shoots large balls that push players (or bots) off the platforms.
 
 use crate::{
+    actor::Actor,
+    interactable::SignalMessage,
     net::{InstanceDescriptor, ServerMessage},
     Game,
 };
 use fyrox::core::math::vector_to_quat;
-use fyrox::plugin::error::GameResult;
+use fyrox::graph::SceneGraph;
+use fyrox::plugin::error::{GameError, GameResult};
 use fyrox::{
     core::{
-        pool::Handle, reflect::prelude::*, type_traits::prelude::*, variable::InheritableVariable,
+        algebra::{Unit, UnitQuaternion, Vector3},
+        pool::Handle,
+        reflect::prelude::*,
+        type_traits::prelude::*,
+        variable::InheritableVariable,
         visitor::prelude::*,
     },
+    rand::{thread_rng, Rng},
     resource::model::{ModelResource, ModelResourceExtension},
-    scene::{animation::AnimationPlayer, sound::Sound},
-    script::{ScriptContext, ScriptTrait},
+    scene::{
+        animation::AnimationPlayer, base::SceneNodeId, graph::Graph, node::Node, sound::Sound,
+    },
+    script::{ScriptContext, ScriptMessageContext, ScriptMessagePayload, ScriptTrait},
 };
+use std::{collections::VecDeque, ops::Range};
+use strum_macros::{AsRefStr, EnumString, VariantNames};
+
+#[derive(
+    Default,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Visit,
+    Reflect,
+    TypeUuidProvider,
+    AsRefStr,
+    EnumString,
+    VariantNames,
+)]
+#[type_uuid(id = "f1c3e5a7-2b4d-4f6e-8a9c-0d2e4f6a8b1c")]
+pub enum FiringMode {
+    /// The cannon shoots when the first animation of its animation player emits "Shoot" event.
+    #[default]
+    Animation,
+    /// The cannon shoots once per interval.
+    FixedInterval,
+    /// The cannon shoots a series of balls once per interval.
+    Burst,
+    /// The cannon shoots at random intervals from the interval range.
+    RandomInterval,
+    /// The cannon turns its turret to the nearest actor within its cone and shoots once per
+    /// interval.
+    Tracking,
+}
 
 #[derive(Clone, Debug, Visit, Reflect, TypeUuidProvider, ComponentProvider)]
 #[type_uuid(id = "becf5c5f-c745-40ee-85c9-491656fd222e")]
@@ -25,6 +66,57 @@ pub struct Cannon {
     shooting_force: InheritableVariable<f32>,
     shot_sound: InheritableVariable<Handle<Sound>>,
     animation_player: InheritableVariable<Handle<AnimationPlayer>>,
+    /// Disabled cannons do not shoot. The state can be changed by a signal.
+    enabled: InheritableVariable<bool>,
+    firing_mode: InheritableVariable<FiringMode>,
+    /// Time (in seconds) between shots (or bursts).
+    interval: InheritableVariable<f32>,
+    /// Range of intervals for the random interval mode.
+    interval_range: InheritableVariable<Range<f32>>,
+    /// Amount of shots in a burst.
+    burst_count: InheritableVariable<u32>,
+    /// Time (in seconds) between shots in a burst.
+    burst_delay: InheritableVariable<f32>,
+    /// Max angle (in degrees) of random deviation of shots.
+    spread: InheritableVariable<f32>,
+    /// Mass of balls. Zero means that the mass of the ball prefab will be used.
+    ball_mass: InheritableVariable<f32>,
+    /// Max amount of balls, that could exist at the same time. The oldest balls are removed
+    /// when the limit is reached. Zero means that there's no limit.
+    max_live_balls: InheritableVariable<usize>,
+    /// A node that turns to a target in the tracking mode, balls are shot along its look vector.
+    /// Without a turret, the cannon shoots straight when there's a target in the cone.
+    turret: InheritableVariable<Handle<Node>>,
+    /// Max distance to a target in the tracking mode.
+    tracking_range: InheritableVariable<f32>,
+    /// Half-angle (in degrees) of the tracking cone around the look vector of the cannon.
+    tracking_cone: InheritableVariable<f32>,
+    /// Turret turn speed (in degrees per second).
+    turn_speed: InheritableVariable<f32>,
+    #[reflect(hidden)]
+    shot_timer: f32,
+    #[reflect(hidden)]
+    burst_shots_left: u32,
+    #[reflect(hidden)]
+    #[visit(skip)]
+    live_balls: VecDeque<SceneNodeId>,
+}
+
+/// Rotates the direction by the given angles (in radians) around two axes, that are
+/// perpendicular to the direction, so the deviation does not depend on where the direction points.
+fn deviate(direction: Vector3<f32>, horizontal: f32, vertical: f32) -> Vector3<f32> {
+    let up = if direction.y.abs() < 0.99 {
+        Vector3::y()
+    } else {
+        Vector3::x()
+    };
+    let Some(side) = direction.cross(&up).try_normalize(f32::EPSILON) else {
+        return direction;
+    };
+    let up = side.cross(&direction);
+    let rotation = UnitQuaternion::from_axis_angle(&Unit::new_normalize(up), horizontal)
+        * UnitQuaternion::from_axis_angle(&Unit::new_unchecked(side), vertical);
+    rotation * direction
 }
 
 impl Default for Cannon {
@@ -35,66 +127,385 @@ impl Default for Cannon {
             shooting_force: 100.0.into(),
             shot_sound: Default::default(),
             animation_player: Default::default(),
+            enabled: true.into(),
+            firing_mode: Default::default(),
+            interval: 3.0.into(),
+            interval_range: (1.0..5.0).into(),
+            burst_count: 3.into(),
+            burst_delay: 0.25.into(),
+            spread: 0.0.into(),
+            ball_mass: 0.0.into(),
+            max_live_balls: 0.into(),
+            turret: Default::default(),
+            tracking_range: 30.0.into(),
+            tracking_cone: 45.0.into(),
+            turn_speed: 90.0.into(),
+            shot_timer: 0.0,
+            burst_shots_left: 0,
+            live_balls: Default::default(),
         }
     }
 }
 
-impl ScriptTrait for Cannon {
-    fn on_update(&mut self, ctx: &mut ScriptContext) -> GameResult {
-        let game = ctx.plugins.get_mut::<Game>();
-        if game.is_client() {
-            return Ok(());
+impl Cannon {
+    /// A node, that defines the position and the direction of shots.
+    fn muzzle(&self, handle: Handle<Node>) -> Handle<Node> {
+        if self.turret.is_some() {
+            *self.turret
+        } else {
+            handle
+        }
+    }
+
+    fn next_interval(&self) -> f32 {
+        match *self.firing_mode {
+            FiringMode::RandomInterval => {
+                let range = &*self.interval_range;
+                if range.start < range.end {
+                    thread_rng().gen_range(range.clone())
+                } else {
+                    range.start
+                }
+            }
+            _ => *self.interval,
+        }
+    }
+
+    /// Returns amount of shots, that must be done on this frame.
+    fn shots_from_animation(&self, graph: &mut Graph) -> u32 {
+        let mut shots = 0;
+        let Ok(animation_player) = graph.try_get_mut(*self.animation_player) else {
+            return 0;
+        };
+        let animations = animation_player.animations_mut().get_value_mut_silent();
+        if let Some(shot_animation) = animations.iter_mut().next() {
+            shot_animation.set_enabled(*self.enabled && *self.firing_mode == FiringMode::Animation);
+            while let Some(event) = shot_animation.pop_event() {
+                if event.name == "Shoot" {
+                    shots += 1;
+                }
+            }
+        }
+        shots
+    }
+
+    fn shots_from_timer(&mut self, has_target: bool, dt: f32) -> u32 {
+        self.shot_timer -= dt;
+        if self.shot_timer > 0.0 {
+            return 0;
+        }
+
+        match *self.firing_mode {
+            FiringMode::Animation => 0,
+            FiringMode::FixedInterval | FiringMode::RandomInterval => {
+                self.shot_timer = self.next_interval();
+                1
+            }
+            FiringMode::Tracking => {
+                if has_target {
+                    self.shot_timer = self.next_interval();
+                    1
+                } else {
+                    0
+                }
+            }
+            FiringMode::Burst => {
+                if self.burst_shots_left == 0 {
+                    self.burst_shots_left = *self.burst_count;
+                }
+                self.burst_shots_left = self.burst_shots_left.saturating_sub(1);
+                self.shot_timer = if self.burst_shots_left == 0 {
+                    self.next_interval()
+                } else {
+                    *self.burst_delay
+                };
+                1
+            }
+        }
+    }
+
+    /// Finds the nearest actor in the tracking cone and turns the turret to it. Returns `true` if
+    /// the turret is aimed at the target.
+    fn track_target(
+        &self,
+        handle: Handle<Node>,
+        actors: impl Iterator<Item = Handle<Node>>,
+        graph: &mut Graph,
+        dt: f32,
+    ) -> Result<bool, GameError> {
+        let self_node = graph.try_get(handle)?;
+        let self_look = self_node
+            .look_vector()
+            .try_normalize(f32::EPSILON)
+            .unwrap_or_default();
+        let muzzle_position = graph.try_get(self.muzzle(handle))?.global_position();
+        let cone_cos = self.tracking_cone.to_radians().cos();
+
+        let mut target = None;
+        let mut min_distance = *self.tracking_range;
+        for actor in actors {
+            let actor = graph.try_get_script_component_of::<Actor>(actor)?;
+            let to_actor = graph.try_get(actor.rigid_body)?.global_position() - muzzle_position;
+            let distance = to_actor.norm();
+            if let Some(dir) = to_actor.try_normalize(f32::EPSILON) {
+                if distance <= min_distance && dir.dot(&self_look) >= cone_cos {
+                    min_distance = distance;
+                    target = Some(dir);
+                }
+            }
+        }
+
+        let Some(target_dir) = target else {
+            return Ok(false);
+        };
+        if self.turret.is_none() {
+            return Ok(true);
         }
 
+        let turret = graph.try_get(*self.turret)?;
+        let parent_rotation = graph
+            .try_get(turret.parent())
+            .map(|p| p.global_rotation())
+            .unwrap_or_default();
+        let local_dir = parent_rotation.inverse_transform_vector(&target_dir);
+        let target_rotation = UnitQuaternion::face_towards(&local_dir, &Vector3::y_axis());
+        let current_rotation = **turret.local_transform().rotation();
+        let max_angle = self.turn_speed.to_radians() * dt;
+        let angle = current_rotation.angle_to(&target_rotation);
+        let new_rotation = if angle <= max_angle {
+            target_rotation
+        } else {
+            current_rotation
+                .try_slerp(&target_rotation, max_angle / angle, f32::EPSILON)
+                .unwrap_or(target_rotation)
+        };
+        graph
+            .try_get_mut(*self.turret)?
+            .local_transform_mut()
+            .set_rotation(new_rotation);
+
+        // Shoot only when the turret looks at the target.
+        Ok(angle <= 5.0f32.to_radians())
+    }
+
+    fn shoot(&mut self, ctx: &mut ScriptContext) -> GameResult {
+        let game = ctx.plugins.get_mut::<Game>();
         let Some(server) = game.server.as_mut() else {
             return Ok(());
         };
 
-        let mbc = ctx.scene.graph.begin_multi_borrow();
-
-        let self_node = mbc.get(ctx.handle);
-        let self_position = self_node.global_position();
-        let shooting_dir = self_node
+        let muzzle = ctx.scene.graph.try_get(self.muzzle(ctx.handle))?;
+        let self_position = muzzle.global_position();
+        let mut shooting_dir = muzzle
             .look_vector()
             .try_normalize(f32::EPSILON)
             .unwrap_or_default();
 
-        let mut animation_player = mbc.try_get_mut(*self.animation_player)?;
+        if *self.spread > 0.0 {
+            let spread = self.spread.to_radians();
+            let mut rng = thread_rng();
+            shooting_dir = deviate(
+                shooting_dir,
+                rng.gen_range(-spread..spread),
+                rng.gen_range(-spread..spread),
+            );
+        }
 
-        let animations = animation_player.animations_mut().get_value_mut_silent();
-        if let Some(shot_animation) = animations.iter_mut().next() {
-            while let Some(event) = shot_animation.pop_event() {
-                if event.name == "Shoot" {
-                    if let Some(ball_prefab) = self.ball_prefab.as_ref() {
-                        server.broadcast_message_to_clients(ServerMessage::Instantiate(vec![
-                            InstanceDescriptor {
-                                path: ctx.resource_manager.resource_path(ball_prefab).unwrap(),
-                                position: self_position,
-                                rotation: Default::default(),
-                                velocity: shooting_dir.scale(*self.shooting_force),
-                                ids: ball_prefab.generate_ids(),
-                            },
-                        ]));
-                    }
-
-                    if let Some(shot_effect) = self.shot_effect.as_ref() {
-                        server.broadcast_message_to_clients(ServerMessage::Instantiate(vec![
-                            InstanceDescriptor {
-                                path: ctx.resource_manager.resource_path(shot_effect).unwrap(),
-                                position: self_position,
-                                rotation: vector_to_quat(shooting_dir),
-                                ids: shot_effect.generate_ids(),
-                                ..Default::default()
-                            },
-                        ]));
-                    }
-
-                    let mut sound = mbc.try_get_mut(*self.shot_sound)?;
-                    sound.set_playback_time(0.0);
-                    sound.play();
+        if let Some(ball_prefab) = self.ball_prefab.as_ref() {
+            let ids = ball_prefab.generate_ids();
+            if *self.max_live_balls > 0 {
+                // Balls could be destroyed already (by explosions or respawn zones), they do not
+                // count. The last ball could be still loading, so it is kept.
+                let last_ball = self.live_balls.pop_back();
+                self.live_balls
+                    .retain(|id| ctx.scene.graph.node_by_id(*id).is_ok());
+                self.live_balls.extend(last_ball);
+
+                let root = ball_prefab.data_ref().get_scene().graph.get_root();
+                if let Some(root_id) = ids.get(&root) {
+                    self.live_balls.push_back(*root_id);
                 }
             }
+
+            server.broadcast_message_to_clients(ServerMessage::Instantiate(vec![
+                InstanceDescriptor {
+                    path: ctx.resource_manager.resource_path(ball_prefab).unwrap(),
+                    position: self_position,
+                    rotation: Default::default(),
+                    velocity: shooting_dir.scale(*self.shooting_force),
+                    mass: (*self.ball_mass > 0.0).then_some(*self.ball_mass),
+                    ids,
+                },
+            ]));
+
+            if *self.max_live_balls > 0 && self.live_balls.len() > *self.max_live_balls {
+                let excess = self.live_balls.len() - *self.max_live_balls;
+                server.broadcast_message_to_clients(ServerMessage::Remove(
+                    self.live_balls.drain(..excess).collect(),
+                ));
+            }
+        }
+
+        if let Some(shot_effect) = self.shot_effect.as_ref() {
+            server.broadcast_message_to_clients(ServerMessage::Instantiate(vec![
+                InstanceDescriptor {
+                    path: ctx.resource_manager.resource_path(shot_effect).unwrap(),
+                    position: self_position,
+                    rotation: vector_to_quat(shooting_dir),
+                    ids: shot_effect.generate_ids(),
+                    ..Default::default()
+                },
+            ]));
+        }
+
+        if let Ok(sound) = ctx.scene.graph.try_get_mut(*self.shot_sound) {
+            sound.set_playback_time(0.0);
+            sound.play();
+        }
+
+        Ok(())
+    }
+}
+
+impl ScriptTrait for Cannon {
+    fn on_start(&mut self, ctx: &mut ScriptContext) -> GameResult {
+        ctx.message_dispatcher
+            .subscribe_to::<SignalMessage>(ctx.handle);
+        self.shot_timer = self.next_interval();
+        Ok(())
+    }
+
+    fn on_message(
+        &mut self,
+        message: &mut dyn ScriptMessagePayload,
+        _ctx: &mut ScriptMessageContext,
+    ) -> GameResult {
+        if let Some(signal) = message.downcast_ref::<SignalMessage>() {
+            self.enabled.set_value_and_mark_modified(signal.active);
         }
         Ok(())
     }
+
+    fn on_update(&mut self, ctx: &mut ScriptContext) -> GameResult {
+        let game = ctx.plugins.get::<Game>();
+        if game.is_client() || game.server.is_none() {
+            return Ok(());
+        }
+
+        let mut shots = self.shots_from_animation(&mut ctx.scene.graph);
+
+        if *self.enabled && *self.firing_mode != FiringMode::Animation {
+            let has_target = *self.firing_mode == FiringMode::Tracking
+                && self.track_target(
+                    ctx.handle,
+                    game.level.actors.iter().cloned(),
+                    &mut ctx.scene.graph,
+                    ctx.dt,
+                )?;
+            shots += self.shots_from_timer(has_target, ctx.dt);
+        }
+
+        for _ in 0..shots {
+            self.shoot(ctx)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn deviate_rotates_around_perpendicular_axes() {
+        let angle = 10.0f32.to_radians();
+        for direction in [
+            Vector3::x(),
+            Vector3::y(),
+            -Vector3::y(),
+            Vector3::z(),
+            Vector3::new(1.0, 1.0, 0.0).normalize(),
+        ] {
+            let horizontal = deviate(direction, angle, 0.0);
+            let vertical = deviate(direction, 0.0, angle);
+            assert!((horizontal.angle(&direction) - angle).abs() < 1.0e-4);
+            assert!((vertical.angle(&direction) - angle).abs() < 1.0e-4);
+            // Both axes deviate the direction, and they do it in different planes.
+            assert!(horizontal.metric_distance(&vertical) > 0.1);
+        }
+    }
+
+    /// Returns amount of shots on every step of the given length.
+    fn shots(cannon: &mut Cannon, has_target: bool, dt: f32, steps: usize) -> Vec<u32> {
+        (0..steps)
+            .map(|_| cannon.shots_from_timer(has_target, dt))
+            .collect()
+    }
+
+    #[test]
+    fn fixed_interval_shoots_once_per_interval() {
+        let mut cannon = Cannon {
+            firing_mode: FiringMode::FixedInterval.into(),
+            interval: 2.0.into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            shots(&mut cannon, false, 0.5, 9),
+            [1, 0, 0, 0, 1, 0, 0, 0, 1]
+        );
+    }
+
+    #[test]
+    fn burst_shoots_series_of_balls() {
+        let mut cannon = Cannon {
+            firing_mode: FiringMode::Burst.into(),
+            burst_count: 3.into(),
+            burst_delay: 0.5.into(),
+            interval: 2.0.into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            shots(&mut cannon, false, 0.5, 9),
+            [1, 1, 1, 0, 0, 0, 1, 1, 1]
+        );
+    }
+
+    #[test]
+    fn random_interval_stays_in_range() {
+        let mut cannon = Cannon {
+            firing_mode: FiringMode::RandomInterval.into(),
+            interval_range: (1.0..2.0).into(),
+            ..Default::default()
+        };
+        for _ in 0..10 {
+            cannon.shot_timer = 0.0;
+            assert_eq!(cannon.shots_from_timer(false, 0.0), 1);
+            assert!((1.0..2.0).contains(&cannon.shot_timer));
+        }
+
+        // An empty range means a fixed interval.
+        cannon.interval_range = (3.0..3.0).into();
+        cannon.shot_timer = 0.0;
+        cannon.shots_from_timer(false, 0.0);
+        assert_eq!(cannon.shot_timer, 3.0);
+    }
+
+    #[test]
+    fn tracking_shoots_only_at_targets() {
+        let mut cannon = Cannon {
+            firing_mode: FiringMode::Tracking.into(),
+            interval: 1.0.into(),
+            ..Default::default()
+        };
+        assert_eq!(shots(&mut cannon, false, 0.5, 4), [0, 0, 0, 0]);
+        // The cannon is reloaded while there's no target, so it shoots right away.
+        assert_eq!(shots(&mut cannon, true, 0.5, 5), [1, 0, 1, 0, 1]);
+    }
+
+    #[test]
+    fn animation_mode_ignores_timer() {
+        let mut cannon = Cannon::default();
+        assert_eq!(shots(&mut cannon, true, 1.0, 5), [0, 0, 0, 0, 0]);
+    }
 }
@@ -1,8 +1,12 @@
 use crate::{
-    actor::Actor,
-    level::Level,
+    actor::{Actor, ActorState},
+    crumble::CrumblePlatform,
+    interactable::Interactable,
+    level::{Level, MatchStage},
     menu::Menu,
     net::{ClientMessage, InstanceDescriptor, PlayerDescriptor, ServerMessage},
+    player::Player,
+    tournament::TournamentStandingsMessage,
     Game,
 };
 use fyrox::{
@@ -10,7 +14,7 @@ use fyrox::{
     graph::SceneGraph,
     plugin::{error::GameResult, PluginContext},
     resource::model::{Model, ModelResourceExtension},
-    scene::{rigidbody::RigidBody, Scene},
+    scene::{graph::Graph, node::Node, rigidbody::RigidBody, Scene},
 };
 use std::{
     fmt::{Debug, Formatter},
@@ -32,6 +36,9 @@ pub struct WinContext {
 pub struct Client {
     connection: NetStream,
     pub win_context: Option<WinContext>,
+    /// Cumulative results of the last finished tournament round.
+    pub tournament_standings: Option<TournamentStandingsMessage>,
+    last_stage: MatchStage,
 }
 
 impl Debug for Client {
@@ -54,6 +61,9 @@ fn instantiate_objects(instances: Vec<InstanceDescriptor>, ctx: &mut PluginConte
                     .finish();
                 if let Ok(rigid_body) = scene.graph.try_get_mut_of_type::<RigidBody>(instance) {
                     rigid_body.set_lin_vel(new_instance.velocity);
+                    if let Some(mass) = new_instance.mass {
+                        rigid_body.set_mass(mass);
+                    }
                 }
                 Ok(())
             },
@@ -73,18 +83,51 @@ fn add_players(players: Vec<PlayerDescriptor>, ctx: &mut PluginContext) {
                     .finish();
                 let actor = scene.graph.try_get_script_component_of_mut::<Actor>(root)?;
                 actor.kind = player.kind;
+                actor.participant = player.participant;
+                actor.spectator = player.spectator;
+                actor.team = player.team;
                 let rigid_body = actor.rigid_body;
                 scene
                     .graph
                     .try_get_mut(rigid_body)?
                     .local_transform_mut()
                     .set_position(player.instance.position);
+                if let Some(script) = scene
+                    .graph
+                    .try_get_mut(root)?
+                    .try_get_script_mut::<Player>()
+                {
+                    script.local_player = player.local_player;
+                }
                 Ok(())
             },
         );
     }
 }
 
+/// Repeats the elimination of an actor (or turning it into a spectator), that was done by the
+/// server: the actor becomes a ghost and its model is hidden.
+fn hide_eliminated_actor(handle: Handle<Node>, graph: &mut Graph) -> GameResult {
+    let node = graph.try_get_mut(handle)?;
+    // The camera of a player is attached to the root, so only the model is hidden.
+    let model = node
+        .try_get_script::<Player>()
+        .map_or(handle, |player| player.model());
+    let Some(actor) = node.try_get_script_component_mut::<Actor>() else {
+        return Ok(());
+    };
+    // The host shares the level with the server, so the actor could be eliminated already.
+    if actor.spectator {
+        return Ok(());
+    }
+    actor.spectator = true;
+    let (rigid_body, ragdoll) = (actor.rigid_body, actor.ragdoll);
+
+    Actor::make_ghost(rigid_body, ragdoll, graph)?;
+    graph.try_get_mut(model)?.set_visibility(false);
+    Ok(())
+}
+
 impl Client {
     pub fn try_connect<A>(server_addr: A) -> io::Result<Self>
     where
@@ -93,6 +136,8 @@ impl Client {
         Ok(Self {
             connection: NetStream::connect(server_addr)?,
             win_context: None,
+            tournament_standings: None,
+            last_stage: Default::default(),
         })
     }
 
@@ -129,26 +174,45 @@ impl Client {
                 ServerMessage::Instantiate(instances) => {
                     instantiate_objects(instances, ctx);
                 }
+                ServerMessage::Remove(nodes) => {
+                    let scene = ctx.scenes.try_get_mut(level.scene)?;
+                    for node in nodes {
+                        if let Ok((handle, _)) = scene.graph.node_by_id(node) {
+                            scene.graph.remove_node(handle);
+                        }
+                    }
+                }
                 ServerMessage::AddPlayers(players) => add_players(players, ctx),
                 ServerMessage::EndMatch => {
-                    let scene = ctx.scenes.try_get(level.scene)?;
-                    let mut players = level
-                        .leaderboard
-                        .entries
-                        .values()
-                        .map(|e| {
-                            let actor = scene
-                                .graph
-                                .try_get_script_component_of::<Actor>(e.actor)
-                                .unwrap();
-
-                            FinishedPlayer {
-                                name: actor.name.clone(),
-                                place: e.finished_position,
-                            }
-                        })
-                        .collect::<Vec<_>>();
-                    players.sort_by_key(|e| e.place);
+                    let players = match self.tournament_standings.take() {
+                        // The overall winner of a tournament is the winner of the last round.
+                        Some(msg) if msg.is_final() => msg
+                            .standings
+                            .into_iter()
+                            .enumerate()
+                            .map(|(n, standing)| FinishedPlayer {
+                                name: standing.name,
+                                place: n + 1,
+                            })
+                            .collect::<Vec<_>>(),
+                        _ => {
+                            let scene = ctx.scenes.try_get(level.scene)?;
+                            level
+                                .game_mode
+                                .ranking(&level.leaderboard)
+                                .into_iter()
+                                // Actors could be removed already.
+                                .filter_map(|actor| {
+                                    scene.graph.try_get_script_component_of::<Actor>(actor).ok()
+                                })
+                                .enumerate()
+                                .map(|(n, actor)| FinishedPlayer {
+                                    name: actor.name.clone(),
+                                    place: n + 1,
+                                })
+                                .collect::<Vec<_>>()
+                        }
+                    };
 
                     self.win_context = Some(WinContext {
                         timer: 10.0,
@@ -167,6 +231,70 @@ impl Client {
                     level.leaderboard.entries =
                         msg.players.into_iter().map(|e| (e.actor, e)).collect();
                 }
+                ServerMessage::PickupCollected {
+                    pickup,
+                    actor,
+                    effect,
+                    expires_at,
+                } => {
+                    let scene = ctx.scenes.try_get_mut(level.scene)?;
+                    if let Ok((_, pickup)) = scene.graph.node_by_id_mut(pickup) {
+                        pickup.set_visibility(false);
+                    }
+                    if let Ok((actor, _)) = scene.graph.node_by_id(actor) {
+                        scene
+                            .graph
+                            .try_get_script_component_of_mut::<Actor>(actor)?
+                            .set_effect(effect, expires_at);
+                    }
+                }
+                ServerMessage::PickupRespawned { pickup } => {
+                    let scene = ctx.scenes.try_get_mut(level.scene)?;
+                    if let Ok((_, pickup)) = scene.graph.node_by_id_mut(pickup) {
+                        pickup.set_visibility(true);
+                    }
+                }
+                ServerMessage::InteractableState { node, active } => {
+                    let scene = ctx.scenes.try_get_mut(level.scene)?;
+                    if let Ok((_, node)) = scene.graph.node_by_id_mut(node) {
+                        if let Some(interactable) = node.try_get_script_mut::<Interactable>() {
+                            interactable.sync_state(active);
+                            let visible = interactable.is_visible();
+                            node.set_visibility(visible);
+                        }
+                    }
+                }
+                ServerMessage::TileState { node, state } => {
+                    let scene = ctx.scenes.try_get_mut(level.scene)?;
+                    if let Ok((handle, node)) = scene.graph.node_by_id_mut(node) {
+                        if let Some(tile) = node.try_get_script_mut::<CrumblePlatform>() {
+                            tile.sync_state(state);
+                            CrumblePlatform::apply_state(state, handle, &mut scene.graph)?;
+                        }
+                    }
+                }
+                ServerMessage::ActorState { actor, state } => {
+                    let scene = ctx.scenes.try_get_mut(level.scene)?;
+                    if let Ok((handle, node)) = scene.graph.node_by_id_mut(actor) {
+                        if let Some(actor) = node.try_get_script_component_mut::<Actor>() {
+                            // Transitions to the same state are refused, so the host (which
+                            // shares the level with the server) ignores the message.
+                            actor.set_state(state, handle, level);
+                        }
+                        if matches!(state, ActorState::Eliminated | ActorState::Spectating) {
+                            hide_eliminated_actor(handle, &mut scene.graph)?;
+                        }
+                    }
+                }
+                ServerMessage::TournamentStandings(msg) => {
+                    self.tournament_standings = Some(msg);
+                }
+                ServerMessage::MatchState(msg) => {
+                    level.stage = msg.stage;
+                    level.match_timer = msg.match_timer;
+                    level.match_time = msg.match_time;
+                    level.game_mode = msg.game_mode;
+                }
             }
         }
         Ok(())
@@ -176,19 +304,42 @@ impl Client {
         if level.scene.is_some() {
             ctx.scenes.remove(level.scene);
         }
+        self.last_stage = Default::default();
         ctx.load_scene(path, false, |result, game: &mut Game, ctx| {
             game.on_scene_loaded(result?.payload, ctx)
         });
     }
 
-    pub fn update(&mut self, dt: f32) {
+    pub fn update(
+        &mut self,
+        level: &mut Level,
+        has_server: bool,
+        menu: Option<&Menu>,
+        ctx: &mut PluginContext,
+    ) -> GameResult {
         if let Some(win_context) = self.win_context.as_mut() {
-            win_context.timer -= dt;
+            win_context.timer -= ctx.dt;
 
             if win_context.timer <= 0.0 {
                 self.win_context.take();
             }
         }
+
+        // The server sends the match state only from time to time, remote clients advance the
+        // clock between the messages.
+        if !has_server {
+            level.advance_clock(ctx.dt);
+        }
+
+        // The level could be shared with the server, so the stage is tracked separately to not
+        // miss the changes.
+        let prev_stage = std::mem::replace(&mut self.last_stage, level.stage);
+        if prev_stage.countdown_number() != level.stage.countdown_number() {
+            if let Some(menu) = menu {
+                menu.on_countdown_changed(ctx, level.stage)?;
+            }
+        }
+        Ok(())
     }
 
     pub fn on_scene_loaded(
@@ -201,6 +352,7 @@ impl Client {
         if !has_server {
             scene.graph.physics.enabled.set_value_silent(false);
         }
+        self.send_message_to_server(ClientMessage::LevelLoaded);
         Ok(())
     }
 }
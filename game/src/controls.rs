@@ -0,0 +1,252 @@
+//! Rebindable controls.
+
+use fyrox::gui::message::KeyCode;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+use strum_macros::AsRefStr;
+
+/// An action, that can be bound to keys.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, AsRefStr,
+)]
+pub enum ControlAction {
+    #[strum(serialize = "Move Forward")]
+    MoveForward,
+    #[strum(serialize = "Move Backward")]
+    MoveBackward,
+    #[strum(serialize = "Move Left")]
+    MoveLeft,
+    #[strum(serialize = "Move Right")]
+    MoveRight,
+    Jump,
+    Interact,
+    Grab,
+    Shove,
+    Dive,
+    Menu,
+    #[strum(serialize = "Show Physics")]
+    ShowPhysics,
+    #[strum(serialize = "Show Paths")]
+    ShowPaths,
+    #[strum(serialize = "Disable Ragdoll")]
+    DisableRagdoll,
+    #[strum(serialize = "Shorten Match")]
+    ShortenMatch,
+    #[strum(serialize = "Show Impacts")]
+    ShowImpacts,
+}
+
+impl ControlAction {
+    pub const ALL: [ControlAction; 15] = [
+        ControlAction::MoveForward,
+        ControlAction::MoveBackward,
+        ControlAction::MoveLeft,
+        ControlAction::MoveRight,
+        ControlAction::Jump,
+        ControlAction::Interact,
+        ControlAction::Grab,
+        ControlAction::Shove,
+        ControlAction::Dive,
+        ControlAction::Menu,
+        ControlAction::ShowPhysics,
+        ControlAction::ShowPaths,
+        ControlAction::DisableRagdoll,
+        ControlAction::ShortenMatch,
+        ControlAction::ShowImpacts,
+    ];
+}
+
+/// Keys of every action. An action can have up to [`Controls::MAX_BINDINGS`] keys.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Controls {
+    #[serde(deserialize_with = "merge_with_defaults")]
+    bindings: BTreeMap<ControlAction, Vec<KeyCode>>,
+}
+
+/// Saved bindings replace the default ones action by action, so the actions, that were added after
+/// the settings were saved, keep their default keys.
+fn merge_with_defaults<'de, D>(
+    deserializer: D,
+) -> Result<BTreeMap<ControlAction, Vec<KeyCode>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let mut bindings = Controls::default().bindings;
+    for (action, keys) in BTreeMap::<ControlAction, Vec<KeyCode>>::deserialize(deserializer)? {
+        if action == ControlAction::Menu && keys.is_empty() {
+            continue;
+        }
+        bindings.insert(action, keys);
+    }
+    Ok(bindings)
+}
+
+impl Default for Controls {
+    fn default() -> Self {
+        let bindings = [
+            (
+                ControlAction::MoveForward,
+                vec![KeyCode::KeyW, KeyCode::ArrowUp],
+            ),
+            (
+                ControlAction::MoveBackward,
+                vec![KeyCode::KeyS, KeyCode::ArrowDown],
+            ),
+            (
+                ControlAction::MoveLeft,
+                vec![KeyCode::KeyA, KeyCode::ArrowLeft],
+            ),
+            (
+                ControlAction::MoveRight,
+                vec![KeyCode::KeyD, KeyCode::ArrowRight],
+            ),
+            (ControlAction::Jump, vec![KeyCode::Space]),
+            (ControlAction::Interact, vec![KeyCode::KeyE]),
+            (ControlAction::Grab, vec![KeyCode::KeyQ]),
+            (ControlAction::Shove, vec![KeyCode::KeyF]),
+            (ControlAction::Dive, vec![KeyCode::KeyC]),
+            (ControlAction::Menu, vec![KeyCode::Escape]),
+            (ControlAction::ShowPhysics, vec![KeyCode::F1]),
+            (ControlAction::ShowPaths, vec![KeyCode::F2]),
+            (ControlAction::DisableRagdoll, vec![KeyCode::F3]),
+            (ControlAction::ShortenMatch, vec![KeyCode::F4]),
+            (ControlAction::ShowImpacts, vec![KeyCode::F5]),
+        ];
+        Self {
+            bindings: bindings.into_iter().collect(),
+        }
+    }
+}
+
+impl Controls {
+    pub const MAX_BINDINGS: usize = 2;
+
+    pub fn bindings(&self, action: ControlAction) -> &[KeyCode] {
+        self.bindings
+            .get(&action)
+            .map(|keys| keys.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Returns every action, that is bound to the given key. There could be more than one action,
+    /// if the bindings are in conflict.
+    pub fn actions(&self, key: KeyCode) -> impl Iterator<Item = ControlAction> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |(_, keys)| keys.contains(&key))
+            .map(|(action, _)| *action)
+    }
+
+    /// Sets the key of the given binding slot, `None` removes the binding. The last key of the menu
+    /// cannot be removed, otherwise there's no way to open the menu.
+    pub fn set_binding(&mut self, action: ControlAction, slot: usize, key: Option<KeyCode>) {
+        let keys = self.bindings.entry(action).or_default();
+        match key {
+            Some(key) if keys.contains(&key) => (),
+            Some(key) if slot < keys.len() => keys[slot] = key,
+            Some(key) if keys.len() < Self::MAX_BINDINGS => keys.push(key),
+            Some(_) => (),
+            None if action == ControlAction::Menu && keys.len() <= 1 => (),
+            None if slot < keys.len() => {
+                keys.remove(slot);
+            }
+            None => (),
+        }
+    }
+
+    /// Returns every key, that is bound to more than one action.
+    pub fn conflicts(&self) -> Vec<(KeyCode, Vec<ControlAction>)> {
+        let mut actions_by_key = BTreeMap::<String, (KeyCode, Vec<ControlAction>)>::new();
+        for (action, keys) in self.bindings.iter() {
+            for key in keys {
+                actions_by_key
+                    .entry(key.as_ref().to_string())
+                    .or_insert_with(|| (*key, Vec::new()))
+                    .1
+                    .push(*action);
+            }
+        }
+        actions_by_key
+            .into_values()
+            .filter(|(_, actions)| actions.len() > 1)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn set_binding_replaces_and_appends_keys() {
+        let mut controls = Controls::default();
+        controls.set_binding(ControlAction::Jump, 0, Some(KeyCode::KeyJ));
+        assert_eq!(controls.bindings(ControlAction::Jump), [KeyCode::KeyJ]);
+        controls.set_binding(ControlAction::Jump, 1, Some(KeyCode::Space));
+        assert_eq!(
+            controls.bindings(ControlAction::Jump),
+            [KeyCode::KeyJ, KeyCode::Space]
+        );
+        // No more than two keys, and no duplicates.
+        controls.set_binding(ControlAction::Jump, 2, Some(KeyCode::KeyK));
+        controls.set_binding(ControlAction::Jump, 0, Some(KeyCode::Space));
+        assert_eq!(
+            controls.bindings(ControlAction::Jump),
+            [KeyCode::KeyJ, KeyCode::Space]
+        );
+    }
+
+    #[test]
+    fn set_binding_removes_keys() {
+        let mut controls = Controls::default();
+        controls.set_binding(ControlAction::MoveForward, 0, None);
+        assert_eq!(
+            controls.bindings(ControlAction::MoveForward),
+            [KeyCode::ArrowUp]
+        );
+        controls.set_binding(ControlAction::MoveForward, 5, None);
+        assert_eq!(
+            controls.bindings(ControlAction::MoveForward),
+            [KeyCode::ArrowUp]
+        );
+        controls.set_binding(ControlAction::MoveForward, 0, None);
+        assert!(controls.bindings(ControlAction::MoveForward).is_empty());
+    }
+
+    #[test]
+    fn menu_always_has_a_key() {
+        let mut controls = Controls::default();
+        controls.set_binding(ControlAction::Menu, 0, None);
+        assert_eq!(controls.bindings(ControlAction::Menu), [KeyCode::Escape]);
+        controls.set_binding(ControlAction::Menu, 1, Some(KeyCode::KeyP));
+        controls.set_binding(ControlAction::Menu, 0, None);
+        assert_eq!(controls.bindings(ControlAction::Menu), [KeyCode::KeyP]);
+    }
+
+    #[test]
+    fn conflicts_list_keys_of_several_actions() {
+        let mut controls = Controls::default();
+        assert!(controls.conflicts().is_empty());
+        controls.set_binding(ControlAction::Grab, 1, Some(KeyCode::Space));
+        assert_eq!(
+            controls.conflicts(),
+            [(
+                KeyCode::Space,
+                vec![ControlAction::Jump, ControlAction::Grab]
+            )]
+        );
+        assert_eq!(
+            controls.actions(KeyCode::Space).collect::<Vec<_>>(),
+            [ControlAction::Jump, ControlAction::Grab]
+        );
+    }
+
+    #[test]
+    fn saved_bindings_are_merged_with_defaults() {
+        let controls: Controls = ron::from_str("(bindings: {Jump: [KeyJ], Menu: []})").unwrap();
+        assert_eq!(controls.bindings(ControlAction::Jump), [KeyCode::KeyJ]);
+        // Missing actions and the empty menu binding are taken from the defaults.
+        assert_eq!(controls.bindings(ControlAction::Dive), [KeyCode::KeyC]);
+        assert_eq!(controls.bindings(ControlAction::Menu), [KeyCode::Escape]);
+    }
+}
@@ -0,0 +1,313 @@
+//! Crumble platform is a floor tile, that shakes and then falls down some time after an actor
+//! (player or bot) touches it for the first time. The tile should be a kinematic rigid body. The
+//! state of the tile is driven by the server, clients only receive its state and transform. Fallen
+//! tiles are hidden and their colliders do not collide with anything until the reset.
+
+use crate::{actor::Actor, net::ServerMessage, server::Server, Game};
+use fyrox::graph::SceneGraph;
+use fyrox::plugin::error::{GameError, GameResult};
+use fyrox::{
+    core::{
+        algebra::Vector3, pool::Handle, reflect::prelude::*, type_traits::prelude::*,
+        variable::InheritableVariable, visitor::prelude::*,
+    },
+    rand::{thread_rng, Rng},
+    scene::{collider::Collider, graph::Graph, node::Node, sound::Sound},
+    script::{ScriptContext, ScriptTrait},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Visit, Reflect, Serialize, Deserialize)]
+pub enum TileState {
+    #[default]
+    Intact,
+    /// An actor has touched the tile and it is about to fall.
+    Shaking,
+    Falling,
+    /// The tile is hidden and waits for reset (if any).
+    Fallen,
+}
+
+#[derive(Clone, Debug, Visit, Reflect, TypeUuidProvider, ComponentProvider)]
+#[type_uuid(id = "6e4a1d8c-3f72-4b95-a1e0-9c5d7b2f4a83")]
+#[visit(optional)]
+pub struct CrumblePlatform {
+    /// Time (in seconds) between the first touch and the fall.
+    delay: InheritableVariable<f32>,
+    /// Max offset of the tile while it shakes.
+    shake_amplitude: InheritableVariable<f32>,
+    /// Acceleration of the falling tile.
+    fall_acceleration: InheritableVariable<f32>,
+    /// Time (in seconds) after which the falling tile disappears.
+    fall_time: InheritableVariable<f32>,
+    /// Time (in seconds) after the fall, when the tile comes back. Zero means that the tile never
+    /// comes back.
+    reset_time: InheritableVariable<f32>,
+    shake_sound: InheritableVariable<Handle<Sound>>,
+    #[reflect(hidden)]
+    state: TileState,
+    #[reflect(hidden)]
+    #[visit(skip)]
+    timer: f32,
+    #[reflect(hidden)]
+    #[visit(skip)]
+    fall_speed: f32,
+    #[reflect(hidden)]
+    #[visit(skip)]
+    initial_position: Vector3<f32>,
+}
+
+impl Default for CrumblePlatform {
+    fn default() -> Self {
+        Self {
+            delay: 1.0.into(),
+            shake_amplitude: 0.05.into(),
+            fall_acceleration: 20.0.into(),
+            fall_time: 1.5.into(),
+            reset_time: 0.0.into(),
+            shake_sound: Default::default(),
+            state: Default::default(),
+            timer: 0.0,
+            fall_speed: 0.0,
+            initial_position: Default::default(),
+        }
+    }
+}
+
+impl CrumblePlatform {
+    pub fn state(&self) -> TileState {
+        self.state
+    }
+
+    /// Only intact tiles can be stepped on.
+    pub fn is_safe(&self) -> bool {
+        self.state == TileState::Intact
+    }
+
+    /// Sets the state of the tile on a client.
+    pub fn sync_state(&mut self, state: TileState) {
+        self.state = state;
+    }
+
+    /// Hides the fallen tile and turns its colliders into sensors, so actors fall through it.
+    /// It is used by clients too, that's why it takes the state instead of the tile.
+    pub fn apply_state(state: TileState, handle: Handle<Node>, graph: &mut Graph) -> GameResult {
+        let fallen = state == TileState::Fallen;
+        let node = graph.try_get_mut(handle)?;
+        node.set_visibility(!fallen);
+        let mut colliders = vec![handle];
+        colliders.extend_from_slice(node.children());
+        for collider in colliders {
+            if let Ok(collider) = graph.try_get_mut_of_type::<Collider>(collider) {
+                collider.set_is_sensor(fallen);
+            }
+        }
+        Ok(())
+    }
+
+    fn enter_state(&mut self, state: TileState) {
+        self.state = state;
+        self.timer = 0.0;
+        self.fall_speed = 0.0;
+    }
+
+    /// Advances the timer of the tile and returns the state, that the tile must switch to.
+    fn advance(&mut self, is_touched: bool, dt: f32) -> Option<TileState> {
+        self.timer += dt;
+        match self.state {
+            TileState::Intact => is_touched.then_some(TileState::Shaking),
+            TileState::Shaking => (self.timer >= *self.delay).then_some(TileState::Falling),
+            TileState::Falling => (self.timer >= *self.fall_time).then_some(TileState::Fallen),
+            TileState::Fallen => (*self.reset_time > 0.0 && self.timer >= *self.reset_time)
+                .then_some(TileState::Intact),
+        }
+    }
+
+    fn is_touched_by_actor(
+        &self,
+        handle: Handle<Node>,
+        actors: &HashSet<Handle<Node>>,
+        graph: &Graph,
+    ) -> Result<bool, GameError> {
+        let mut actor_colliders = HashSet::new();
+        for actor in actors {
+            actor_colliders.insert(graph.try_get_script_component_of::<Actor>(*actor)?.collider);
+        }
+
+        let mut colliders = vec![handle];
+        colliders.extend_from_slice(graph.try_get(handle)?.children());
+        for collider in colliders {
+            let Ok(collider) = graph.try_get_of_type::<Collider>(collider) else {
+                continue;
+            };
+            for contact in collider.contacts(&graph.physics) {
+                if contact.has_any_active_contact
+                    && (actor_colliders.contains(&contact.collider1)
+                        || actor_colliders.contains(&contact.collider2))
+                {
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    fn set_state(
+        &mut self,
+        state: TileState,
+        handle: Handle<Node>,
+        graph: &mut Graph,
+        server: Option<&mut Server>,
+    ) -> GameResult {
+        self.enter_state(state);
+
+        if state == TileState::Shaking {
+            if let Ok(sound) = graph.try_get_mut(*self.shake_sound) {
+                sound.set_playback_time(0.0);
+                sound.play();
+            }
+        }
+
+        Self::apply_state(state, handle, graph)?;
+        let node = graph.try_get_mut(handle)?;
+        if matches!(state, TileState::Intact | TileState::Falling) {
+            node.local_transform_mut()
+                .set_position(self.initial_position);
+        }
+
+        if let Some(server) = server {
+            server.broadcast_message_to_clients(ServerMessage::TileState {
+                node: node.instance_id(),
+                state,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl ScriptTrait for CrumblePlatform {
+    fn on_start(&mut self, ctx: &mut ScriptContext) -> GameResult {
+        self.initial_position = **ctx
+            .scene
+            .graph
+            .try_get(ctx.handle)?
+            .local_transform()
+            .position();
+        Ok(())
+    }
+
+    fn on_update(&mut self, ctx: &mut ScriptContext) -> GameResult {
+        let game = ctx.plugins.get_mut::<Game>();
+        if game.is_client() {
+            return Ok(());
+        }
+
+        let graph = &mut ctx.scene.graph;
+        let is_touched = self.state == TileState::Intact
+            && self.is_touched_by_actor(ctx.handle, &game.level.actors, graph)?;
+
+        match self.state {
+            TileState::Shaking => {
+                let amplitude = *self.shake_amplitude;
+                let mut rng = thread_rng();
+                let offset = Vector3::new(
+                    rng.gen_range(-amplitude..=amplitude),
+                    0.0,
+                    rng.gen_range(-amplitude..=amplitude),
+                );
+                graph
+                    .try_get_mut(ctx.handle)?
+                    .local_transform_mut()
+                    .set_position(self.initial_position + offset);
+            }
+            TileState::Falling => {
+                self.fall_speed += *self.fall_acceleration * ctx.dt;
+                let transform = graph.try_get_mut(ctx.handle)?.local_transform_mut();
+                let position = **transform.position();
+                transform.set_position(Vector3::new(
+                    position.x,
+                    position.y - self.fall_speed * ctx.dt,
+                    position.z,
+                ));
+            }
+            TileState::Intact | TileState::Fallen => (),
+        }
+
+        if let Some(new_state) = self.advance(is_touched, ctx.dt) {
+            self.set_state(new_state, ctx.handle, graph, game.server.as_mut())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Runs the tile with the given time step and returns the times (in seconds since the start)
+    /// of every state change.
+    fn run(tile: &mut CrumblePlatform, touch_time: f32, duration: f32) -> Vec<(f32, TileState)> {
+        let dt = 0.25;
+        let mut changes = Vec::new();
+        let mut time = 0.0;
+        while time < duration {
+            time += dt;
+            if let Some(state) = tile.advance(time >= touch_time, dt) {
+                tile.enter_state(state);
+                changes.push((time, state));
+            }
+        }
+        changes
+    }
+
+    #[test]
+    fn tile_falls_after_delay() {
+        let mut tile = CrumblePlatform {
+            delay: 1.0.into(),
+            fall_time: 0.5.into(),
+            ..Default::default()
+        };
+        assert!(tile.is_safe());
+        assert_eq!(
+            run(&mut tile, 2.0, 10.0),
+            [
+                (2.0, TileState::Shaking),
+                (3.0, TileState::Falling),
+                (3.5, TileState::Fallen)
+            ]
+        );
+        // Without the reset time the tile never comes back.
+        assert_eq!(tile.state(), TileState::Fallen);
+        assert!(!tile.is_safe());
+    }
+
+    #[test]
+    fn tile_comes_back_after_reset_time() {
+        let mut tile = CrumblePlatform {
+            delay: 1.0.into(),
+            fall_time: 0.5.into(),
+            reset_time: 2.0.into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            run(&mut tile, 0.0, 4.0),
+            [
+                (0.25, TileState::Shaking),
+                (1.25, TileState::Falling),
+                (1.75, TileState::Fallen),
+                (3.75, TileState::Intact),
+                (4.0, TileState::Shaking),
+            ]
+        );
+    }
+
+    #[test]
+    fn untouched_tile_stays_intact() {
+        let mut tile = CrumblePlatform::default();
+        assert!(run(&mut tile, f32::MAX, 10.0).is_empty());
+        assert!(tile.is_safe());
+    }
+}
@@ -0,0 +1,268 @@
+//! Bombs that explode and push actors (players or bots) away.
+
+use crate::{
+    actor::{Actor, ActorMessage},
+    net::{InstanceDescriptor, ServerMessage},
+    Game,
+};
+use fyrox::graph::SceneGraph;
+use fyrox::plugin::error::{GameError, GameResult};
+use fyrox::{
+    core::{
+        algebra::Vector3, log::Log, pool::Handle, reflect::prelude::*, type_traits::prelude::*,
+        variable::InheritableVariable, visitor::prelude::*,
+    },
+    resource::model::{ModelResource, ModelResourceExtension},
+    scene::{collider::Collider, graph::Graph, node::Node, rigidbody::RigidBody},
+    script::{ScriptContext, ScriptTrait},
+};
+use std::collections::HashSet;
+use strum_macros::{AsRefStr, EnumString, VariantNames};
+
+#[derive(
+    Default,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Visit,
+    Reflect,
+    TypeUuidProvider,
+    AsRefStr,
+    EnumString,
+    VariantNames,
+)]
+#[type_uuid(id = "e2b7c9a4-1f35-4d68-9a0c-3b5d7f9e1c42")]
+pub enum ExplosionTrigger {
+    /// The bomb explodes when its fuse time is out.
+    #[default]
+    Timer,
+    /// The bomb explodes when an actor touches it.
+    Contact,
+    /// The bomb explodes when it hits anything after falling down.
+    Dropped,
+}
+
+#[derive(Clone, Debug, Visit, Reflect, TypeUuidProvider, ComponentProvider)]
+#[type_uuid(id = "4a9d2e6f-8b1c-4f37-a5e0-7c2b9d4f6a18")]
+#[visit(optional)]
+pub struct Explosive {
+    trigger: InheritableVariable<ExplosionTrigger>,
+    /// Time (in seconds) after which a bomb with the timer trigger explodes.
+    fuse_time: InheritableVariable<f32>,
+    /// Actors further than this distance are not affected.
+    radius: InheritableVariable<f32>,
+    /// Impulse at the center of the explosion, it falls off linearly to zero at the radius.
+    impulse: InheritableVariable<f32>,
+    /// Min vertical speed of a falling bomb, at which it becomes armed.
+    falling_speed: InheritableVariable<f32>,
+    explosion_effect: InheritableVariable<Option<ModelResource>>,
+    explosion_sound: InheritableVariable<Option<ModelResource>>,
+    #[reflect(hidden)]
+    fuse_timer: f32,
+    #[reflect(hidden)]
+    armed: bool,
+    #[reflect(hidden)]
+    exploded: bool,
+}
+
+impl Default for Explosive {
+    fn default() -> Self {
+        Self {
+            trigger: Default::default(),
+            fuse_time: 5.0.into(),
+            radius: 5.0.into(),
+            impulse: 15.0.into(),
+            falling_speed: 2.0.into(),
+            explosion_effect: Default::default(),
+            explosion_sound: Default::default(),
+            fuse_timer: 0.0,
+            armed: false,
+            exploded: false,
+        }
+    }
+}
+
+/// Calculates an impulse of an explosion for a body at the given position. The impulse falls off
+/// linearly to zero at the radius, bodies outside the radius are not affected.
+pub fn radial_impulse(
+    center: Vector3<f32>,
+    radius: f32,
+    impulse: f32,
+    position: Vector3<f32>,
+) -> Option<Vector3<f32>> {
+    let offset = position - center;
+    let distance = offset.norm();
+    if distance >= radius {
+        return None;
+    }
+    // Throw the bodies up a bit, it looks better than pushing them along the ground.
+    let direction = (offset.try_normalize(f32::EPSILON).unwrap_or_default() + Vector3::y())
+        .try_normalize(f32::EPSILON)
+        .unwrap_or_else(Vector3::y);
+    Some(direction.scale(impulse * (1.0 - distance / radius)))
+}
+
+impl Explosive {
+    /// Checks whether any collider of the bomb has contact with something. If `filter` is not
+    /// empty, only contacts with the given colliders are taken into account.
+    fn has_contact(
+        &self,
+        handle: Handle<Node>,
+        graph: &Graph,
+        filter: &HashSet<Handle<Collider>>,
+    ) -> Result<bool, GameError> {
+        for child in graph.try_get(handle)?.children() {
+            let Ok(collider) = graph.try_get_of_type::<Collider>(*child) else {
+                continue;
+            };
+            for contact in collider.contacts(&graph.physics) {
+                if contact.has_any_active_contact
+                    && (filter.is_empty()
+                        || filter.contains(&contact.collider1)
+                        || filter.contains(&contact.collider2))
+                {
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    fn should_explode(&mut self, ctx: &ScriptContext) -> Result<bool, GameError> {
+        let game = ctx.plugins.get::<Game>();
+        let graph = &ctx.scene.graph;
+        match *self.trigger {
+            ExplosionTrigger::Timer => {
+                if !game.level.is_movement_locked() {
+                    self.fuse_timer += ctx.dt;
+                }
+                Ok(self.fuse_timer >= *self.fuse_time)
+            }
+            ExplosionTrigger::Contact => {
+                let mut actor_colliders = HashSet::new();
+                for actor in game.level.actors.iter() {
+                    actor_colliders
+                        .insert(graph.try_get_script_component_of::<Actor>(*actor)?.collider);
+                }
+                Ok(!actor_colliders.is_empty()
+                    && self.has_contact(ctx.handle, graph, &actor_colliders)?)
+            }
+            ExplosionTrigger::Dropped => {
+                if !self.armed {
+                    if let Ok(rigid_body) = graph.try_get_of_type::<RigidBody>(ctx.handle) {
+                        self.armed = rigid_body.lin_vel().y <= -*self.falling_speed;
+                    }
+                    return Ok(false);
+                }
+                self.has_contact(ctx.handle, graph, &Default::default())
+            }
+        }
+    }
+
+    fn explode(&mut self, ctx: &mut ScriptContext) -> GameResult {
+        self.exploded = true;
+
+        let game = ctx.plugins.get_mut::<Game>();
+        let center = ctx.scene.graph.try_get(ctx.handle)?.global_position();
+
+        for actor in game.level.actors.iter() {
+            ctx.message_sender.send_to_target(
+                *actor,
+                ActorMessage::Explosion {
+                    center,
+                    radius: *self.radius,
+                    impulse: *self.impulse,
+                },
+            );
+        }
+
+        let Some(server) = game.server.as_mut() else {
+            return Ok(());
+        };
+
+        let instances = [
+            self.explosion_effect.as_ref(),
+            self.explosion_sound.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .filter_map(|resource| {
+            // Clients instantiate the effects by their path, embedded resources cannot be sent.
+            let Some(path) = ctx.resource_manager.resource_path(resource) else {
+                Log::warn(format!(
+                    "Explosion effect {resource:?} has no path, it is not shown on clients!"
+                ));
+                return None;
+            };
+            Some(InstanceDescriptor {
+                path,
+                position: center,
+                ids: resource.generate_ids(),
+                ..Default::default()
+            })
+        })
+        .collect::<Vec<_>>();
+        if !instances.is_empty() {
+            server.broadcast_message_to_clients(ServerMessage::Instantiate(instances));
+        }
+
+        server.broadcast_message_to_clients(ServerMessage::Remove(vec![ctx
+            .scene
+            .graph
+            .try_get(ctx.handle)?
+            .instance_id()]));
+
+        Ok(())
+    }
+}
+
+impl ScriptTrait for Explosive {
+    fn on_update(&mut self, ctx: &mut ScriptContext) -> GameResult {
+        let game = ctx.plugins.get::<Game>();
+        if game.is_client() || self.exploded {
+            return Ok(());
+        }
+
+        if self.should_explode(ctx)? {
+            self.explode(ctx)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn impulse_falls_off_with_distance() {
+        let center = Vector3::new(1.0, 0.0, 0.0);
+
+        let near = radial_impulse(center, 4.0, 10.0, Vector3::new(2.0, 0.0, 0.0)).unwrap();
+        let far = radial_impulse(center, 4.0, 10.0, Vector3::new(4.0, 0.0, 0.0)).unwrap();
+        assert!((near.norm() - 7.5).abs() < 1.0e-5);
+        assert!((far.norm() - 2.5).abs() < 1.0e-5);
+
+        // Bodies are pushed away from the center and thrown up.
+        assert!(near.x > 0.0 && near.y > 0.0);
+        assert!((near.x - near.y).abs() < 1.0e-5);
+
+        assert_eq!(
+            radial_impulse(center, 4.0, 10.0, Vector3::new(5.0, 0.0, 0.0)),
+            None
+        );
+        assert_eq!(
+            radial_impulse(center, 4.0, 10.0, Vector3::new(1.0, 9.0, 0.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn impulse_at_center_goes_up() {
+        let center = Vector3::new(1.0, 2.0, 3.0);
+        let impulse = radial_impulse(center, 4.0, 10.0, center).unwrap();
+        assert!((impulse - Vector3::new(0.0, 10.0, 0.0)).norm() < 1.0e-5);
+    }
+}
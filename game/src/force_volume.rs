@@ -0,0 +1,204 @@
+//! Force volume is an area, that pushes actors (players or bots) and their ragdolls inside it.
+//! It can be used for wind, fans and water. Bounds of the volume is a unit box transformed by the
+//! global transform of the node.
+
+use crate::{
+    actor::{Actor, ActorMessage},
+    Game,
+};
+use fyrox::graph::SceneGraph;
+use fyrox::plugin::error::GameResult;
+use fyrox::{
+    core::{
+        algebra::Vector3, math::aabb::AxisAlignedBoundingBox, reflect::prelude::*,
+        type_traits::prelude::*, variable::InheritableVariable, visitor::prelude::*,
+    },
+    scene::node::Node,
+    script::{ScriptContext, ScriptDeinitContext, ScriptTrait},
+};
+use strum_macros::{AsRefStr, EnumString, VariantNames};
+
+#[derive(
+    Default,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Visit,
+    Reflect,
+    TypeUuidProvider,
+    AsRefStr,
+    EnumString,
+    VariantNames,
+)]
+#[type_uuid(id = "8f2c6a1e-4b93-4d07-b5e8-1a7d3c9f2e64")]
+pub enum ForceKind {
+    /// Constant force in the given direction (wind, fans).
+    #[default]
+    Directional,
+    /// Upward force, that grows with the depth of an actor in the volume (water).
+    Buoyancy,
+}
+
+#[derive(Clone, Debug, Visit, Reflect, TypeUuidProvider, ComponentProvider)]
+#[type_uuid(id = "2d7e9b4c-61a5-4f38-8c0d-5e3b1f7a9c26")]
+#[visit(optional)]
+pub struct ForceVolume {
+    kind: InheritableVariable<ForceKind>,
+    /// Force in local coordinates of the volume. Only its length is used for buoyancy.
+    force: InheritableVariable<Vector3<f32>>,
+    /// Bodies faster than this (horizontally) will not be accelerated any more.
+    max_speed: InheritableVariable<f32>,
+    /// Max random deviation of the force (in fractions of its length).
+    turbulence: InheritableVariable<f32>,
+    /// Time (in seconds) for which the volume is on. Zero means that the volume is always on.
+    on_time: InheritableVariable<f32>,
+    /// Time (in seconds) for which the volume is off.
+    off_time: InheritableVariable<f32>,
+}
+
+impl Default for ForceVolume {
+    fn default() -> Self {
+        Self {
+            kind: Default::default(),
+            force: Vector3::new(0.0, 0.0, 20.0).into(),
+            max_speed: 10.0.into(),
+            turbulence: 0.0.into(),
+            on_time: 0.0.into(),
+            off_time: 0.0.into(),
+        }
+    }
+}
+
+impl ForceVolume {
+    pub fn bounds(node: &Node) -> AxisAlignedBoundingBox {
+        AxisAlignedBoundingBox::unit().transform(&node.global_transform())
+    }
+
+    /// On/off cycle is derived from the match clock.
+    pub fn is_active(&self, time: f32) -> bool {
+        if *self.on_time <= 0.0 {
+            return true;
+        }
+        let cycle = *self.on_time + self.off_time.max(0.0);
+        time.max(0.0) % cycle < *self.on_time
+    }
+
+    /// Checks whether the volume can lift an actor up.
+    pub fn lifts(&self, node: &Node) -> bool {
+        match *self.kind {
+            ForceKind::Directional => node.global_rotation().transform_vector(&self.force).y > 0.0,
+            ForceKind::Buoyancy => self.force.norm() > 0.0,
+        }
+    }
+
+    /// Calculates force, that is applied to a body at the given point.
+    fn force_at(&self, node: &Node, point: Vector3<f32>, time: f32) -> Vector3<f32> {
+        let force = match *self.kind {
+            ForceKind::Directional => node.global_rotation().transform_vector(&self.force),
+            ForceKind::Buoyancy => {
+                let bounds = Self::bounds(node);
+                let height = (bounds.max.y - bounds.min.y).max(f32::EPSILON);
+                let depth = ((bounds.max.y - point.y) / height).clamp(0.0, 1.0);
+                Vector3::new(0.0, self.force.norm() * depth, 0.0)
+            }
+        };
+
+        // Smooth pseudo-random gusts.
+        let turbulence = Vector3::new(
+            (time * 3.1).sin(),
+            (time * 2.3 + 1.0).sin(),
+            (time * 1.7 + 2.0).cos(),
+        );
+        force + turbulence.scale(force.norm() * *self.turbulence)
+    }
+}
+
+impl ScriptTrait for ForceVolume {
+    fn on_init(&mut self, ctx: &mut ScriptContext) -> GameResult {
+        ctx.plugins
+            .get_mut::<Game>()
+            .level
+            .force_volumes
+            .insert(ctx.handle);
+        Ok(())
+    }
+
+    fn on_deinit(&mut self, ctx: &mut ScriptDeinitContext) -> GameResult {
+        ctx.plugins
+            .get_mut::<Game>()
+            .level
+            .force_volumes
+            .remove(&ctx.node_handle);
+        Ok(())
+    }
+
+    fn on_update(&mut self, ctx: &mut ScriptContext) -> GameResult {
+        let game = ctx.plugins.get::<Game>();
+        let time = game.level.match_time;
+        if game.is_client() || !self.is_active(time) {
+            return Ok(());
+        }
+
+        let graph = &ctx.scene.graph;
+        let this = graph.try_get(ctx.handle)?;
+        let bounds = Self::bounds(this);
+
+        for actor_handle in game.level.actors.iter() {
+            let actor = graph.try_get_script_component_of::<Actor>(*actor_handle)?;
+            let position = graph.try_get(actor.force_body(graph)?)?.global_position();
+            if bounds.is_contains_point(position) {
+                ctx.message_sender.send_to_target(
+                    *actor_handle,
+                    ActorMessage::Force {
+                        force: self.force_at(this, position, time),
+                        max_speed: *self.max_speed,
+                    },
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn volume(on_time: f32, off_time: f32) -> ForceVolume {
+        ForceVolume {
+            on_time: on_time.into(),
+            off_time: off_time.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn is_active_without_cycle() {
+        let volume = volume(0.0, 3.0);
+        assert!(volume.is_active(0.0));
+        assert!(volume.is_active(2.5));
+        assert!(volume.is_active(100.0));
+    }
+
+    #[test]
+    fn is_active_follows_cycle() {
+        let volume = volume(2.0, 1.0);
+        assert!(volume.is_active(0.0));
+        assert!(volume.is_active(1.9));
+        assert!(!volume.is_active(2.1));
+        assert!(!volume.is_active(2.9));
+        assert!(volume.is_active(3.1));
+        assert!(!volume.is_active(5.5));
+        // Negative time is treated as the start of the match.
+        assert!(volume.is_active(-1.0));
+    }
+
+    #[test]
+    fn is_active_with_negative_off_time() {
+        let volume = volume(1.0, -1.0);
+        assert!(volume.is_active(0.5));
+        assert!(volume.is_active(1.5));
+    }
+}
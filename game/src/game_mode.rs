@@ -0,0 +1,276 @@
+//! Game mode defines the rules of a match: how actors get their places and when the match ends.
+
+use crate::level::{Leaderboard, MatchStage};
+use fyrox::{
+    core::{pool::Handle, visitor::prelude::*},
+    plugin::error::GameResult,
+    scene::{graph::Graph, node::Node},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    fmt::{Display, Formatter},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, Visit)]
+pub enum GameMode {
+    /// Actors must reach the finish, the first one wins.
+    #[default]
+    Race,
+    /// Actors that fall off the platforms are eliminated. The round ends when only the given
+    /// amount of survivors is left, or when the time is out.
+    Survival { survivors: usize },
+}
+
+impl GameMode {
+    pub const PRESETS: [GameMode; 2] = [GameMode::Race, GameMode::Survival { survivors: 1 }];
+    pub const MAX_SURVIVORS: usize = 8;
+
+    /// Returns the same mode with the given survivor quota, the quota is used only by
+    /// [`GameMode::Survival`].
+    pub fn with_survivors(self, survivors: usize) -> Self {
+        match self {
+            GameMode::Race => GameMode::Race,
+            GameMode::Survival { .. } => GameMode::Survival {
+                survivors: survivors.clamp(1, Self::MAX_SURVIVORS),
+            },
+        }
+    }
+
+    /// Returns `true` if actors, that fell off the platforms, must be respawned at start points.
+    /// Otherwise, such actors are eliminated.
+    pub fn respawns_actors(&self) -> bool {
+        match self {
+            GameMode::Race => true,
+            GameMode::Survival { .. } => false,
+        }
+    }
+
+    pub fn has_finish_line(&self) -> bool {
+        match self {
+            GameMode::Race => true,
+            GameMode::Survival { .. } => false,
+        }
+    }
+
+    /// Checks whether the given amount of actors has made it through the round.
+    pub fn is_quota_reached(&self, leaderboard: &Leaderboard, quota: usize) -> bool {
+        match self {
+            GameMode::Race => leaderboard.finished_count() >= quota,
+            GameMode::Survival { .. } => leaderboard.alive_count() <= quota,
+        }
+    }
+
+    /// Checks whether the match should be ended earlier than the match timer runs out.
+    pub fn is_match_ended(&self, stage: MatchStage, leaderboard: &Leaderboard) -> bool {
+        match self {
+            GameMode::Race => false,
+            GameMode::Survival { survivors } => {
+                stage == MatchStage::Running && self.is_quota_reached(leaderboard, *survivors)
+            }
+        }
+    }
+
+    pub fn update_leaderboard(
+        &self,
+        leaderboard: &mut Leaderboard,
+        actors: &HashSet<Handle<Node>>,
+        finish_point: Option<Handle<Node>>,
+        graph: &Graph,
+    ) -> GameResult {
+        match self {
+            GameMode::Race => {
+                if let Some(finish_point) = finish_point {
+                    leaderboard.update(actors, finish_point, graph)?;
+                }
+            }
+            GameMode::Survival { .. } => leaderboard.update_survivors(actors, graph)?,
+        }
+        Ok(())
+    }
+
+    /// Sorts actors by their places, the best actor goes first.
+    pub fn ranking(&self, leaderboard: &Leaderboard) -> Vec<Handle<Node>> {
+        let mut entries = leaderboard.entries.values().collect::<Vec<_>>();
+        match self {
+            // Finished actors go first (in order of finishing), the rest are sorted by their
+            // distance to the finish.
+            GameMode::Race => entries.sort_by_key(|e| {
+                if e.finished {
+                    (0, e.finished_position)
+                } else {
+                    (1, e.real_time_position)
+                }
+            }),
+            // Survivors go first, the rest are sorted in reverse order of elimination.
+            GameMode::Survival { .. } => entries.sort_by_key(|e| {
+                if e.eliminated {
+                    (1, e.finished_position)
+                } else {
+                    (0, e.real_time_position)
+                }
+            }),
+        }
+        entries.into_iter().map(|e| e.actor).collect()
+    }
+}
+
+impl Display for GameMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameMode::Race => write!(f, "Race"),
+            GameMode::Survival { survivors } => write!(f, "Survival ({survivors} left)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::level::{LeaderBoardEntry, Level};
+
+    fn actor(index: u32) -> Handle<Node> {
+        Handle::new(index, 1)
+    }
+
+    fn leaderboard(entries: &[LeaderBoardEntry]) -> Leaderboard {
+        let mut leaderboard = Leaderboard::default();
+        for (index, entry) in entries.iter().enumerate() {
+            let actor = actor(index as u32 + 1);
+            leaderboard.entries.insert(
+                actor,
+                LeaderBoardEntry {
+                    actor,
+                    ..entry.clone()
+                },
+            );
+        }
+        leaderboard
+    }
+
+    fn finished(finished_position: usize) -> LeaderBoardEntry {
+        LeaderBoardEntry {
+            finished: true,
+            finished_position,
+            ..Default::default()
+        }
+    }
+
+    fn eliminated(finished_position: usize) -> LeaderBoardEntry {
+        LeaderBoardEntry {
+            eliminated: true,
+            finished_position,
+            ..Default::default()
+        }
+    }
+
+    fn running(real_time_position: usize) -> LeaderBoardEntry {
+        LeaderBoardEntry {
+            real_time_position,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn survivor_quota_is_clamped() {
+        assert_eq!(GameMode::Race.with_survivors(3), GameMode::Race);
+        let survival = GameMode::Survival { survivors: 1 };
+        assert_eq!(
+            survival.with_survivors(0),
+            GameMode::Survival { survivors: 1 }
+        );
+        assert_eq!(
+            survival.with_survivors(4),
+            GameMode::Survival { survivors: 4 }
+        );
+        assert_eq!(
+            survival.with_survivors(100),
+            GameMode::Survival {
+                survivors: GameMode::MAX_SURVIVORS
+            }
+        );
+    }
+
+    #[test]
+    fn race_ranking_puts_finished_actors_first() {
+        let leaderboard = leaderboard(&[running(0), finished(2), running(1), finished(1)]);
+        assert_eq!(
+            GameMode::Race.ranking(&leaderboard),
+            [actor(4), actor(2), actor(1), actor(3)]
+        );
+    }
+
+    #[test]
+    fn survival_ranking_puts_survivors_first() {
+        // The first eliminated actor takes the last place.
+        let leaderboard = leaderboard(&[eliminated(4), running(0), eliminated(3)]);
+        assert_eq!(
+            GameMode::Survival { survivors: 1 }.ranking(&leaderboard),
+            [actor(2), actor(3), actor(1)]
+        );
+    }
+
+    #[test]
+    fn race_quota() {
+        let leaderboard = leaderboard(&[finished(1), running(0), running(1)]);
+        assert!(GameMode::Race.is_quota_reached(&leaderboard, 0));
+        assert!(GameMode::Race.is_quota_reached(&leaderboard, 1));
+        assert!(!GameMode::Race.is_quota_reached(&leaderboard, 2));
+        // Races end only by the match timer.
+        assert!(!GameMode::Race.is_match_ended(MatchStage::Running, &leaderboard));
+    }
+
+    #[test]
+    fn survival_quota() {
+        let mode = GameMode::Survival { survivors: 1 };
+        let leaderboard = leaderboard(&[running(0), eliminated(3), running(0)]);
+        assert!(!mode.is_quota_reached(&leaderboard, 0));
+        assert!(!mode.is_quota_reached(&leaderboard, 1));
+        assert!(mode.is_quota_reached(&leaderboard, 2));
+        // The quota is reached from the start, when there are not enough participants.
+        assert!(mode.is_quota_reached(&leaderboard, 3));
+        assert!(mode.is_quota_reached(&leaderboard, 10));
+        assert!(!mode.is_match_ended(MatchStage::Running, &leaderboard));
+
+        let leaderboard = self::leaderboard(&[eliminated(2), running(0), eliminated(3)]);
+        assert!(mode.is_match_ended(MatchStage::Running, &leaderboard));
+        // The match cannot end before it has started.
+        assert!(!mode.is_match_ended(MatchStage::Countdown { time_left: 1.0 }, &leaderboard));
+        assert!(!mode.is_match_ended(MatchStage::WaitingForPlayers, &leaderboard));
+
+        let everyone_survives = GameMode::Survival { survivors: 3 };
+        assert!(everyone_survives.is_match_ended(
+            MatchStage::Running,
+            &self::leaderboard(&[running(0), running(0), running(0)])
+        ));
+    }
+
+    #[test]
+    fn fallen_actors_take_places_from_the_end() {
+        let mut level = Level {
+            game_mode: GameMode::Survival { survivors: 1 },
+            ..Default::default()
+        };
+        level.actors.extend((1..=3).map(actor));
+
+        assert!(!level.on_actor_fell(actor(2)));
+        assert!(!level.on_actor_fell(actor(3)));
+        // Falling twice does not change the place.
+        assert!(!level.on_actor_fell(actor(2)));
+
+        let entries = &level.leaderboard.entries;
+        assert_eq!(entries[&actor(2)].finished_position, 3);
+        assert_eq!(entries[&actor(3)].finished_position, 2);
+        assert!(entries[&actor(2)].eliminated);
+        assert_eq!(level.actors.len(), 1);
+    }
+
+    #[test]
+    fn fallen_actors_respawn_in_race() {
+        let mut level = Level::default();
+        level.actors.insert(actor(1));
+        assert!(level.on_actor_fell(actor(1)));
+        assert!(level.actors.contains(&actor(1)));
+        assert!(level.leaderboard.entries.is_empty());
+    }
+}
@@ -0,0 +1,201 @@
+//! Gamepad input.
+
+use fyrox::core::{algebra::Vector2, log::Log};
+use gilrs::{Axis, Button, EventType, GamepadId, Gilrs};
+
+/// An action bound to a gamepad button.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GamepadAction {
+    Jump,
+    Interact,
+    Grab,
+    Shove,
+    Dive,
+    Menu,
+}
+
+impl GamepadAction {
+    fn from_button(button: Button) -> Option<Self> {
+        match button {
+            Button::South => Some(Self::Jump),
+            Button::North => Some(Self::Interact),
+            Button::RightTrigger2 => Some(Self::Grab),
+            Button::West => Some(Self::Shove),
+            Button::East => Some(Self::Dive),
+            Button::Start => Some(Self::Menu),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct GamepadEvent {
+    pub action: GamepadAction,
+    pub pressed: bool,
+}
+
+/// A state of a single connected gamepad.
+#[derive(Debug)]
+pub struct GamepadPad {
+    id: GamepadId,
+    events: Vec<GamepadEvent>,
+    movement: Vector2<f32>,
+    camera: Vector2<f32>,
+}
+
+impl GamepadPad {
+    /// Button events of the current frame.
+    pub fn events(&self) -> &[GamepadEvent] {
+        &self.events
+    }
+
+    /// Left stick, `x` goes right and `y` goes forward.
+    pub fn movement(&self) -> Vector2<f32> {
+        self.movement
+    }
+
+    /// Right stick, `x` goes right and `y` goes up.
+    pub fn camera(&self) -> Vector2<f32> {
+        self.camera
+    }
+}
+
+/// Polls connected gamepads once per frame. Gamepads are indexed in the order of connection, so
+/// every local player can have its own gamepad.
+#[derive(Debug, Default)]
+pub struct Gamepad {
+    gilrs: Option<Gilrs>,
+    pads: Vec<GamepadPad>,
+}
+
+impl Gamepad {
+    pub fn new() -> Self {
+        let gilrs = match Gilrs::new() {
+            Ok(gilrs) => Some(gilrs),
+            Err(err) => {
+                Log::warn(format!("Gamepads are not supported. Reason: {err:?}"));
+                None
+            }
+        };
+        Self {
+            gilrs,
+            ..Default::default()
+        }
+    }
+
+    /// Collects the button events of the current frame and reads the sticks.
+    pub fn poll(&mut self, deadzone: f32) {
+        let Some(gilrs) = self.gilrs.as_mut() else {
+            return;
+        };
+
+        // Keep the order of the gamepads, that are still connected, and add the new ones.
+        self.pads
+            .retain(|pad| gilrs.connected_gamepad(pad.id).is_some());
+        for (id, _) in gilrs.gamepads() {
+            if !self.pads.iter().any(|pad| pad.id == id) {
+                self.pads.push(GamepadPad {
+                    id,
+                    events: Default::default(),
+                    movement: Default::default(),
+                    camera: Default::default(),
+                });
+            }
+        }
+        for pad in self.pads.iter_mut() {
+            pad.events.clear();
+        }
+
+        while let Some(event) = gilrs.next_event() {
+            let (button, pressed) = match event.event {
+                EventType::ButtonPressed(button, _) => (button, true),
+                EventType::ButtonReleased(button, _) => (button, false),
+                _ => continue,
+            };
+            let action = GamepadAction::from_button(button);
+            let pad = self.pads.iter_mut().find(|pad| pad.id == event.id);
+            if let (Some(action), Some(pad)) = (action, pad) {
+                pad.events.push(GamepadEvent { action, pressed });
+            }
+        }
+
+        for pad in self.pads.iter_mut() {
+            let Some(gamepad) = gilrs.connected_gamepad(pad.id) else {
+                continue;
+            };
+            pad.movement = apply_deadzone(
+                Vector2::new(
+                    gamepad.value(Axis::LeftStickX),
+                    gamepad.value(Axis::LeftStickY),
+                ),
+                deadzone,
+            );
+            pad.camera = apply_deadzone(
+                Vector2::new(
+                    gamepad.value(Axis::RightStickX),
+                    gamepad.value(Axis::RightStickY),
+                ),
+                deadzone,
+            );
+        }
+    }
+
+    /// Returns the gamepad with the given index, gamepads are indexed in the order of connection.
+    pub fn pad(&self, index: usize) -> Option<&GamepadPad> {
+        self.pads.get(index)
+    }
+
+    /// Checks whether the action was pressed on any gamepad in the current frame.
+    pub fn is_pressed(&self, action: GamepadAction) -> bool {
+        self.pads
+            .iter()
+            .flat_map(|pad| pad.events.iter())
+            .any(|e| e.action == action && e.pressed)
+    }
+}
+
+/// Cuts off the stick values near the center and rescales the rest to `[0; 1]` range, so the
+/// speed grows smoothly from the edge of the deadzone.
+fn apply_deadzone(stick: Vector2<f32>, deadzone: f32) -> Vector2<f32> {
+    let length = stick.norm();
+    if length <= deadzone || deadzone >= 1.0 {
+        return Vector2::default();
+    }
+    let scaled_length = ((length - deadzone) / (1.0 - deadzone)).min(1.0);
+    stick.scale(scaled_length / length)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn stick_inside_deadzone_is_ignored() {
+        assert_eq!(
+            apply_deadzone(Vector2::new(0.1, 0.1), 0.2),
+            Vector2::default()
+        );
+        // The edge of the deadzone is still inside.
+        assert_eq!(
+            apply_deadzone(Vector2::new(0.2, 0.0), 0.2),
+            Vector2::default()
+        );
+    }
+
+    #[test]
+    fn stick_is_rescaled_outside_deadzone() {
+        let stick = apply_deadzone(Vector2::new(0.0, 0.6), 0.2);
+        assert!((stick.y - 0.5).abs() < 1.0e-6);
+        assert_eq!(stick.x, 0.0);
+    }
+
+    #[test]
+    fn full_deflection_keeps_direction() {
+        let stick = apply_deadzone(Vector2::new(1.0, 0.0), 0.2);
+        assert!((stick.x - 1.0).abs() < 1.0e-6);
+        // Diagonals of square gates go beyond the unit circle, but the length is clamped.
+        let stick = apply_deadzone(Vector2::new(1.0, 1.0), 0.2);
+        assert!((stick.norm() - 1.0).abs() < 1.0e-6);
+        assert!((stick.x - stick.y).abs() < 1.0e-6);
+    }
+}
@@ -0,0 +1,171 @@
+//! Hazards of a level.
+//!
+//! [`Hazard`] marks a rigid body (a cannon ball, a blade, spikes), that hits actors. Such hits are
+//! blocked by the shield power-up.
+//!
+//! Rising hazard (lava, water, etc.) is a surface, that rises over the match time. Any actor
+//! (player or bot) below the surface will be respawned or eliminated, just like in a respawn zone.
+//! Bounds of the hazard is a unit box transformed by the global transform of the node, the surface
+//! is the top of the box.
+
+use crate::{
+    explosive::Explosive,
+    hinge::{Pendulum, Sweeper},
+    respawn::Respawner,
+    Game,
+};
+use fyrox::graph::SceneGraph;
+use fyrox::plugin::error::GameResult;
+use fyrox::{
+    core::{
+        algebra::Vector3, math::aabb::AxisAlignedBoundingBox, pool::Handle, reflect::prelude::*,
+        type_traits::prelude::*, variable::InheritableVariable, visitor::prelude::*,
+    },
+    resource::curve::CurveResource,
+    scene::{graph::Graph, node::Node},
+    script::{ScriptContext, ScriptTrait},
+};
+
+#[derive(Clone, Default, Debug, Visit, Reflect, TypeUuidProvider, ComponentProvider)]
+#[type_uuid(id = "e4a1c9d2-6b37-4f58-9a0e-3d5f7b2c8146")]
+#[visit(optional)]
+pub struct Hazard {}
+
+impl ScriptTrait for Hazard {}
+
+impl Hazard {
+    /// Checks whether the given rigid body is a hazard. The script could be assigned to the body
+    /// itself or to its parent. Bombs, pendulums and sweepers are hazards too.
+    pub fn is_hazard_body(body: Handle<Node>, graph: &Graph) -> bool {
+        let Ok(node) = graph.try_get(body) else {
+            return false;
+        };
+        [body, node.parent()].into_iter().any(|handle| {
+            graph.try_get(handle).is_ok_and(|node| {
+                node.try_get_script::<Hazard>().is_some()
+                    || node.try_get_script::<Explosive>().is_some()
+                    || node.try_get_script::<Pendulum>().is_some()
+                    || node.try_get_script::<Sweeper>().is_some()
+            })
+        })
+    }
+}
+
+#[derive(Clone, Debug, Visit, Reflect, TypeUuidProvider, ComponentProvider)]
+#[type_uuid(id = "9b3f7c2e-58d1-4a06-b4e9-7d2a6c1f8e35")]
+#[visit(optional)]
+pub struct RisingHazard {
+    /// Height of the surface (relative to its initial position) over the match time (in seconds).
+    curve: InheritableVariable<Option<CurveResource>>,
+    /// Rise speed (in units per second), that is used when there's no curve.
+    rise_speed: InheritableVariable<f32>,
+    /// Max height of the surface relative to its initial position.
+    max_height: InheritableVariable<f32>,
+    /// How much faster the surface rises during sudden death.
+    sudden_death_factor: InheritableVariable<f32>,
+    #[reflect(hidden)]
+    elapsed: f32,
+    #[reflect(hidden)]
+    #[visit(skip)]
+    initial_position: Vector3<f32>,
+    #[reflect(hidden)]
+    #[visit(skip)]
+    respawner: Respawner,
+}
+
+impl Default for RisingHazard {
+    fn default() -> Self {
+        Self {
+            curve: Default::default(),
+            rise_speed: 0.1.into(),
+            max_height: 50.0.into(),
+            sudden_death_factor: 3.0.into(),
+            elapsed: 0.0,
+            initial_position: Default::default(),
+            respawner: Default::default(),
+        }
+    }
+}
+
+impl RisingHazard {
+    fn height_at(&self, time: f32) -> f32 {
+        let height = match self.curve.as_ref() {
+            Some(curve) => curve.data_ref().curve.value_at(time),
+            None => *self.rise_speed * time,
+        };
+        height.clamp(0.0, self.max_height.max(0.0))
+    }
+}
+
+impl ScriptTrait for RisingHazard {
+    fn on_start(&mut self, ctx: &mut ScriptContext) -> GameResult {
+        self.initial_position = **ctx
+            .scene
+            .graph
+            .try_get(ctx.handle)?
+            .local_transform()
+            .position();
+        Ok(())
+    }
+
+    fn on_update(&mut self, ctx: &mut ScriptContext) -> GameResult {
+        // The position of the surface is replicated to clients with the rest of the scene.
+        let game = ctx.plugins.get::<Game>();
+        if game.is_client() {
+            return Ok(());
+        }
+
+        if !game.level.is_movement_locked() {
+            self.elapsed += if game.level.is_time_critical() {
+                ctx.dt * *self.sudden_death_factor
+            } else {
+                ctx.dt
+            };
+        }
+
+        let height = self.height_at(self.elapsed);
+        let this = ctx.scene.graph.try_get_mut(ctx.handle)?;
+        this.local_transform_mut().set_position(Vector3::new(
+            self.initial_position.x,
+            self.initial_position.y + height,
+            self.initial_position.z,
+        ));
+
+        let self_bounds = AxisAlignedBoundingBox::unit().transform(&this.global_transform());
+        self.respawner.respawn_touching_actors(&self_bounds, ctx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn surface_rises_at_constant_speed() {
+        let hazard = RisingHazard {
+            rise_speed: 0.5.into(),
+            max_height: 10.0.into(),
+            ..Default::default()
+        };
+        assert_eq!(hazard.height_at(0.0), 0.0);
+        assert_eq!(hazard.height_at(4.0), 2.0);
+        assert_eq!(hazard.height_at(20.0), 10.0);
+        // The surface stops at the max height.
+        assert_eq!(hazard.height_at(100.0), 10.0);
+    }
+
+    #[test]
+    fn surface_never_goes_below_initial_position() {
+        let sinking = RisingHazard {
+            rise_speed: (-1.0).into(),
+            ..Default::default()
+        };
+        assert_eq!(sinking.height_at(5.0), 0.0);
+
+        let no_room = RisingHazard {
+            max_height: (-1.0).into(),
+            ..Default::default()
+        };
+        assert_eq!(no_room.height_at(5.0), 0.0);
+    }
+}
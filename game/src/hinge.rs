@@ -0,0 +1,271 @@
+//! Pendulums and sweepers are dynamic rigid bodies attached to the world by a hinge (revolute)
+//! joint. The server drives the bodies by their angular velocity, so they still interact with
+//! actors (players or bots) like any other physical object and knock them down on a hard hit. The
+//! target angle is derived from the match clock, and clients receive transforms from the server,
+//! so the motion stays in the same phase on every peer.
+
+use crate::Game;
+use fyrox::graph::SceneGraph;
+use fyrox::plugin::error::GameResult;
+use fyrox::{
+    core::{
+        algebra::{UnitQuaternion, Vector3},
+        pool::Handle,
+        reflect::prelude::*,
+        type_traits::prelude::*,
+        variable::InheritableVariable,
+        visitor::prelude::*,
+    },
+    scene::{graph::Graph, node::Node, rigidbody::RigidBody},
+    script::{ScriptContext, ScriptTrait},
+};
+use std::f32::consts::PI;
+
+/// Wraps the angle (in radians) to the -PI..PI range.
+fn wrap_angle(angle: f32) -> f32 {
+    (angle + PI).rem_euclid(2.0 * PI) - PI
+}
+
+/// State of a hinge, that is shared between pendulums and sweepers.
+#[derive(Clone, Debug, Default, Reflect)]
+struct Hinge {
+    initial_rotation: UnitQuaternion<f32>,
+}
+
+impl Hinge {
+    fn init(&mut self, body: Handle<Node>, graph: &Graph) -> GameResult {
+        self.initial_rotation = graph.try_get_of_type::<RigidBody>(body)?.global_rotation();
+        Ok(())
+    }
+
+    /// Sets angular velocity of the body, so it follows the target angle (in radians) around the
+    /// axis (in local coordinates of the body).
+    fn drive(
+        &self,
+        body: Handle<Node>,
+        axis: Vector3<f32>,
+        target_angle: f32,
+        target_velocity: f32,
+        stiffness: f32,
+        graph: &mut Graph,
+    ) -> GameResult {
+        let axis = axis.try_normalize(f32::EPSILON).unwrap_or_else(Vector3::x);
+        let world_axis = self.initial_rotation.transform_vector(&axis);
+
+        let rigid_body = graph.try_get_mut_of_type::<RigidBody>(body)?;
+        let delta = self.initial_rotation.inverse() * rigid_body.global_rotation();
+        let angle = delta.scaled_axis().dot(&axis);
+
+        // Wrap the error, so a body spinning for a long time does not try to unwind.
+        let error = wrap_angle(target_angle - angle);
+        rigid_body.set_ang_vel(world_axis.scale(target_velocity + stiffness * error));
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Visit, Reflect, TypeUuidProvider, ComponentProvider)]
+#[type_uuid(id = "c2e85a7d-1f46-4b39-9d0a-6e8b3f5c2d71")]
+#[visit(optional)]
+pub struct Pendulum {
+    /// A body to swing. The node of the script is used, if not set.
+    body: InheritableVariable<Handle<Node>>,
+    /// Hinge axis in local coordinates of the body.
+    axis: InheritableVariable<Vector3<f32>>,
+    /// Max swing angle (in degrees) from the initial orientation.
+    amplitude: InheritableVariable<f32>,
+    /// Time (in seconds) of a full swing cycle.
+    period: InheritableVariable<f32>,
+    /// Time offset (in seconds), that allows to swing neighbouring pendulums in different phases.
+    phase: InheritableVariable<f32>,
+    /// How hard the body is pulled back to the target angle after a collision.
+    stiffness: InheritableVariable<f32>,
+    #[reflect(hidden)]
+    #[visit(skip)]
+    hinge: Hinge,
+}
+
+impl Default for Pendulum {
+    fn default() -> Self {
+        Self {
+            body: Default::default(),
+            axis: Vector3::x().into(),
+            amplitude: 60.0.into(),
+            period: 3.0.into(),
+            phase: 0.0.into(),
+            stiffness: 10.0.into(),
+            hinge: Default::default(),
+        }
+    }
+}
+
+impl Pendulum {
+    /// Returns the target angle (in radians) and angular velocity at the given match time.
+    fn target_at(&self, time: f32) -> (f32, f32) {
+        let frequency = 2.0 * PI / self.period.max(0.1);
+        let amplitude = self.amplitude.to_radians();
+        let phase = frequency * (time + *self.phase);
+        (amplitude * phase.sin(), amplitude * frequency * phase.cos())
+    }
+
+    fn body(&self, handle: Handle<Node>) -> Handle<Node> {
+        if self.body.is_some() {
+            *self.body
+        } else {
+            handle
+        }
+    }
+}
+
+impl ScriptTrait for Pendulum {
+    fn on_start(&mut self, ctx: &mut ScriptContext) -> GameResult {
+        self.hinge.init(self.body(ctx.handle), &ctx.scene.graph)
+    }
+
+    fn on_update(&mut self, ctx: &mut ScriptContext) -> GameResult {
+        let game = ctx.plugins.get::<Game>();
+        if game.is_client() {
+            return Ok(());
+        }
+
+        let (angle, velocity) = self.target_at(game.level.match_time);
+        self.hinge.drive(
+            self.body(ctx.handle),
+            *self.axis,
+            angle,
+            velocity,
+            *self.stiffness,
+            &mut ctx.scene.graph,
+        )
+    }
+}
+
+#[derive(Clone, Debug, Visit, Reflect, TypeUuidProvider, ComponentProvider)]
+#[type_uuid(id = "4a7f1c9e-2b58-4d63-8e0f-3c6d9a1b5e42")]
+#[visit(optional)]
+pub struct Sweeper {
+    /// A body to rotate. The node of the script is used, if not set.
+    body: InheritableVariable<Handle<Node>>,
+    /// Hinge axis in local coordinates of the body.
+    axis: InheritableVariable<Vector3<f32>>,
+    /// Rotation speed (in degrees per second) of the motor.
+    speed: InheritableVariable<f32>,
+    /// Initial angle (in degrees).
+    phase: InheritableVariable<f32>,
+    /// How hard the body is pulled back to the target angle after a collision.
+    stiffness: InheritableVariable<f32>,
+    #[reflect(hidden)]
+    #[visit(skip)]
+    hinge: Hinge,
+}
+
+impl Default for Sweeper {
+    fn default() -> Self {
+        Self {
+            body: Default::default(),
+            axis: Vector3::y().into(),
+            speed: 90.0.into(),
+            phase: 0.0.into(),
+            stiffness: 10.0.into(),
+            hinge: Default::default(),
+        }
+    }
+}
+
+impl Sweeper {
+    /// Returns the target angle (in radians) and angular velocity at the given match time.
+    fn target_at(&self, time: f32) -> (f32, f32) {
+        let speed = self.speed.to_radians();
+        (speed * time + self.phase.to_radians(), speed)
+    }
+
+    fn body(&self, handle: Handle<Node>) -> Handle<Node> {
+        if self.body.is_some() {
+            *self.body
+        } else {
+            handle
+        }
+    }
+}
+
+impl ScriptTrait for Sweeper {
+    fn on_start(&mut self, ctx: &mut ScriptContext) -> GameResult {
+        self.hinge.init(self.body(ctx.handle), &ctx.scene.graph)
+    }
+
+    fn on_update(&mut self, ctx: &mut ScriptContext) -> GameResult {
+        let game = ctx.plugins.get::<Game>();
+        if game.is_client() {
+            return Ok(());
+        }
+
+        let (angle, velocity) = self.target_at(game.level.match_time);
+        self.hinge.drive(
+            self.body(ctx.handle),
+            *self.axis,
+            angle,
+            velocity,
+            *self.stiffness,
+            &mut ctx.scene.graph,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 1.0e-4, "{a} != {b}");
+    }
+
+    #[test]
+    fn angle_error_takes_the_short_way() {
+        assert_near(wrap_angle(0.0), 0.0);
+        assert_near(wrap_angle(1.0), 1.0);
+        assert_near(wrap_angle(-1.0), -1.0);
+        assert_near(wrap_angle(2.0 * PI + 0.5), 0.5);
+        assert_near(wrap_angle(-2.0 * PI - 0.5), -0.5);
+        assert_near(wrap_angle(1.5 * PI), -0.5 * PI);
+        // A sweeper, that made ten turns, does not try to unwind.
+        assert_near(wrap_angle(20.0 * PI + 0.25), 0.25);
+    }
+
+    #[test]
+    fn pendulum_swings_within_amplitude() {
+        let pendulum = Pendulum {
+            amplitude: 90.0.into(),
+            period: 4.0.into(),
+            ..Default::default()
+        };
+        let (angle, velocity) = pendulum.target_at(0.0);
+        assert_near(angle, 0.0);
+        assert_near(velocity, PI / 2.0 * PI / 2.0);
+        // The highest point is at a quarter of the period, the body stops there.
+        let (angle, velocity) = pendulum.target_at(1.0);
+        assert_near(angle, PI / 2.0);
+        assert_near(velocity, 0.0);
+        assert_near(pendulum.target_at(3.0).0, -PI / 2.0);
+        assert_near(pendulum.target_at(4.0).0, 0.0);
+
+        // The phase shifts the swing in time.
+        let shifted = Pendulum {
+            phase: 1.0.into(),
+            ..pendulum.clone()
+        };
+        assert_near(shifted.target_at(0.0).0, pendulum.target_at(1.0).0);
+    }
+
+    #[test]
+    fn sweeper_rotates_at_constant_speed() {
+        let sweeper = Sweeper {
+            speed: 90.0.into(),
+            phase: 45.0.into(),
+            ..Default::default()
+        };
+        let (angle, velocity) = sweeper.target_at(0.0);
+        assert_near(angle, PI / 4.0);
+        assert_near(velocity, PI / 2.0);
+        assert_near(sweeper.target_at(2.0).0, PI + PI / 4.0);
+    }
+}
@@ -0,0 +1,319 @@
+//! Interactable objects (levers, pressure plates, keys, doors) and signals that connect them with
+//! other objects on a level. An interactable sends a signal to its targets every time when its
+//! state changes, the targets are configured in the editor.
+
+use crate::{actor::Actor, net::ServerMessage, server::Server, Game};
+use fyrox::graph::SceneGraph;
+use fyrox::plugin::error::GameResult;
+use fyrox::{
+    core::{
+        algebra::{UnitQuaternion, Vector3},
+        log::Log,
+        pool::Handle,
+        reflect::prelude::*,
+        type_traits::prelude::*,
+        variable::InheritableVariable,
+        visitor::prelude::*,
+    },
+    scene::{graph::Graph, node::Node, sound::Sound},
+    script::{
+        ScriptContext, ScriptDeinitContext, ScriptMessageContext, ScriptMessagePayload,
+        ScriptMessageSender, ScriptTrait,
+    },
+};
+use strum_macros::{AsRefStr, EnumString, VariantNames};
+
+/// A signal, that is sent by an interactable to its targets.
+#[derive(Debug, Clone, Copy, ScriptMessagePayload)]
+pub struct SignalMessage {
+    pub active: bool,
+}
+
+#[derive(
+    Default,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Visit,
+    Reflect,
+    TypeUuidProvider,
+    AsRefStr,
+    EnumString,
+    VariantNames,
+)]
+#[type_uuid(id = "c4d2a7e1-5b36-4f08-9e1a-8b7c6d5e4f30")]
+pub enum InteractableKind {
+    /// Toggles its state, when an actor uses it.
+    #[default]
+    Lever,
+    /// Active while there's at least one actor on it.
+    PressurePlate,
+    /// Taken by the first actor that touches it. The actor can open doors with the same key name.
+    Key,
+    /// Opened by a signal or by an actor, that has the key with the required name.
+    Door,
+}
+
+#[derive(Clone, Debug, Visit, Reflect, TypeUuidProvider, ComponentProvider)]
+#[type_uuid(id = "7e1f3b5a-9c2d-4e6f-8a0b-1d3c5e7f9a2b")]
+#[visit(optional)]
+pub struct Interactable {
+    kind: InheritableVariable<InteractableKind>,
+    /// Objects, that will receive a signal when the state of this object changes.
+    targets: InheritableVariable<Vec<Handle<Node>>>,
+    /// Name of the key, that is given by a key or required to open a door. Doors without a key
+    /// name can only be opened by a signal.
+    key_name: InheritableVariable<String>,
+    /// Max distance between an actor and the object for interaction.
+    interaction_radius: InheritableVariable<f32>,
+    /// A node, that moves when the object becomes active (a lever handle, a door wing, etc.).
+    moving_part: InheritableVariable<Handle<Node>>,
+    /// Offset of the moving part in active state.
+    active_offset: InheritableVariable<Vector3<f32>>,
+    /// Rotation (Euler angles in degrees) of the moving part in active state.
+    active_rotation: InheritableVariable<Vector3<f32>>,
+    /// Time (in seconds) of transition between the states.
+    transition_time: InheritableVariable<f32>,
+    use_sound: InheritableVariable<Handle<Sound>>,
+    #[reflect(hidden)]
+    active: bool,
+    #[reflect(hidden)]
+    #[visit(skip)]
+    transition: f32,
+    #[reflect(hidden)]
+    #[visit(skip)]
+    initial_position: Vector3<f32>,
+    #[reflect(hidden)]
+    #[visit(skip)]
+    initial_rotation: UnitQuaternion<f32>,
+}
+
+impl Default for Interactable {
+    fn default() -> Self {
+        Self {
+            kind: Default::default(),
+            targets: Default::default(),
+            key_name: Default::default(),
+            interaction_radius: 1.5.into(),
+            moving_part: Default::default(),
+            active_offset: Default::default(),
+            active_rotation: Default::default(),
+            transition_time: 0.5.into(),
+            use_sound: Default::default(),
+            active: false,
+            transition: 0.0,
+            initial_position: Default::default(),
+            initial_rotation: Default::default(),
+        }
+    }
+}
+
+impl Interactable {
+    pub fn kind(&self) -> InteractableKind {
+        *self.kind
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub fn interaction_radius(&self) -> f32 {
+        *self.interaction_radius
+    }
+
+    /// Taken keys are hidden.
+    pub fn is_visible(&self) -> bool {
+        !(*self.kind == InteractableKind::Key && self.active)
+    }
+
+    /// Checks whether the given actor is able to change the state of the object by using it.
+    pub fn can_be_used_by(&self, actor: &Actor) -> bool {
+        match *self.kind {
+            InteractableKind::Lever => true,
+            InteractableKind::Door => {
+                !self.active && !self.key_name.is_empty() && actor.keys.contains(&*self.key_name)
+            }
+            InteractableKind::PressurePlate | InteractableKind::Key => false,
+        }
+    }
+
+    /// Sets the state of the object on a client.
+    pub fn sync_state(&mut self, active: bool) {
+        self.active = active;
+    }
+
+    /// Changes the state of the object and sends a signal to its targets.
+    fn set_active(
+        &mut self,
+        active: bool,
+        handle: Handle<Node>,
+        graph: &mut Graph,
+        message_sender: &ScriptMessageSender,
+        server: Option<&mut Server>,
+    ) -> GameResult {
+        if self.active == active {
+            return Ok(());
+        }
+        self.active = active;
+
+        for target in self.targets.iter() {
+            message_sender.send_to_target(*target, SignalMessage { active });
+        }
+
+        if let Ok(sound) = graph.try_get_mut(*self.use_sound) {
+            sound.set_playback_time(0.0);
+            sound.play();
+        }
+
+        let node = graph.try_get_mut(handle)?;
+        node.set_visibility(self.is_visible());
+
+        if let Some(server) = server {
+            server.broadcast_message_to_clients(ServerMessage::InteractableState {
+                node: node.instance_id(),
+                active,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn update_moving_part(&mut self, graph: &mut Graph, dt: f32) -> GameResult {
+        if self.moving_part.is_none() {
+            return Ok(());
+        }
+
+        let step = dt / self.transition_time.max(f32::EPSILON);
+        self.transition = if self.active {
+            (self.transition + step).min(1.0)
+        } else {
+            (self.transition - step).max(0.0)
+        };
+
+        let angles = self.active_rotation.scale(self.transition);
+        let rotation = UnitQuaternion::from_euler_angles(
+            angles.x.to_radians(),
+            angles.y.to_radians(),
+            angles.z.to_radians(),
+        );
+        graph
+            .try_get_mut(*self.moving_part)?
+            .local_transform_mut()
+            .set_position(self.initial_position + self.active_offset.scale(self.transition))
+            .set_rotation(self.initial_rotation * rotation);
+
+        Ok(())
+    }
+}
+
+impl ScriptTrait for Interactable {
+    fn on_init(&mut self, ctx: &mut ScriptContext) -> GameResult {
+        ctx.plugins
+            .get_mut::<Game>()
+            .level
+            .interactables
+            .insert(ctx.handle);
+        Ok(())
+    }
+
+    fn on_start(&mut self, ctx: &mut ScriptContext) -> GameResult {
+        ctx.message_dispatcher
+            .subscribe_to::<SignalMessage>(ctx.handle);
+
+        if let Ok(moving_part) = ctx.scene.graph.try_get(*self.moving_part) {
+            self.initial_position = **moving_part.local_transform().position();
+            self.initial_rotation = **moving_part.local_transform().rotation();
+        }
+        Ok(())
+    }
+
+    fn on_deinit(&mut self, ctx: &mut ScriptDeinitContext) -> GameResult {
+        ctx.plugins
+            .get_mut::<Game>()
+            .level
+            .interactables
+            .remove(&ctx.node_handle);
+        Ok(())
+    }
+
+    fn on_update(&mut self, ctx: &mut ScriptContext) -> GameResult {
+        let game = ctx.plugins.get_mut::<Game>();
+        if game.is_client() {
+            return Ok(());
+        }
+
+        let graph = &mut ctx.scene.graph;
+        let self_position = graph.try_get(ctx.handle)?.global_position();
+
+        let mut nearby_actors = Vec::new();
+        for actor_handle in game.level.actors.iter() {
+            let actor = graph.try_get_script_component_of::<Actor>(*actor_handle)?;
+            let actor_position = graph.try_get(actor.rigid_body)?.global_position();
+            if actor_position.metric_distance(&self_position) <= *self.interaction_radius {
+                nearby_actors.push(*actor_handle);
+            }
+        }
+
+        let new_state = match *self.kind {
+            InteractableKind::PressurePlate => !nearby_actors.is_empty(),
+            InteractableKind::Key => match nearby_actors.first() {
+                Some(actor) if !self.active => {
+                    graph
+                        .try_get_script_component_of_mut::<Actor>(*actor)?
+                        .keys
+                        .push(self.key_name.clone_inner());
+                    true
+                }
+                _ => self.active,
+            },
+            InteractableKind::Lever | InteractableKind::Door => {
+                let mut new_state = self.active;
+                for actor in nearby_actors {
+                    let actor = graph.try_get_script_component_of_mut::<Actor>(actor)?;
+                    if actor.wants_to_interact() && self.can_be_used_by(actor) {
+                        actor.consume_interaction();
+                        new_state = !self.active;
+                        break;
+                    }
+                }
+                new_state
+            }
+        };
+
+        self.set_active(
+            new_state,
+            ctx.handle,
+            graph,
+            ctx.message_sender,
+            game.server.as_mut(),
+        )?;
+        self.update_moving_part(graph, ctx.dt)?;
+
+        Ok(())
+    }
+
+    fn on_message(
+        &mut self,
+        message: &mut dyn ScriptMessagePayload,
+        ctx: &mut ScriptMessageContext,
+    ) -> GameResult {
+        let Some(signal) = message.downcast_ref::<SignalMessage>() else {
+            return Ok(());
+        };
+
+        if *self.kind == InteractableKind::Key {
+            Log::warn("Keys cannot receive signals!");
+            return Ok(());
+        }
+
+        // The new state will be passed further to the targets of this object.
+        self.set_active(
+            signal.active,
+            ctx.handle,
+            &mut ctx.scene.graph,
+            ctx.message_sender,
+            ctx.plugins.get_mut::<Game>().server.as_mut(),
+        )
+    }
+}
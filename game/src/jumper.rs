@@ -1,61 +1,239 @@
-//! Jumper is platform that pushes actors (players or bots) up.
+//! Jumper is platform that pushes actors (players or bots) up, or launches them along a ballistic
+//! arc to a landing point.
 
-use crate::{Bot, Game, Player};
+use crate::{actor::Actor, Game};
 use fyrox::graph::SceneGraph;
-use fyrox::plugin::error::GameResult;
+use fyrox::plugin::error::{GameError, GameResult};
 use fyrox::{
     core::{
-        algebra::Vector3, reflect::prelude::*, type_traits::prelude::*,
+        algebra::Vector3, pool::Handle, reflect::prelude::*, type_traits::prelude::*,
         variable::InheritableVariable, visitor::prelude::*,
     },
-    scene::{collider::Collider, rigidbody::RigidBody},
-    script::{ScriptContext, ScriptTrait},
+    scene::{collider::Collider, graph::Graph, node::Node},
+    script::{ScriptContext, ScriptDeinitContext, ScriptTrait},
 };
 use std::collections::HashSet;
+use strum_macros::{AsRefStr, EnumString, VariantNames};
 
-#[derive(Clone, Default, Debug, Visit, Reflect, TypeUuidProvider, ComponentProvider)]
+#[derive(
+    Default,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Visit,
+    Reflect,
+    TypeUuidProvider,
+    AsRefStr,
+    EnumString,
+    VariantNames,
+)]
+#[type_uuid(id = "a3c5e7b9-4d2f-4a61-8e0b-5f7d9c1e3a26")]
+pub enum JumperMode {
+    /// Sets vertical velocity of actors to the push force.
+    #[default]
+    Vertical,
+    /// Sends actors along a ballistic arc to the landing point.
+    LaunchPad,
+}
+
+#[derive(Clone, Debug, Visit, Reflect, TypeUuidProvider, ComponentProvider)]
 #[type_uuid(id = "be8a29af-c10a-4518-a78b-955c8f48a8cd")]
 #[visit(optional)]
 pub struct Jumper {
     push_force: InheritableVariable<f32>,
+    mode: InheritableVariable<JumperMode>,
+    /// A node, where actors will land in the launch pad mode.
+    landing_point: InheritableVariable<Handle<Node>>,
+    /// Time (in seconds) of flight from the launch pad to the landing point.
+    flight_time: InheritableVariable<f32>,
+}
+
+impl Default for Jumper {
+    fn default() -> Self {
+        Self {
+            push_force: Default::default(),
+            mode: Default::default(),
+            landing_point: Default::default(),
+            flight_time: 1.5.into(),
+        }
+    }
+}
+
+impl Jumper {
+    pub fn is_launch_pad(&self) -> bool {
+        *self.mode == JumperMode::LaunchPad && self.landing_point.is_some()
+    }
+
+    pub fn landing_point(&self) -> Handle<Node> {
+        *self.landing_point
+    }
+
+    pub fn flight_time(&self) -> f32 {
+        self.flight_time.max(0.1)
+    }
+
+    /// Calculates initial velocity of a body at the given position, that is needed to reach the
+    /// landing point in the flight time.
+    pub fn launch_velocity(&self, from: Vector3<f32>, graph: &Graph) -> Option<Vector3<f32>> {
+        let landing_point = graph.try_get(*self.landing_point).ok()?.global_position();
+        let time = self.flight_time();
+        let gravity = *graph.physics.gravity;
+        Some((landing_point - from - gravity.scale(0.5 * time * time)).scale(1.0 / time))
+    }
+
+    /// Returns points of the arc from the given position to the landing point.
+    pub fn trajectory(
+        &self,
+        from: Vector3<f32>,
+        graph: &Graph,
+        segments: usize,
+    ) -> Option<Vec<Vector3<f32>>> {
+        let velocity = self.launch_velocity(from, graph)?;
+        let gravity = *graph.physics.gravity;
+        let segments = segments.max(1);
+        Some(
+            (0..=segments)
+                .map(|i| {
+                    let t = self.flight_time() * i as f32 / segments as f32;
+                    from + velocity.scale(t) + gravity.scale(0.5 * t * t)
+                })
+                .collect(),
+        )
+    }
+
+    /// Returns actors that touch any collider of the jumper.
+    fn find_contacted_actors(
+        &self,
+        handle: Handle<Node>,
+        actors: &HashSet<Handle<Node>>,
+        graph: &Graph,
+    ) -> Result<HashSet<Handle<Node>>, GameError> {
+        let mut result = HashSet::new();
+
+        let mut colliders = vec![handle];
+        colliders.extend_from_slice(graph.try_get(handle)?.children());
+
+        for collider in colliders {
+            let Ok(collider) = graph.try_get_of_type::<Collider>(collider) else {
+                continue;
+            };
+            for contact in collider.contacts(&graph.physics) {
+                for actor in actors.iter() {
+                    let actor_collider =
+                        graph.try_get_script_component_of::<Actor>(*actor)?.collider;
+                    if contact.collider1 == actor_collider || contact.collider2 == actor_collider {
+                        result.insert(*actor);
+                    }
+                }
+            }
+        }
+
+        Ok(result)
+    }
 }
 
 impl ScriptTrait for Jumper {
+    fn on_init(&mut self, ctx: &mut ScriptContext) -> GameResult {
+        if self.is_launch_pad() {
+            ctx.plugins
+                .get_mut::<Game>()
+                .level
+                .launch_pads
+                .insert(ctx.handle);
+        }
+        Ok(())
+    }
+
+    fn on_deinit(&mut self, ctx: &mut ScriptDeinitContext) -> GameResult {
+        ctx.plugins
+            .get_mut::<Game>()
+            .level
+            .launch_pads
+            .remove(&ctx.node_handle);
+        Ok(())
+    }
+
     fn on_update(&mut self, ctx: &mut ScriptContext) -> GameResult {
         let game = ctx.plugins.get::<Game>();
         if game.is_client() {
             return Ok(());
         }
 
-        let collider = ctx.scene.graph.try_get_of_type::<Collider>(ctx.handle)?;
+        let graph = &mut ctx.scene.graph;
+        let contacted_actors = self.find_contacted_actors(ctx.handle, &game.level.actors, graph)?;
 
-        let mut contacted_colliders = HashSet::new();
+        for actor in contacted_actors {
+            let actor = graph.try_get_script_component_of_mut::<Actor>(actor)?;
+            let rigid_body = actor.rigid_body;
 
-        for contact in collider.contacts(&ctx.scene.graph.physics) {
-            for actor in game.level.actors.iter() {
-                let actor_script = ctx.scene.graph[*actor].script(0);
+            if self.is_launch_pad() {
+                if actor.is_launched() {
+                    continue;
+                }
+                actor.launch(self.flight_time());
 
-                if let Some(actor_collider) = actor_script
-                    .and_then(|s| s.query_component_ref::<Player>().map(|p| p.actor.collider))
-                    .or_else(|| {
-                        actor_script
-                            .and_then(|s| s.query_component_ref::<Bot>().map(|b| b.actor.collider))
-                    })
-                {
-                    if contact.collider1 == actor_collider || contact.collider2 == actor_collider {
-                        contacted_colliders.insert(actor_collider);
-                    }
+                let position = graph.try_get(rigid_body)?.global_position();
+                if let Some(velocity) = self.launch_velocity(position, graph) {
+                    graph.try_get_mut(rigid_body)?.set_lin_vel(velocity);
                 }
+            } else {
+                let rigid_body = graph.try_get_mut(rigid_body)?;
+                let lin_vel = rigid_body.lin_vel();
+                rigid_body.set_lin_vel(Vector3::new(lin_vel.x, *self.push_force, lin_vel.z));
             }
         }
 
-        for collider in contacted_colliders {
-            let parent = ctx.scene.graph.try_get(collider)?.parent();
-            let rigid_body = ctx.scene.graph.try_get_mut_of_type::<RigidBody>(parent)?;
-            let lin_vel = rigid_body.lin_vel();
-            rigid_body.set_lin_vel(Vector3::new(lin_vel.x, *self.push_force, lin_vel.z));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use fyrox::scene::{base::BaseBuilder, pivot::PivotBuilder, transform::TransformBuilder};
+
+    fn launch_pad(graph: &mut Graph, landing_position: Vector3<f32>) -> Jumper {
+        let landing_point = PivotBuilder::new(
+            BaseBuilder::new().with_local_transform(
+                TransformBuilder::new()
+                    .with_local_position(landing_position)
+                    .build(),
+            ),
+        )
+        .build(graph);
+        graph.update_hierarchical_data();
+        Jumper {
+            mode: JumperMode::LaunchPad.into(),
+            landing_point: landing_point.into(),
+            ..Default::default()
         }
+    }
 
-        Ok(())
+    #[test]
+    fn launch_velocity_reaches_landing_point() {
+        let mut graph = Graph::new();
+        let landing_position = Vector3::new(10.0, 3.0, -4.0);
+        let jumper = launch_pad(&mut graph, landing_position);
+        let from = Vector3::new(1.0, 0.0, 2.0);
+
+        let trajectory = jumper.trajectory(from, &graph, 8).unwrap();
+        assert_eq!(trajectory.len(), 9);
+        assert!(trajectory[0].metric_distance(&from) < 1.0e-4);
+        assert!(trajectory[8].metric_distance(&landing_position) < 1.0e-3);
+
+        let velocity = jumper.launch_velocity(from, &graph).unwrap();
+        assert!(velocity.y > 0.0);
+    }
+
+    #[test]
+    fn launch_velocity_without_landing_point() {
+        let graph = Graph::new();
+        let jumper = Jumper {
+            mode: JumperMode::LaunchPad.into(),
+            ..Default::default()
+        };
+        assert!(!jumper.is_launch_pad());
+        assert!(jumper.launch_velocity(Vector3::zeros(), &graph).is_none());
     }
 }
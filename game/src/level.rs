@@ -1,4 +1,7 @@
-use crate::actor::Actor;
+use crate::{
+    actor::{Actor, ActorStateEvent},
+    game_mode::GameMode,
+};
 use fyrox::graph::SceneGraph;
 use fyrox::plugin::error::GameResult;
 use fyrox::{
@@ -16,11 +19,41 @@ pub struct LeaderBoardEntry {
     pub finished: bool,
     pub real_time_position: usize,
     pub finished_position: usize,
+    #[visit(optional)]
+    pub eliminated: bool,
+    #[visit(optional)]
+    pub team: Option<usize>,
+}
+
+/// Stage of a match. Actors can move only when the match is running.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Visit)]
+pub enum MatchStage {
+    /// The server waits until every client has loaded the level.
+    #[default]
+    WaitingForPlayers,
+    /// Every client has loaded the level, the race will start when the countdown ends.
+    Countdown {
+        time_left: f32,
+    },
+    Running,
+    /// The round is over, but the level is still shown (for example, between tournament rounds).
+    Finished,
+}
+
+impl MatchStage {
+    /// Returns a number that should be shown on the screen during the countdown.
+    pub fn countdown_number(&self) -> Option<u32> {
+        match self {
+            MatchStage::Countdown { time_left } => Some(time_left.ceil() as u32),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum LeaderBoardEvent {
     Finished { actor: Handle<Node>, place: usize },
+    Eliminated { actor: Handle<Node>, place: usize },
 }
 
 #[derive(Default, Visit, Debug)]
@@ -33,20 +66,45 @@ pub struct Leaderboard {
 }
 
 impl Leaderboard {
+    /// Returns `true` if the actor is out of the match, either because it has finished or
+    /// because it was eliminated.
     pub fn is_finished(&self, actor: Handle<Node>) -> bool {
         self.entries
             .get(&actor)
-            .map(|e| e.finished)
+            .map(|e| e.finished || e.eliminated)
             .unwrap_or_default()
     }
 
-    pub fn finish(&mut self, actor: Handle<Node>) {
-        let prev_position = self
+    pub fn finished_count(&self) -> usize {
+        self.entries.values().filter(|e| e.finished).count()
+    }
+
+    pub fn alive_count(&self) -> usize {
+        self.entries.values().filter(|e| !e.eliminated).count()
+    }
+
+    /// Eliminates the actor, it takes the given place.
+    pub fn eliminate(&mut self, actor: Handle<Node>, place: usize) {
+        let entry = self
             .entries
-            .iter()
-            .min_by_key(|(_, v)| v.finished_position)
-            .map(|e| e.1.finished_position)
-            .unwrap_or_default();
+            .entry(actor)
+            .or_insert_with(|| LeaderBoardEntry {
+                actor,
+                ..Default::default()
+            });
+        if !entry.eliminated {
+            entry.finished_position = place;
+            entry.eliminated = true;
+            if let Some(sender) = self.sender.as_ref() {
+                sender
+                    .send(LeaderBoardEvent::Eliminated { actor, place })
+                    .unwrap();
+            }
+        }
+    }
+
+    pub fn finish(&mut self, actor: Handle<Node>) {
+        let prev_position = self.finished_count();
         let entry = self
             .entries
             .entry(actor)
@@ -80,6 +138,7 @@ impl Leaderboard {
             let position = graph[actor_ref.rigid_body].global_position();
             self.temp_array
                 .push((*actor, position.metric_distance(&finish_point)));
+            self.set_team(*actor, actor_ref.team);
         }
 
         self.temp_array
@@ -98,6 +157,47 @@ impl Leaderboard {
 
         Ok(())
     }
+
+    /// Survivors share the same place, until some of them are eliminated.
+    pub fn update_survivors(
+        &mut self,
+        actors: &HashSet<Handle<Node>>,
+        graph: &Graph,
+    ) -> GameResult {
+        for actor in actors {
+            let actor_ref = graph.try_get_script_component_of::<Actor>(*actor)?;
+            self.set_team(*actor, actor_ref.team);
+            if let Some(entry) = self.entries.get_mut(actor) {
+                entry.real_time_position = 0;
+            }
+        }
+        Ok(())
+    }
+
+    fn set_team(&mut self, actor: Handle<Node>, team: Option<usize>) {
+        self.entries
+            .entry(actor)
+            .or_insert_with(|| LeaderBoardEntry {
+                actor,
+                ..Default::default()
+            })
+            .team = team;
+    }
+
+    /// Sums the points of every team. An actor at `n`-th place of the given ranking brings
+    /// `ranking.len() - n` points to its team. Returns `(team, points)` pairs, the best team
+    /// goes first.
+    pub fn team_scores(&self, ranking: &[Handle<Node>]) -> Vec<(usize, usize)> {
+        let mut scores = FxHashMap::<usize, usize>::default();
+        for (rank, actor) in ranking.iter().enumerate() {
+            if let Some(team) = self.entries.get(actor).and_then(|e| e.team) {
+                *scores.entry(team).or_default() += ranking.len() - rank;
+            }
+        }
+        let mut scores = scores.into_iter().collect::<Vec<_>>();
+        scores.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        scores
+    }
 }
 
 #[derive(Visit, Debug)]
@@ -107,8 +207,29 @@ pub struct Level {
     pub start_points: HashSet<Handle<Node>>,
     pub actors: HashSet<Handle<Node>>,
     pub respawners: HashSet<Handle<Node>>,
+    #[visit(optional)]
+    pub interactables: HashSet<Handle<Node>>,
+    #[visit(optional)]
+    pub launch_pads: HashSet<Handle<Node>>,
+    #[visit(optional)]
+    pub force_volumes: HashSet<Handle<Node>>,
+    /// Rigid bodies of every actor (capsules and ragdoll limbs), they're registered when actors
+    /// start.
+    #[visit(skip)]
+    pub actor_bodies: HashSet<Handle<Node>>,
     pub leaderboard: Leaderboard,
     pub match_timer: f32,
+    pub stage: MatchStage,
+    /// Amount of time passed since the start of the race.
+    pub match_time: f32,
+    #[visit(optional)]
+    pub game_mode: GameMode,
+    /// Amount of players, that share the screen of this machine.
+    #[visit(optional)]
+    pub local_players: usize,
+    /// Listeners of actor state changes.
+    #[visit(skip)]
+    pub actor_state_listeners: Vec<Sender<ActorStateEvent>>,
 }
 
 impl Default for Level {
@@ -119,24 +240,77 @@ impl Default for Level {
             start_points: Default::default(),
             actors: Default::default(),
             respawners: Default::default(),
+            interactables: Default::default(),
+            launch_pads: Default::default(),
+            force_volumes: Default::default(),
+            actor_bodies: Default::default(),
             leaderboard: Default::default(),
             match_timer: 15.0 * 60.0,
+            stage: Default::default(),
+            match_time: 0.0,
+            game_mode: Default::default(),
+            local_players: 1,
+            actor_state_listeners: Default::default(),
         }
     }
 }
 
 impl Level {
+    pub const COUNTDOWN_DURATION: f32 = 3.0;
+
     pub fn update(&mut self, ctx: &PluginContext) -> GameResult {
         if let Ok(scene) = ctx.scenes.try_get(self.scene) {
-            self.match_timer = (self.match_timer - ctx.dt).max(0.0);
-            if let Some(target) = self.targets.iter().next().cloned() {
-                self.leaderboard
-                    .update(&self.actors, target, &scene.graph)?;
-            }
+            self.advance_clock(ctx.dt);
+            self.game_mode.update_leaderboard(
+                &mut self.leaderboard,
+                &self.actors,
+                self.targets.iter().next().cloned(),
+                &scene.graph,
+            )?;
         }
         Ok(())
     }
 
+    /// Advances the countdown and the match clock. Clients call it too, to not wait for the
+    /// server to tell them the time every frame.
+    pub fn advance_clock(&mut self, dt: f32) {
+        match self.stage {
+            MatchStage::WaitingForPlayers | MatchStage::Finished => (),
+            MatchStage::Countdown { time_left } => {
+                let time_left = time_left - dt;
+                self.stage = if time_left <= 0.0 {
+                    MatchStage::Running
+                } else {
+                    MatchStage::Countdown { time_left }
+                };
+            }
+            MatchStage::Running => {
+                self.match_timer = (self.match_timer - dt).max(0.0);
+                self.match_time += dt;
+            }
+        }
+    }
+
+    pub fn start_countdown(&mut self) {
+        if self.stage == MatchStage::WaitingForPlayers {
+            self.stage = MatchStage::Countdown {
+                time_left: Self::COUNTDOWN_DURATION,
+            };
+        }
+    }
+
+    /// Actors must stand still until the race is started.
+    pub fn is_movement_locked(&self) -> bool {
+        self.stage != MatchStage::Running
+    }
+
+    pub fn publish_actor_state(&self, event: ActorStateEvent) {
+        for listener in self.actor_state_listeners.iter() {
+            // A listener could be gone already, it is fine.
+            let _ = listener.send(event);
+        }
+    }
+
     pub fn sudden_death(&mut self) {
         if self.match_timer > 60.0 {
             self.match_timer = 60.0;
@@ -148,6 +322,130 @@ impl Level {
     }
 
     pub fn is_match_ended(&self) -> bool {
-        self.match_timer <= 0.0
+        self.match_timer <= 0.0 || self.game_mode.is_match_ended(self.stage, &self.leaderboard)
+    }
+
+    pub fn team_scores(&self) -> Vec<(usize, usize)> {
+        self.leaderboard
+            .team_scores(&self.game_mode.ranking(&self.leaderboard))
+    }
+
+    /// Must be called when an actor falls off the platforms. Returns `true` if the actor must be
+    /// respawned, otherwise the actor is eliminated.
+    pub fn on_actor_fell(&mut self, actor: Handle<Node>) -> bool {
+        if self.game_mode.respawns_actors() {
+            return true;
+        }
+        if self.actors.remove(&actor) {
+            // The first eliminated actor takes the last place.
+            let place = self.actors.len() + 1;
+            self.leaderboard.eliminate(actor, place);
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn actor(index: u32) -> Handle<Node> {
+        Handle::new(index, 1)
+    }
+
+    #[test]
+    fn finish_places_follow_finish_order() {
+        let mut leaderboard = Leaderboard::default();
+        for index in 1..=3 {
+            leaderboard.entries.insert(
+                actor(index),
+                LeaderBoardEntry {
+                    actor: actor(index),
+                    ..Default::default()
+                },
+            );
+        }
+
+        leaderboard.finish(actor(2));
+        leaderboard.finish(actor(3));
+        // Finishing twice must not change the place.
+        leaderboard.finish(actor(2));
+        leaderboard.finish(actor(1));
+
+        assert_eq!(leaderboard.entries[&actor(2)].finished_position, 1);
+        assert_eq!(leaderboard.entries[&actor(3)].finished_position, 2);
+        assert_eq!(leaderboard.entries[&actor(1)].finished_position, 3);
+        assert_eq!(leaderboard.finished_count(), 3);
+    }
+
+    #[test]
+    fn countdown_starts_the_race() {
+        let mut level = Level::default();
+
+        // The clock does not run until every client has loaded the level.
+        level.advance_clock(1.0);
+        assert_eq!(level.stage, MatchStage::WaitingForPlayers);
+        assert_eq!(level.stage.countdown_number(), None);
+        assert!(level.is_movement_locked());
+
+        level.start_countdown();
+        assert_eq!(level.stage.countdown_number(), Some(3));
+
+        let mut numbers = Vec::new();
+        while level.stage != MatchStage::Running {
+            numbers.extend(level.stage.countdown_number());
+            numbers.dedup();
+            level.advance_clock(0.25);
+        }
+        assert_eq!(numbers, [3, 2, 1]);
+        assert_eq!(level.match_time, 0.0);
+        assert!(!level.is_movement_locked());
+
+        // The countdown cannot be started twice.
+        level.start_countdown();
+        assert_eq!(level.stage, MatchStage::Running);
+
+        let match_timer = level.match_timer;
+        level.advance_clock(0.5);
+        assert_eq!(level.match_time, 0.5);
+        assert_eq!(level.match_timer, match_timer - 0.5);
+    }
+
+    #[test]
+    fn finished_match_clock_is_stopped() {
+        let mut level = Level {
+            stage: MatchStage::Finished,
+            match_time: 10.0,
+            ..Default::default()
+        };
+        level.start_countdown();
+        level.advance_clock(1.0);
+        assert_eq!(level.stage, MatchStage::Finished);
+        assert_eq!(level.match_time, 10.0);
+        assert!(level.is_movement_locked());
+    }
+
+    #[test]
+    fn team_scores_sum_places_of_members() {
+        let mut leaderboard = Leaderboard::default();
+        for (index, team) in [(1, Some(0)), (2, Some(1)), (3, Some(0)), (4, None)] {
+            leaderboard.entries.insert(
+                actor(index),
+                LeaderBoardEntry {
+                    actor: actor(index),
+                    team,
+                    ..Default::default()
+                },
+            );
+        }
+
+        // The best actor gets as many points as there are actors in the ranking.
+        let ranking = [actor(2), actor(1), actor(4), actor(3)];
+        assert_eq!(leaderboard.team_scores(&ranking), [(0, 4), (1, 4)]);
+
+        let ranking = [actor(1), actor(4), actor(2), actor(3)];
+        assert_eq!(leaderboard.team_scores(&ranking), [(0, 5), (1, 2)]);
+
+        assert!(Leaderboard::default().team_scores(&ranking).is_empty());
     }
 }
@@ -1,18 +1,29 @@
 //! Game project.
 use crate::{
-    actor::Actor,
+    actor::{Actor, ImpactThreshold, KnockdownProfile},
     bot::Bot,
     camera::CameraController,
-    cannon::Cannon,
+    cannon::{Cannon, FiringMode},
     client::Client,
-    jumper::Jumper,
+    controls::ControlAction,
+    crumble::CrumblePlatform,
+    explosive::{ExplosionTrigger, Explosive},
+    force_volume::{ForceKind, ForceVolume},
+    gamepad::{Gamepad, GamepadAction},
+    hazard::{Hazard, RisingHazard},
+    hinge::{Pendulum, Sweeper},
+    interactable::{Interactable, InteractableKind},
+    jumper::{Jumper, JumperMode},
     level::Level,
     menu::{InGameMenu, Menu, MenuData, MenuSceneData, ServerMenu, SettingsMenu},
+    moving_platform::{MovingPlatform, PathMode},
+    pickup::{Pickup, PickupEffect},
     player::Player,
     respawn::{RespawnMode, Respawner},
     server::Server,
     settings::Settings,
     start::StartPoint,
+    surface::{SurfaceKind, SurfaceZone},
     target::Target,
     trigger::{Action, Trigger},
 };
@@ -28,9 +39,10 @@ use fyrox::{
         message::UiMessage,
         UserInterface,
     },
-    keyboard::{KeyCode, PhysicalKey},
+    keyboard::PhysicalKey,
     plugin::{error::GameResult, Plugin, PluginContext, PluginRegistrationContext},
     scene::Scene,
+    utils::translate_key_to_ui,
     window::Fullscreen,
 };
 use std::sync::Arc;
@@ -40,16 +52,31 @@ pub mod bot;
 pub mod camera;
 pub mod cannon;
 pub mod client;
+pub mod controls;
+pub mod crumble;
+pub mod explosive;
+pub mod force_volume;
+pub mod game_mode;
+pub mod gamepad;
+pub mod hazard;
+pub mod hinge;
+pub mod interactable;
 pub mod jumper;
 pub mod level;
 pub mod menu;
+pub mod moving_platform;
 pub mod net;
+pub mod pickup;
 pub mod player;
 pub mod respawn;
 pub mod server;
 pub mod settings;
 pub mod start;
+pub mod surface;
 pub mod target;
+pub mod team;
+pub mod touch;
+pub mod tournament;
 pub mod trigger;
 pub mod utils;
 
@@ -58,6 +85,8 @@ pub struct DebugSettings {
     pub show_paths: bool,
     pub show_physics: bool,
     pub disable_ragdoll: bool,
+    #[visit(optional)]
+    pub show_impacts: bool,
 }
 
 #[derive(Reflect, Debug)]
@@ -69,6 +98,7 @@ pub struct Game {
     server: Option<Server>,
     client: Option<Client>,
     settings: Settings,
+    gamepad: Gamepad,
 }
 
 impl Visit for Game {
@@ -114,6 +144,7 @@ impl Game {
             server: None,
             client: None,
             settings: Settings::load(),
+            gamepad: Gamepad::new(),
         }
     }
 
@@ -131,8 +162,19 @@ impl Game {
             ..Default::default()
         };
 
+        if let Some(server) = self.server.as_ref() {
+            self.level.game_mode = server.game_mode;
+            self.level.local_players = server.local_players();
+            self.level
+                .actor_state_listeners
+                .push(server.actor_state_sender());
+        }
+
         if let Some(menu) = self.menu.as_ref() {
             self.level.leaderboard.sender = Some(menu.leader_board_channel.sender.clone());
+            self.level
+                .actor_state_listeners
+                .push(menu.actor_state_channel.sender.clone());
             menu.set_menu_visibility(ctx.user_interfaces.first(), false);
         }
         if let Some(server) = self.server.as_mut() {
@@ -163,18 +205,39 @@ impl Plugin for Game {
             .add::<Respawner>("Respawner")
             .add::<Cannon>("Cannon")
             .add::<Trigger>("Trigger")
-            .add::<Jumper>("Jumper");
+            .add::<Jumper>("Jumper")
+            .add::<Pickup>("Pickup")
+            .add::<MovingPlatform>("Moving Platform")
+            .add::<Interactable>("Interactable")
+            .add::<Explosive>("Explosive")
+            .add::<SurfaceZone>("Surface Zone")
+            .add::<ForceVolume>("Force Volume")
+            .add::<CrumblePlatform>("Crumble Platform")
+            .add::<RisingHazard>("Rising Hazard")
+            .add::<Hazard>("Hazard")
+            .add::<Pendulum>("Pendulum")
+            .add::<Sweeper>("Sweeper");
         Ok(())
     }
 
     fn register_property_editors(&self, container: Arc<PropertyEditorDefinitionContainer>) {
         container.insert(InspectablePropertyEditorDefinition::<Actor>::new());
+        container.insert(InspectablePropertyEditorDefinition::<KnockdownProfile>::new());
+        container.insert(InspectablePropertyEditorDefinition::<ImpactThreshold>::new());
         container.insert(InspectablePropertyEditorDefinition::<Menu>::new());
         container.insert(InspectablePropertyEditorDefinition::<InGameMenu>::new());
         container.insert(InspectablePropertyEditorDefinition::<ServerMenu>::new());
         container.insert(InspectablePropertyEditorDefinition::<SettingsMenu>::new());
         container.register_inheritable_enum::<RespawnMode, _>();
         container.register_inheritable_enum::<Action, _>();
+        container.register_inheritable_enum::<PickupEffect, _>();
+        container.register_inheritable_enum::<PathMode, _>();
+        container.register_inheritable_enum::<InteractableKind, _>();
+        container.register_inheritable_enum::<ExplosionTrigger, _>();
+        container.register_inheritable_enum::<FiringMode, _>();
+        container.register_inheritable_enum::<JumperMode, _>();
+        container.register_inheritable_enum::<SurfaceKind, _>();
+        container.register_inheritable_enum::<ForceKind, _>();
     }
 
     fn init(&mut self, _scene_path: Option<&str>, mut ctx: PluginContext) -> GameResult {
@@ -196,6 +259,17 @@ impl Plugin for Game {
     }
 
     fn update(&mut self, ctx: &mut PluginContext) -> GameResult {
+        self.gamepad.poll(self.settings.read().gamepad_deadzone);
+        let is_touch_menu_requested = self
+            .menu
+            .as_mut()
+            .is_some_and(|menu| menu.touch_controls.take_menu_request());
+        if self.gamepad.is_pressed(GamepadAction::Menu) || is_touch_menu_requested {
+            if let Some(menu) = self.menu.as_ref() {
+                menu.switch_visibility(ctx.user_interfaces.first(), self.client.is_some());
+            }
+        }
+
         if let Some(server) = self.server.as_mut() {
             server.accept_connections();
 
@@ -205,7 +279,12 @@ impl Plugin for Game {
 
         if let Some(client) = self.client.as_mut() {
             client.read_messages(&mut self.level, self.menu.as_ref(), ctx)?;
-            client.update(ctx.dt);
+            client.update(
+                &mut self.level,
+                self.server.is_some(),
+                self.menu.as_ref(),
+                ctx,
+            )?;
         }
 
         if let Ok(scene) = ctx.scenes.try_get_mut(self.level.scene) {
@@ -217,43 +296,71 @@ impl Plugin for Game {
         }
 
         if let Some(menu) = self.menu.as_mut() {
-            menu.update(ctx, &self.server, &self.client, &mut self.level)?;
+            menu.update(
+                ctx,
+                &self.server,
+                &self.client,
+                &mut self.level,
+                &self.debug_settings,
+            )?;
         }
 
         Ok(())
     }
 
     fn on_os_event(&mut self, event: &Event<()>, ctx: PluginContext) -> GameResult {
+        if let Some(menu) = self.menu.as_mut() {
+            menu.touch_controls
+                .on_os_event(event, ctx.user_interfaces.first());
+        }
+
         if let Event::WindowEvent {
             event: WindowEvent::KeyboardInput { event, .. },
             ..
         } = event
         {
             if let PhysicalKey::Code(key_code) = event.physical_key {
-                if event.state == ElementState::Pressed {
-                    match key_code {
-                        KeyCode::F1 => {
-                            self.debug_settings.show_physics = !self.debug_settings.show_physics
-                        }
-                        KeyCode::F2 => {
-                            self.debug_settings.show_paths = !self.debug_settings.show_paths
-                        }
-                        KeyCode::F3 => {
-                            self.debug_settings.disable_ragdoll =
-                                !self.debug_settings.disable_ragdoll
-                        }
-                        KeyCode::Escape => {
-                            if let Some(menu) = self.menu.as_ref() {
-                                menu.switch_visibility(
-                                    ctx.user_interfaces.first(),
-                                    self.client.is_some(),
-                                );
+                // The key is being bound in the settings menu.
+                let is_binding_key = self
+                    .menu
+                    .as_ref()
+                    .is_some_and(|menu| menu.is_listening_for_key());
+                if event.state == ElementState::Pressed && !is_binding_key {
+                    let key = translate_key_to_ui(key_code);
+                    let actions = self
+                        .settings
+                        .read()
+                        .controls
+                        .actions(key)
+                        .collect::<Vec<_>>();
+                    for action in actions {
+                        match action {
+                            ControlAction::ShowPhysics => {
+                                self.debug_settings.show_physics = !self.debug_settings.show_physics
                             }
+                            ControlAction::ShowPaths => {
+                                self.debug_settings.show_paths = !self.debug_settings.show_paths
+                            }
+                            ControlAction::DisableRagdoll => {
+                                self.debug_settings.disable_ragdoll =
+                                    !self.debug_settings.disable_ragdoll
+                            }
+                            ControlAction::Menu => {
+                                if let Some(menu) = self.menu.as_ref() {
+                                    menu.switch_visibility(
+                                        ctx.user_interfaces.first(),
+                                        self.client.is_some(),
+                                    );
+                                }
+                            }
+                            ControlAction::ShortenMatch => {
+                                self.level.match_timer = 3.0;
+                            }
+                            ControlAction::ShowImpacts => {
+                                self.debug_settings.show_impacts = !self.debug_settings.show_impacts
+                            }
+                            _ => (),
                         }
-                        KeyCode::F4 => {
-                            self.level.match_timer = 3.0;
-                        }
-                        _ => (),
                     }
                 }
             }
@@ -1,10 +1,17 @@
 use crate::{
-    actor::{Actor, ActorKind},
+    actor::{Actor, ActorKind, ActorState, ActorStateEvent},
+    camera::split_screen_viewport,
     client::Client,
-    level::{LeaderBoardEvent, Level},
+    controls::{ControlAction, Controls},
+    game_mode::GameMode,
+    level::{LeaderBoardEntry, LeaderBoardEvent, Level, MatchStage},
+    player::Player,
     server::Server,
     settings::Settings,
-    utils, Game,
+    team::{self, MAX_TEAMS},
+    touch::TouchControls,
+    tournament::{Qualification, TournamentRound, TournamentStandingsMessage},
+    utils, DebugSettings, Game,
 };
 use fyrox::{
     asset::manager::ResourceManager,
@@ -16,17 +23,20 @@ use fyrox::{
     graph::SceneGraph,
     gui::{
         animation::{AnimationPlayer, AnimationPlayerMessage},
-        button::{Button, ButtonMessage},
+        button::{Button, ButtonBuilder, ButtonContent, ButtonMessage},
         check_box::{CheckBox, CheckBoxMessage},
         font::Font,
-        list_view::{ListView, ListViewMessage},
-        message::UiMessage,
+        formatted_text::WrapMode,
+        list_view::{ListView, ListViewBuilder, ListViewMessage},
+        message::{KeyCode, UiMessage},
         scroll_bar::{ScrollBar, ScrollBarMessage},
         selector::{Selector, SelectorMessage},
+        stack_panel::{StackPanel, StackPanelBuilder},
         text::{Text, TextBuilder, TextMessage},
         text_box::TextBox,
         widget::{WidgetBuilder, WidgetMessage},
-        BuildContext, HorizontalAlignment, Thickness, UiNode, UserInterface, VerticalAlignment,
+        BuildContext, HorizontalAlignment, Orientation, Thickness, UiNode, UserInterface,
+        VerticalAlignment,
     },
     plugin::{error::GameResult, PluginContext},
     resource::model::Model,
@@ -61,6 +71,240 @@ fn set_visibility(ui: &UserInterface, pairs: &[(Handle<UiNode>, bool)]) {
     }
 }
 
+fn make_button(ctx: &mut BuildContext, text: &str) -> Handle<Button> {
+    ButtonBuilder::new(
+        WidgetBuilder::new()
+            .with_height(32.0)
+            .with_margin(Thickness::uniform(2.0)),
+    )
+    .with_text(text)
+    .build(ctx)
+}
+
+/// A panel of the server menu, that allows to select a game mode and to make a playlist of maps
+/// for a tournament. If the playlist is empty, a single match on the selected map will be started.
+#[derive(Debug, Default, Clone)]
+struct MatchSetupPanel {
+    root: Handle<StackPanel>,
+    change_game_mode: Handle<Button>,
+    game_mode: usize,
+    change_survivors: Handle<Button>,
+    survivors: usize,
+    rounds_list: Handle<ListView>,
+    qualification_text: Handle<Text>,
+    change_qualification: Handle<Button>,
+    add_round: Handle<Button>,
+    clear_rounds: Handle<Button>,
+    change_teams: Handle<Button>,
+    teams: usize,
+    change_local_players: Handle<Button>,
+    local_players: usize,
+    qualification: usize,
+    rounds: Vec<TournamentRound>,
+    visible: bool,
+}
+
+impl MatchSetupPanel {
+    fn new(ui: &mut UserInterface, resource_manager: &ResourceManager) -> Self {
+        let ctx = &mut ui.build_ctx();
+        let change_game_mode = make_button(ctx, &Self::game_mode_label(GameMode::PRESETS[0]));
+        let change_survivors = make_button(ctx, &Self::survivors_label(1));
+        let change_teams = make_button(ctx, &Self::teams_label(0));
+        let change_local_players = make_button(ctx, &Self::local_players_label(1));
+        let title = make_text_widget(
+            ctx,
+            "Tournament",
+            resource_manager,
+            HorizontalAlignment::Center,
+        );
+        let rounds_list = ListViewBuilder::new(
+            WidgetBuilder::new()
+                .with_height(150.0)
+                .with_margin(Thickness::uniform(2.0)),
+        )
+        .build(ctx);
+        let qualification_text = make_text_widget(
+            ctx,
+            &Self::qualification_label(0),
+            resource_manager,
+            HorizontalAlignment::Center,
+        );
+        let change_qualification = make_button(ctx, "Change Rule");
+        let add_round = make_button(ctx, "Add Selected Map");
+        let clear_rounds = make_button(ctx, "Clear");
+        let root = StackPanelBuilder::new(
+            WidgetBuilder::new()
+                .with_width(300.0)
+                .with_visibility(false)
+                .with_horizontal_alignment(HorizontalAlignment::Right)
+                .with_vertical_alignment(VerticalAlignment::Center)
+                .with_children([
+                    change_game_mode.to_base(),
+                    change_survivors.to_base(),
+                    change_teams.to_base(),
+                    change_local_players.to_base(),
+                    title.to_base(),
+                    rounds_list.to_base(),
+                    qualification_text.to_base(),
+                    change_qualification.to_base(),
+                    add_round.to_base(),
+                    clear_rounds.to_base(),
+                ]),
+        )
+        .build(ctx);
+
+        Self {
+            root,
+            change_game_mode,
+            game_mode: 0,
+            change_survivors,
+            survivors: 1,
+            rounds_list,
+            qualification_text,
+            change_qualification,
+            add_round,
+            clear_rounds,
+            change_teams,
+            teams: 0,
+            change_local_players,
+            local_players: 1,
+            qualification: 0,
+            rounds: Default::default(),
+            visible: false,
+        }
+    }
+
+    /// The panel is a separate root widget, so it follows the visibility of the server menu.
+    fn set_visibility(&mut self, ui: &UserInterface, visible: bool) {
+        if self.visible != visible {
+            self.visible = visible;
+            ui.send(self.root, WidgetMessage::Visibility(visible));
+        }
+    }
+
+    fn game_mode(&self) -> GameMode {
+        GameMode::PRESETS[self.game_mode].with_survivors(self.survivors)
+    }
+
+    fn game_mode_label(game_mode: GameMode) -> String {
+        format!("Mode: {game_mode}")
+    }
+
+    fn survivors_label(survivors: usize) -> String {
+        format!("Survivors: {survivors}")
+    }
+
+    fn teams_label(teams: usize) -> String {
+        if teams == 0 {
+            "Teams: None".to_string()
+        } else {
+            format!("Teams: {teams}")
+        }
+    }
+
+    fn local_players_label(local_players: usize) -> String {
+        format!("Local Players: {local_players}")
+    }
+
+    fn qualification_label(preset: usize) -> String {
+        format!("Qualification: {}", Qualification::PRESETS[preset])
+    }
+
+    fn sync_rounds_list(&self, ui: &mut UserInterface, resource_manager: &ResourceManager) {
+        let items = self
+            .rounds
+            .iter()
+            .enumerate()
+            .map(|(n, round)| {
+                make_text_widget(
+                    &mut ui.build_ctx(),
+                    &format!(
+                        "{}. {} - {}",
+                        n + 1,
+                        round
+                            .map
+                            .file_stem()
+                            .map(|s| s.to_string_lossy().to_string())
+                            .unwrap_or_default(),
+                        round.qualification
+                    ),
+                    resource_manager,
+                    HorizontalAlignment::Left,
+                )
+            })
+            .collect::<Vec<_>>();
+        ui.send(self.rounds_list, ListViewMessage::Items(items.to_base()));
+    }
+
+    fn handle_ui_message(
+        &mut self,
+        ui: &mut UserInterface,
+        resource_manager: &ResourceManager,
+        message: &UiMessage,
+        selected_level: Option<&PathBuf>,
+    ) {
+        if let Some(ButtonMessage::Click) = message.data() {
+            if message.destination() == self.change_game_mode
+                || message.destination() == self.change_survivors
+            {
+                if message.destination() == self.change_game_mode {
+                    self.game_mode = (self.game_mode + 1) % GameMode::PRESETS.len();
+                } else {
+                    self.survivors = self.survivors % GameMode::MAX_SURVIVORS + 1;
+                    ui.send(
+                        self.change_survivors,
+                        ButtonMessage::Content(ButtonContent::text(&Self::survivors_label(
+                            self.survivors,
+                        ))),
+                    );
+                }
+                ui.send(
+                    self.change_game_mode,
+                    ButtonMessage::Content(ButtonContent::text(&Self::game_mode_label(
+                        self.game_mode(),
+                    ))),
+                );
+            } else if message.destination() == self.change_teams {
+                // No teams, then 2..MAX_TEAMS teams.
+                self.teams = match self.teams {
+                    0 => 2,
+                    n if n >= MAX_TEAMS => 0,
+                    n => n + 1,
+                };
+                ui.send(
+                    self.change_teams,
+                    ButtonMessage::Content(ButtonContent::text(&Self::teams_label(self.teams))),
+                );
+            } else if message.destination() == self.change_local_players {
+                self.local_players = self.local_players % 4 + 1;
+                ui.send(
+                    self.change_local_players,
+                    ButtonMessage::Content(ButtonContent::text(&Self::local_players_label(
+                        self.local_players,
+                    ))),
+                );
+            } else if message.destination() == self.change_qualification {
+                self.qualification = (self.qualification + 1) % Qualification::PRESETS.len();
+                ui.send(
+                    self.qualification_text,
+                    TextMessage::Text(Self::qualification_label(self.qualification)),
+                );
+            } else if message.destination() == self.add_round {
+                if let Some(map) = selected_level {
+                    self.rounds.push(TournamentRound {
+                        map: map.clone(),
+                        qualification: Qualification::PRESETS[self.qualification],
+                    });
+                    self.sync_rounds_list(ui, resource_manager);
+                }
+            } else if message.destination() == self.clear_rounds {
+                self.rounds.clear();
+                self.sync_rounds_list(ui, resource_manager);
+            }
+        }
+    }
+}
+
 #[derive(Visit, Reflect, Debug, Default, Clone, TypeUuidProvider)]
 #[type_uuid(id = "7dc2d3b9-1990-464c-bab3-3b6973f930e9")]
 pub struct ServerMenu {
@@ -78,9 +322,27 @@ pub struct ServerMenu {
     available_levels: Vec<PathBuf>,
     #[reflect(hidden)]
     selected_level: Option<usize>,
+    #[visit(skip)]
+    #[reflect(hidden)]
+    match_setup_panel: MatchSetupPanel,
+    /// Entries of the players list, clicking on an entry moves the player to another team.
+    #[visit(skip)]
+    #[reflect(hidden)]
+    player_entries: Vec<Handle<Button>>,
+    #[visit(skip)]
+    #[reflect(hidden)]
+    player_labels: Vec<String>,
 }
 
 impl ServerMenu {
+    fn create_match_setup_panel(
+        &mut self,
+        ui: &mut UserInterface,
+        resource_manager: &ResourceManager,
+    ) {
+        self.match_setup_panel = MatchSetupPanel::new(ui, resource_manager);
+    }
+
     pub fn fill_levels_list(&mut self, ui: &mut UserInterface, resource_manager: &ResourceManager) {
         self.available_levels = walkdir::WalkDir::new("./data/maps")
             .into_iter()
@@ -126,13 +388,38 @@ impl ServerMenu {
         message: &UiMessage,
         server: &mut Option<Server>,
     ) {
+        self.match_setup_panel.handle_ui_message(
+            ctx.user_interfaces.first_mut(),
+            ctx.resource_manager,
+            message,
+            self.selected_level
+                .and_then(|selected| self.available_levels.get(selected)),
+        );
+
         let ui = ctx.user_interfaces.first();
 
         if let Some(ButtonMessage::Click) = message.data() {
+            if let Some(server) = server.as_mut() {
+                if message.destination() == self.match_setup_panel.change_teams {
+                    server.set_teams_count(self.match_setup_panel.teams);
+                } else if message.destination() == self.match_setup_panel.change_local_players {
+                    server.set_local_players(self.match_setup_panel.local_players);
+                } else if let Some(connection) = self
+                    .player_entries
+                    .iter()
+                    .position(|entry| *entry == message.destination())
+                {
+                    server.switch_team(connection);
+                }
+            }
+
             if message.destination() == self.start {
                 ui.send(self.self_handle, WidgetMessage::Visibility(false));
-                if let Some(selected_level) = self.selected_level {
-                    if let Some(server) = server.as_mut() {
+                if let Some(server) = server.as_mut() {
+                    server.game_mode = self.match_setup_panel.game_mode();
+                    if !self.match_setup_panel.rounds.is_empty() {
+                        server.start_tournament(self.match_setup_panel.rounds.clone());
+                    } else if let Some(selected_level) = self.selected_level {
                         server.start_game(&self.available_levels[selected_level]);
                     }
                 }
@@ -156,34 +443,228 @@ impl ServerMenu {
         }
     }
 
-    pub fn update(&self, ctx: &mut PluginContext, server: &Option<Server>) {
+    pub fn update(&mut self, ctx: &mut PluginContext, server: &Option<Server>) {
+        let ui = ctx.user_interfaces.first();
+        let is_visible = ui
+            .try_get(self.self_handle)
+            .map(|n| n.is_globally_visible())
+            .unwrap_or_default();
+        self.match_setup_panel.set_visibility(ui, is_visible);
+
         let Some(server) = server else {
             return;
         };
 
-        let player_entries_count = ctx.user_interfaces.first()[self.players_list].items().len();
-        if server.connections().len() != player_entries_count {
-            let new_player_entries = server
-                .connections()
+        let player_labels = server
+            .connections()
+            .iter()
+            .enumerate()
+            .map(|(n, e)| {
+                let mut label = format!(
+                    "{} - {}",
+                    e.string_peer_address(),
+                    if n == 0 { "Host" } else { "Peer" }
+                );
+                if let Some(team) = server.team_of_connection(n) {
+                    label += &format!(" ({})", team::team_name(team));
+                }
+                label
+            })
+            .collect::<Vec<_>>();
+        if player_labels != self.player_labels {
+            let ui = ctx.user_interfaces.first_mut();
+            self.player_entries = player_labels
                 .iter()
-                .enumerate()
-                .map(|(n, e)| {
-                    make_text_widget(
-                        &mut ctx.user_interfaces.first_mut().build_ctx(),
-                        &format!(
-                            "{} - {}",
-                            e.string_peer_address(),
-                            if n == 0 { "Host" } else { "Peer" }
-                        ),
-                        ctx.resource_manager,
-                        HorizontalAlignment::Left,
-                    )
-                })
+                .map(|label| make_button(&mut ui.build_ctx(), label))
                 .collect::<Vec<_>>();
-            ctx.user_interfaces.first().send(
+            ui.send(
                 self.players_list,
-                ListViewMessage::Items(new_player_entries.to_base()),
+                ListViewMessage::Items(self.player_entries.to_base()),
             );
+            self.player_labels = player_labels;
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ControlSlot {
+    action: ControlAction,
+    index: usize,
+    button: Handle<Button>,
+}
+
+/// A page of the settings menu, that allows to rebind the controls. A click on a binding makes it
+/// wait for the next key press: Backspace removes the binding and Escape cancels.
+#[derive(Debug, Default, Clone)]
+struct ControlsPanel {
+    root: Handle<StackPanel>,
+    slots: Vec<ControlSlot>,
+    conflicts_text: Handle<Text>,
+    listening: Option<usize>,
+    visible: bool,
+}
+
+impl ControlsPanel {
+    fn new(
+        ui: &mut UserInterface,
+        resource_manager: &ResourceManager,
+        controls: &Controls,
+    ) -> Self {
+        let ctx = &mut ui.build_ctx();
+        let title = make_text_widget(
+            ctx,
+            "Controls",
+            resource_manager,
+            HorizontalAlignment::Center,
+        );
+        let mut rows = vec![title.to_base()];
+        let mut slots = Vec::new();
+        for action in ControlAction::ALL {
+            let label = TextBuilder::new(
+                WidgetBuilder::new()
+                    .with_width(170.0)
+                    .with_margin(Thickness::uniform(2.0)),
+            )
+            .with_vertical_text_alignment(VerticalAlignment::Center)
+            .with_text(action.as_ref())
+            .with_font(resource_manager.request::<Font>("data/font.ttf"))
+            .with_font_size(20.0.into())
+            .build(ctx);
+            let mut children = vec![label.to_base()];
+            for index in 0..Controls::MAX_BINDINGS {
+                let button = ButtonBuilder::new(
+                    WidgetBuilder::new()
+                        .with_width(110.0)
+                        .with_height(28.0)
+                        .with_margin(Thickness::uniform(2.0)),
+                )
+                .with_text(&Self::binding_label(controls, action, index))
+                .build(ctx);
+                children.push(button.to_base());
+                slots.push(ControlSlot {
+                    action,
+                    index,
+                    button,
+                });
+            }
+            rows.push(
+                StackPanelBuilder::new(WidgetBuilder::new().with_children(children))
+                    .with_orientation(Orientation::Horizontal)
+                    .build(ctx)
+                    .to_base(),
+            );
+        }
+        let conflicts_text =
+            TextBuilder::new(WidgetBuilder::new().with_margin(Thickness::uniform(2.0)))
+                .with_wrap(WrapMode::Word)
+                .with_text(Self::conflicts_label(controls))
+                .with_font(resource_manager.request::<Font>("data/font.ttf"))
+                .with_font_size(20.0.into())
+                .build(ctx);
+        rows.push(conflicts_text.to_base());
+        let root = StackPanelBuilder::new(
+            WidgetBuilder::new()
+                .with_width(420.0)
+                .with_visibility(false)
+                .with_horizontal_alignment(HorizontalAlignment::Right)
+                .with_vertical_alignment(VerticalAlignment::Center)
+                .with_children(rows),
+        )
+        .build(ctx);
+
+        Self {
+            root,
+            slots,
+            conflicts_text,
+            listening: None,
+            visible: false,
+        }
+    }
+
+    /// The panel is a separate root widget, so it follows the visibility of the settings menu.
+    fn set_visibility(&mut self, ui: &UserInterface, visible: bool) {
+        if self.visible != visible {
+            self.visible = visible;
+            ui.send(self.root, WidgetMessage::Visibility(visible));
+        }
+    }
+
+    fn binding_label(controls: &Controls, action: ControlAction, index: usize) -> String {
+        controls
+            .bindings(action)
+            .get(index)
+            .map(|key| key.as_ref().to_string())
+            .unwrap_or_else(|| "-".to_string())
+    }
+
+    fn conflicts_label(controls: &Controls) -> String {
+        controls
+            .conflicts()
+            .iter()
+            .map(|(key, actions)| {
+                let actions = actions.iter().map(|a| a.as_ref()).collect::<Vec<_>>();
+                format!("Conflict: {} - {}", key.as_ref(), actions.join(", "))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn sync(&self, ui: &UserInterface, controls: &Controls) {
+        for slot in self.slots.iter() {
+            ui.send(
+                slot.button,
+                ButtonMessage::Content(ButtonContent::text(&Self::binding_label(
+                    controls,
+                    slot.action,
+                    slot.index,
+                ))),
+            );
+        }
+        ui.send(
+            self.conflicts_text,
+            TextMessage::Text(Self::conflicts_label(controls)),
+        );
+    }
+
+    fn handle_ui_message(
+        &mut self,
+        ui: &UserInterface,
+        message: &UiMessage,
+        settings: &mut Settings,
+    ) {
+        if let Some(ButtonMessage::Click) = message.data() {
+            let clicked = self
+                .slots
+                .iter()
+                .position(|slot| slot.button == message.destination());
+            if clicked.is_some() || self.listening.is_some() {
+                // Any other click cancels the previous binding.
+                self.listening = clicked;
+                self.sync(ui, &settings.read().controls);
+            }
+            if let Some(slot) = clicked.and_then(|n| self.slots.get(n)) {
+                ui.send(
+                    slot.button,
+                    ButtonMessage::Content(ButtonContent::text("Press a key...")),
+                );
+            }
+        } else if let Some(WidgetMessage::KeyDown(key)) = message.data() {
+            let Some(slot) = self.listening.take().and_then(|n| self.slots.get(n)) else {
+                return;
+            };
+            let key = match key {
+                KeyCode::Escape => {
+                    self.sync(ui, &settings.read().controls);
+                    return;
+                }
+                KeyCode::Backspace => None,
+                key => Some(*key),
+            };
+            settings
+                .write()
+                .controls
+                .set_binding(slot.action, slot.index, key);
+            self.sync(ui, &settings.read().controls);
         }
     }
 }
@@ -199,9 +680,34 @@ pub struct SettingsMenu {
     mouse_smoothness: Handle<ScrollBar>,
     back: Handle<Button>,
     reset: Handle<Button>,
+    #[visit(skip)]
+    #[reflect(hidden)]
+    controls_panel: ControlsPanel,
 }
 
 impl SettingsMenu {
+    fn create_controls_panel(
+        &mut self,
+        ui: &mut UserInterface,
+        resource_manager: &ResourceManager,
+        settings: &Settings,
+    ) {
+        self.controls_panel = ControlsPanel::new(ui, resource_manager, &settings.read().controls);
+    }
+
+    /// Checks whether the controls page waits for a key to bind.
+    pub fn is_listening_for_key(&self) -> bool {
+        self.controls_panel.listening.is_some()
+    }
+
+    pub fn update(&mut self, ui: &UserInterface) {
+        let is_visible = ui
+            .try_get(self.menu)
+            .map(|n| n.is_globally_visible())
+            .unwrap_or_default();
+        self.controls_panel.set_visibility(ui, is_visible);
+    }
+
     pub fn sync_with_settings(
         &mut self,
         ui: &mut UserInterface,
@@ -246,7 +752,7 @@ impl SettingsMenu {
 
     #[allow(clippy::too_many_arguments)]
     pub fn handle_ui_message(
-        &self,
+        &mut self,
         message: &UiMessage,
         main_menu: Handle<UiNode>,
         ui: &UserInterface,
@@ -256,6 +762,8 @@ impl SettingsMenu {
         game_scene: Handle<Scene>,
         menu_scene: Handle<Scene>,
     ) -> GameResult {
+        self.controls_panel.handle_ui_message(ui, message, settings);
+
         if let Some(SelectorMessage::Current(Some(index))) = message.data() {
             if message.destination() == self.graphics_quality {
                 let mut settings = settings.write();
@@ -288,6 +796,70 @@ impl SettingsMenu {
     }
 }
 
+/// A text of the HUD, that remembers what was sent to the UI to not send the same every frame.
+#[derive(Debug, Default, Clone)]
+struct HudText {
+    handle: Handle<Text>,
+    visible: bool,
+    text: String,
+}
+
+impl HudText {
+    fn new(handle: Handle<Text>) -> Self {
+        Self {
+            handle,
+            ..Default::default()
+        }
+    }
+
+    /// Wraps a text, that was loaded with the UI, so its actual visibility is remembered.
+    fn from_widget(handle: Handle<Text>, ui: &UserInterface) -> Self {
+        Self {
+            handle,
+            visible: ui
+                .try_get(handle)
+                .map(|n| n.visibility())
+                .unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    fn set_visibility(&mut self, ui: &UserInterface, visible: bool) {
+        if self.visible != visible {
+            self.visible = visible;
+            ui.send(self.handle, WidgetMessage::Visibility(visible));
+        }
+    }
+
+    fn set_text(&mut self, ui: &UserInterface, text: String) {
+        if self.text != text {
+            ui.send(self.handle, TextMessage::Text(text.clone()));
+            self.text = text;
+        }
+    }
+}
+
+/// A text in the corner of a viewport in split-screen mode, that remembers its layout.
+#[derive(Debug, Default, Clone)]
+struct SplitScreenText {
+    text: HudText,
+    margin: Thickness,
+    width: f32,
+}
+
+impl SplitScreenText {
+    fn set_layout(&mut self, ui: &UserInterface, margin: Thickness, width: f32) {
+        if self.margin != margin {
+            self.margin = margin;
+            ui.send(self.text.handle, WidgetMessage::Margin(margin));
+        }
+        if self.width != width {
+            self.width = width;
+            ui.send(self.text.handle, WidgetMessage::Width(width));
+        }
+    }
+}
+
 #[derive(Visit, Reflect, Debug, Default, Clone, TypeUuidProvider)]
 #[type_uuid(id = "24d6e2ad-918c-45db-987b-3605d70469c2")]
 pub struct InGameMenu {
@@ -296,58 +868,322 @@ pub struct InGameMenu {
     finished_text_animation: Handle<AnimationPlayer>,
     match_timer_text: Handle<Text>,
     player_position: Handle<Text>,
+    #[visit(skip)]
+    #[reflect(hidden)]
+    player_position_text: HudText,
+    #[visit(skip)]
+    #[reflect(hidden)]
+    countdown_text: HudText,
+    #[visit(skip)]
+    #[reflect(hidden)]
+    standings_text: HudText,
+    #[visit(skip)]
+    #[reflect(hidden)]
+    team_scores_text: HudText,
+    /// Team scores are recalculated from time to time, not every frame.
+    #[visit(skip)]
+    #[reflect(hidden)]
+    team_scores: Vec<(usize, usize)>,
+    #[visit(skip)]
+    #[reflect(hidden)]
+    team_scores_timer: f32,
+    #[visit(skip)]
+    #[reflect(hidden)]
+    effects_text: HudText,
+    #[visit(skip)]
+    #[reflect(hidden)]
+    state_text: HudText,
+    /// Texts of every viewport in split-screen mode.
+    #[visit(skip)]
+    #[reflect(hidden)]
+    split_screen_texts: Vec<SplitScreenText>,
 }
 
 impl InGameMenu {
+    const TEAM_SCORES_UPDATE_INTERVAL: f32 = 0.5;
+
+    fn create_runtime_widgets(
+        &mut self,
+        ui: &mut UserInterface,
+        resource_manager: &ResourceManager,
+    ) {
+        self.standings_text = HudText::new(
+            TextBuilder::new(
+                WidgetBuilder::new()
+                    .with_visibility(false)
+                    .with_horizontal_alignment(HorizontalAlignment::Center)
+                    .with_vertical_alignment(VerticalAlignment::Center),
+            )
+            .with_horizontal_text_alignment(HorizontalAlignment::Center)
+            .with_font(resource_manager.request::<Font>("data/font.ttf"))
+            .with_font_size(28.0.into())
+            .build(&mut ui.build_ctx()),
+        );
+        self.countdown_text = HudText::new(
+            TextBuilder::new(
+                WidgetBuilder::new()
+                    .with_visibility(false)
+                    .with_horizontal_alignment(HorizontalAlignment::Center)
+                    .with_vertical_alignment(VerticalAlignment::Center),
+            )
+            .with_vertical_text_alignment(VerticalAlignment::Center)
+            .with_horizontal_text_alignment(HorizontalAlignment::Center)
+            .with_font(resource_manager.request::<Font>("data/font.ttf"))
+            .with_font_size(96.0.into())
+            .build(&mut ui.build_ctx()),
+        );
+        self.team_scores_text = HudText::new(
+            TextBuilder::new(
+                WidgetBuilder::new()
+                    .with_visibility(false)
+                    .with_margin(Thickness::uniform(10.0))
+                    .with_horizontal_alignment(HorizontalAlignment::Right)
+                    .with_vertical_alignment(VerticalAlignment::Top),
+            )
+            .with_horizontal_text_alignment(HorizontalAlignment::Right)
+            .with_font(resource_manager.request::<Font>("data/font.ttf"))
+            .with_font_size(24.0.into())
+            .build(&mut ui.build_ctx()),
+        );
+        self.effects_text = HudText::new(
+            TextBuilder::new(
+                WidgetBuilder::new()
+                    .with_visibility(false)
+                    .with_margin(Thickness::uniform(10.0))
+                    .with_horizontal_alignment(HorizontalAlignment::Left)
+                    .with_vertical_alignment(VerticalAlignment::Bottom),
+            )
+            .with_font(resource_manager.request::<Font>("data/font.ttf"))
+            .with_font_size(24.0.into())
+            .build(&mut ui.build_ctx()),
+        );
+        self.state_text = HudText::new(
+            TextBuilder::new(
+                WidgetBuilder::new()
+                    .with_visibility(false)
+                    .with_margin(Thickness::uniform(40.0))
+                    .with_horizontal_alignment(HorizontalAlignment::Center)
+                    .with_vertical_alignment(VerticalAlignment::Bottom),
+            )
+            .with_horizontal_text_alignment(HorizontalAlignment::Center)
+            .with_font(resource_manager.request::<Font>("data/font.ttf"))
+            .with_font_size(32.0.into())
+            .build(&mut ui.build_ctx()),
+        );
+        self.split_screen_texts = (0..4)
+            .map(|_| SplitScreenText {
+                text: HudText::new(
+                    TextBuilder::new(
+                        WidgetBuilder::new()
+                            .with_visibility(false)
+                            .with_horizontal_alignment(HorizontalAlignment::Left)
+                            .with_vertical_alignment(VerticalAlignment::Top),
+                    )
+                    .with_font(resource_manager.request::<Font>("data/font.ttf"))
+                    .with_font_size(24.0.into())
+                    .build(&mut ui.build_ctx()),
+                ),
+                ..Default::default()
+            })
+            .collect();
+        self.player_position_text = HudText::from_widget(self.player_position, ui);
+    }
+
+    fn place_text(level: &Level, entry: &LeaderBoardEntry) -> String {
+        match level.game_mode {
+            GameMode::Race => format!(
+                "Place: {} of {}",
+                entry.real_time_position + 1,
+                level.actors.len()
+            ),
+            GameMode::Survival { .. } if entry.eliminated => {
+                format!("Eliminated: {} place", entry.finished_position)
+            }
+            GameMode::Survival { .. } => format!(
+                "Alive: {} of {}",
+                level.leaderboard.alive_count(),
+                level.leaderboard.entries.len()
+            ),
+        }
+    }
+
+    fn effects_text(level: &Level, actor: &Actor) -> String {
+        let mut effects = String::new();
+        for active_effect in &actor.effects {
+            let time_left = (active_effect.expires_at - level.match_time).max(0.0);
+            effects += &format!("{}: {:.0}s\n", active_effect.effect, time_left.ceil());
+        }
+        effects
+    }
+
+    /// Places a text with the place, the effects and the state of every local player in the
+    /// corner of its viewport.
+    fn update_split_screen(
+        &mut self,
+        ui: &UserInterface,
+        graph: &Graph,
+        level: &Level,
+    ) -> GameResult {
+        let mut used_texts = vec![false; self.split_screen_texts.len()];
+        for actor in &level.actors {
+            let node = graph.try_get(*actor)?;
+            let Some(player) = node.try_get_script::<Player>() else {
+                continue;
+            };
+            let Some(text) = self.split_screen_texts.get_mut(player.local_player) else {
+                continue;
+            };
+            if player.actor.kind != ActorKind::Player {
+                continue;
+            }
+
+            let mut content = format!("P{}\n", player.local_player + 1);
+            if let Some(entry) = level.leaderboard.entries.get(actor) {
+                content += &Self::place_text(level, entry);
+                content += "\n";
+            }
+            content += &Self::effects_text(level, &player.actor);
+            match player.actor.state {
+                ActorState::Ragdoll => content += "Knocked down!",
+                ActorState::GettingUp => content += "Getting up...",
+                _ => (),
+            }
+
+            let viewport = split_screen_viewport(player.local_player, level.local_players);
+            let screen_size = ui.screen_size();
+            text.set_layout(
+                ui,
+                Thickness {
+                    left: viewport.x() * screen_size.x + 10.0,
+                    top: viewport.y() * screen_size.y + 10.0,
+                    right: 0.0,
+                    bottom: 0.0,
+                },
+                viewport.w() * screen_size.x - 20.0,
+            );
+            text.text.set_text(ui, content);
+            text.text.set_visibility(ui, true);
+            used_texts[player.local_player] = true;
+        }
+        for (text, used) in self.split_screen_texts.iter_mut().zip(used_texts) {
+            if !used {
+                text.text.set_visibility(ui, false);
+            }
+        }
+        Ok(())
+    }
+
+    /// Shows the state of the local player, when it cannot move.
+    fn on_actor_state_event(
+        &mut self,
+        ui: &UserInterface,
+        game_scene: &Scene,
+        level: &Level,
+        event: &ActorStateEvent,
+    ) -> GameResult {
+        // The actor could be removed already.
+        let Ok(actor) = game_scene
+            .graph
+            .try_get_script_component_of::<Actor>(event.actor)
+        else {
+            return Ok(());
+        };
+        // Every viewport shows its own state in split-screen mode.
+        if actor.kind != ActorKind::Player || level.local_players > 1 {
+            return Ok(());
+        }
+
+        let text = match event.to {
+            ActorState::Ragdoll => Some("Knocked down!"),
+            ActorState::GettingUp => Some("Getting up..."),
+            _ => None,
+        };
+        self.state_text.set_visibility(ui, text.is_some());
+        if let Some(text) = text {
+            self.state_text.set_text(ui, text.to_string());
+        }
+        Ok(())
+    }
+
     fn on_leaderboard_event(
         &self,
         ui: &mut UserInterface,
         game_scene: &Scene,
         event: &LeaderBoardEvent,
     ) -> GameResult {
-        match event {
-            LeaderBoardEvent::Finished { actor, place } => {
-                let actor = game_scene
-                    .graph
-                    .try_get_script_component_of::<Actor>(*actor)?;
-
-                let suffix = match place {
-                    1 => "st",
-                    2 => "nd",
-                    3 => "d",
-                    _ => "th",
-                };
-                ui.send(
-                    self.finished_text,
-                    TextMessage::Text(format!("{} finished {place}{suffix}", actor.name)),
-                );
+        let (LeaderBoardEvent::Finished { actor, place }
+        | LeaderBoardEvent::Eliminated { actor, place }) = event;
 
-                fn enable_animation(
-                    ui: &UserInterface,
-                    widget: Handle<AnimationPlayer>,
-                    name: &str,
-                ) {
-                    ui.send(
-                        widget,
-                        AnimationPlayerMessage::EnableAnimation {
-                            animation: name.to_string(),
-                            enabled: true,
-                        },
-                    );
-                }
-                let id = "Animation".to_string();
-                enable_animation(ui, self.finished_text_animation, "Animation");
-                enable_animation(ui, self.finished_text_animation, &id);
-                ui.send(
-                    self.finished_text_animation,
-                    AnimationPlayerMessage::RewindAnimation { animation: id },
-                );
+        let actor = game_scene
+            .graph
+            .try_get_script_component_of::<Actor>(*actor)?;
+
+        let suffix = match place {
+            1 => "st",
+            2 => "nd",
+            3 => "d",
+            _ => "th",
+        };
+        let text = match event {
+            LeaderBoardEvent::Finished { .. } => format!("{} finished {place}{suffix}", actor.name),
+            LeaderBoardEvent::Eliminated { .. } => {
+                format!("{} is out, {place}{suffix} place", actor.name)
             }
+        };
+        ui.send(self.finished_text, TextMessage::Text(text));
+
+        fn enable_animation(ui: &UserInterface, widget: Handle<AnimationPlayer>, name: &str) {
+            ui.send(
+                widget,
+                AnimationPlayerMessage::EnableAnimation {
+                    animation: name.to_string(),
+                    enabled: true,
+                },
+            );
         }
+        let id = "Animation".to_string();
+        enable_animation(ui, self.finished_text_animation, "Animation");
+        enable_animation(ui, self.finished_text_animation, &id);
+        ui.send(
+            self.finished_text_animation,
+            AnimationPlayerMessage::RewindAnimation { animation: id },
+        );
         Ok(())
     }
 
-    fn update(&self, ui: &UserInterface, graph: Option<&Graph>, level: &Level) -> GameResult {
+    fn update_standings(
+        &mut self,
+        ui: &UserInterface,
+        level: &Level,
+        standings: Option<&TournamentStandingsMessage>,
+    ) {
+        let standings = standings.filter(|_| level.stage == MatchStage::Finished);
+        self.standings_text
+            .set_visibility(ui, level.scene.is_some() && standings.is_some());
+        if let Some(standings) = standings {
+            let mut text = format!(
+                "Round {} of {} results\n",
+                standings.round + 1,
+                standings.total_rounds
+            );
+            for (n, standing) in standings.standings.iter().enumerate() {
+                text += &format!("{}. {} - {} pts", n + 1, standing.name, standing.points);
+                if standing.eliminated {
+                    text += " (eliminated)";
+                }
+                text += "\n";
+            }
+            self.standings_text.set_text(ui, text);
+        }
+    }
+
+    fn update(
+        &mut self,
+        ui: &UserInterface,
+        graph: Option<&Graph>,
+        level: &Level,
+        dt: f32,
+    ) -> GameResult {
         let minutes = (level.match_timer / 60.0) as u32;
         let seconds = (level.match_timer % 60.0) as u32;
         ui.send(
@@ -356,18 +1192,67 @@ impl InGameMenu {
         );
         ui.send(self.root, WidgetMessage::Visibility(level.scene.is_some()));
 
+        let countdown = match level.stage {
+            MatchStage::WaitingForPlayers | MatchStage::Finished => None,
+            MatchStage::Countdown { .. } => level.stage.countdown_number().map(|n| n.to_string()),
+            MatchStage::Running => (level.match_time < 1.0).then(|| "GO!".to_string()),
+        };
+        self.countdown_text
+            .set_visibility(ui, level.scene.is_some() && countdown.is_some());
+        if let Some(countdown) = countdown {
+            self.countdown_text.set_text(ui, countdown);
+        }
+
+        self.team_scores_timer -= dt;
+        if self.team_scores_timer <= 0.0 {
+            self.team_scores_timer = Self::TEAM_SCORES_UPDATE_INTERVAL;
+            let team_scores = level.team_scores();
+            if self.team_scores != team_scores {
+                let text = team_scores
+                    .iter()
+                    .map(|(team, points)| format!("{}: {points}", team::team_name(*team)))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                self.team_scores_text.set_text(ui, text);
+                self.team_scores = team_scores;
+            }
+        }
+        self.team_scores_text
+            .set_visibility(ui, level.scene.is_some() && !self.team_scores.is_empty());
+
+        // In split-screen mode every viewport has its own texts instead of the single HUD.
+        let split_screen = level.scene.is_some() && level.local_players > 1;
+        self.effects_text
+            .set_visibility(ui, level.scene.is_some() && !split_screen);
+        self.player_position_text.set_visibility(ui, !split_screen);
+        if split_screen {
+            self.state_text.set_visibility(ui, false);
+        } else {
+            for text in self.split_screen_texts.iter_mut() {
+                text.text.set_visibility(ui, false);
+            }
+        }
+
         if let Some(graph) = graph {
+            if split_screen {
+                return self.update_split_screen(ui, graph, level);
+            }
+
+            let mut effects = String::new();
+            for actor in &level.actors {
+                let actor_ref = graph.try_get_script_component_of::<Actor>(*actor)?;
+                if actor_ref.kind == ActorKind::Player {
+                    effects = Self::effects_text(level, actor_ref);
+                    break;
+                }
+            }
+            self.effects_text.set_text(ui, effects);
+
             for (actor, entry) in &level.leaderboard.entries {
                 let actor_ref = graph.try_get_script_component_of::<Actor>(*actor)?;
                 if actor_ref.kind == ActorKind::Player {
-                    ui.send(
-                        self.player_position,
-                        TextMessage::Text(format!(
-                            "Place: {} of {}",
-                            entry.real_time_position + 1,
-                            level.actors.len()
-                        )),
-                    );
+                    self.player_position_text
+                        .set_text(ui, Self::place_text(level, entry));
 
                     break;
                 }
@@ -388,6 +1273,30 @@ pub struct LeaderBoardChannel {
     receiver: Receiver<LeaderBoardEvent>,
 }
 
+#[derive(Visit, Reflect, Debug, TypeUuidProvider)]
+#[type_uuid(id = "3f9c7a2e-8d14-4b6e-a5f0-1c7e9b3d5a28")]
+pub struct ActorStateChannel {
+    #[visit(skip)]
+    #[reflect(hidden)]
+    pub sender: Sender<ActorStateEvent>,
+    #[visit(skip)]
+    #[reflect(hidden)]
+    receiver: Receiver<ActorStateEvent>,
+}
+
+impl Default for ActorStateChannel {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self { sender, receiver }
+    }
+}
+
+impl Clone for ActorStateChannel {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
 impl Default for LeaderBoardChannel {
     fn default() -> Self {
         let (sender, receiver) = mpsc::channel();
@@ -411,6 +1320,10 @@ pub struct MenuSceneData {
     win_camera: Handle<Node>,
     main_camera: Handle<Node>,
     clock_ticking: Handle<Sound>,
+    #[visit(optional)]
+    countdown_tick_sound: Handle<Sound>,
+    #[visit(optional)]
+    countdown_go_sound: Handle<Sound>,
 }
 
 #[derive(Visit, Reflect, Default, Debug, Clone, TypeUuidProvider)]
@@ -435,6 +1348,11 @@ pub struct Menu {
     pub menu_data: MenuData,
     pub menu_scene_data: MenuSceneData,
     pub leader_board_channel: LeaderBoardChannel,
+    #[visit(optional)]
+    pub actor_state_channel: ActorStateChannel,
+    #[visit(skip)]
+    #[reflect(hidden)]
+    pub touch_controls: TouchControls,
 }
 
 fn try_connect_to_server<A>(server_addr: A) -> Option<Client>
@@ -461,6 +1379,16 @@ impl Menu {
         menu_data
             .settings_menu
             .sync_with_settings(&mut ui, ctx.resource_manager, settings);
+        menu_data
+            .settings_menu
+            .create_controls_panel(&mut ui, ctx.resource_manager, settings);
+        menu_data
+            .in_game_menu
+            .create_runtime_widgets(&mut ui, ctx.resource_manager);
+        menu_data
+            .server_menu
+            .create_match_setup_panel(&mut ui, ctx.resource_manager);
+        let touch_controls = TouchControls::new(&mut ui, ctx.resource_manager);
 
         ctx.task_pool.spawn_plugin_task(
             ctx.resource_manager
@@ -481,6 +1409,8 @@ impl Menu {
             menu_data,
             menu_scene_data: Default::default(),
             leader_board_channel: Default::default(),
+            actor_state_channel: Default::default(),
+            touch_controls,
         }
     }
 
@@ -561,6 +1491,23 @@ impl Menu {
         Ok(())
     }
 
+    pub fn on_countdown_changed(&self, ctx: &mut PluginContext, stage: MatchStage) -> GameResult {
+        let sound = match stage {
+            MatchStage::WaitingForPlayers | MatchStage::Finished => return Ok(()),
+            MatchStage::Countdown { .. } => self.menu_scene_data.countdown_tick_sound,
+            MatchStage::Running => self.menu_scene_data.countdown_go_sound,
+        };
+        if sound.is_some() {
+            let scene = ctx.scenes.try_get_mut(self.scene)?;
+            utils::try_play_sound(sound, &mut scene.graph)?;
+        }
+        Ok(())
+    }
+
+    pub fn is_listening_for_key(&self) -> bool {
+        self.menu_data.settings_menu.is_listening_for_key()
+    }
+
     pub fn set_menu_visibility(&self, ui: &UserInterface, visible: bool) {
         ui.send(
             self.menu_data.main_menu_root,
@@ -591,23 +1538,71 @@ impl Menu {
             .unwrap_or_default()
     }
 
+    /// Impacts are detected on the server only, so remote clients see nothing here.
+    fn last_impact_text(scenes: &SceneContainer, level: &Level) -> String {
+        let Ok(scene) = scenes.try_get(level.scene) else {
+            return Default::default();
+        };
+        for actor in level.actors.iter() {
+            let Ok(actor) = scene.graph.try_get_script_component_of::<Actor>(*actor) else {
+                continue;
+            };
+            if actor.kind != ActorKind::Player {
+                continue;
+            }
+            return match actor.last_impact {
+                Some(impact) => format!(
+                    "\nImpact with {}: speed {:.1}, impulse {:.2}, strength {:.2}{}",
+                    impact.source.as_ref(),
+                    impact.relative_speed,
+                    impact.impulse,
+                    impact.strength,
+                    if impact.knocked_down {
+                        format!(
+                            ", stunned for {:.1}s",
+                            actor.knockdown.stun_time(impact.strength)
+                        )
+                    } else {
+                        String::new()
+                    }
+                ),
+                None => "\nNo impacts".to_string(),
+            };
+        }
+        Default::default()
+    }
+
     pub fn update(
-        &self,
+        &mut self,
         ctx: &mut PluginContext,
         server: &Option<Server>,
         client: &Option<Client>,
         level: &mut Level,
+        debug_settings: &DebugSettings,
     ) -> GameResult {
+        self.menu_data.server_menu.update(ctx, server);
+        self.menu_data
+            .settings_menu
+            .update(ctx.user_interfaces.first());
+        let is_menu_active = self.is_active(ctx.user_interfaces.first());
+        self.touch_controls.update(
+            ctx.user_interfaces.first(),
+            level.scene.is_some(),
+            is_menu_active,
+        );
+
         let menu = &self.menu_data;
         let menu_scene = &self.menu_scene_data;
 
-        menu.server_menu.update(ctx, server);
-
         if let GraphicsContext::Initialized(graphics_context) = ctx.graphics_context {
             let fps = graphics_context.renderer.get_statistics().frames_per_second;
+            let mut text = format!("FPS: {fps}");
+            if debug_settings.show_impacts {
+                text += &Self::last_impact_text(ctx.scenes, level);
+            }
             ctx.user_interfaces
                 .first()
-                .send(menu.debug_text, TextMessage::Text(format!("FPS: {fps}")));
+                .send(menu.debug_text, TextMessage::Text(text));
         }
 
         if let Ok(scene) = ctx.scenes.try_get_mut(self.scene) {
@@ -622,11 +1617,31 @@ impl Menu {
             scene.graph[menu_scene.main_camera].set_enabled(!is_in_win_state);
         }
 
-        menu.in_game_menu.update(
+        self.menu_data.in_game_menu.update(
             ctx.user_interfaces.first(),
             ctx.scenes.try_get_mut(level.scene).ok().map(|s| &s.graph),
             level,
+            ctx.dt,
         )?;
+        self.menu_data.in_game_menu.update_standings(
+            ctx.user_interfaces.first(),
+            level,
+            client
+                .as_ref()
+                .and_then(|client| client.tournament_standings.as_ref()),
+        );
+
+        while let Ok(event) = self.actor_state_channel.receiver.try_recv() {
+            let game_scene = ctx.scenes.try_get(level.scene)?;
+            self.menu_data.in_game_menu.on_actor_state_event(
+                ctx.user_interfaces.first(),
+                game_scene,
+                level,
+                &event,
+            )?;
+        }
+
+        let menu = &self.menu_data;
 
         while let Ok(event) = self.leader_board_channel.receiver.try_recv() {
             let game_scene = ctx.scenes.try_get_mut(level.scene)?;
@@ -646,6 +1661,10 @@ impl Menu {
                         utils::try_play_sound(menu_scene.clock_ticking, &mut scene.graph)?;
                     }
                 }
+                LeaderBoardEvent::Eliminated { .. } => {
+                    let scene = ctx.scenes.try_get_mut(self.scene)?;
+                    utils::try_play_sound(menu_scene.click_end_sound, &mut scene.graph)?;
+                }
             }
         }
 
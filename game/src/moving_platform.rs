@@ -0,0 +1,346 @@
+//! Moving platform is a kinematic rigid body, that moves along a set of waypoints and/or rotates
+//! around its vertical axis. The state of the platform is derived from the match clock, so every
+//! peer calculates the same state.
+
+use crate::{actor::Actor, Game};
+use fyrox::graph::SceneGraph;
+use fyrox::plugin::error::{GameError, GameResult};
+use fyrox::{
+    core::{
+        algebra::{Matrix4, Point3, UnitQuaternion, Vector3},
+        pool::Handle,
+        reflect::prelude::*,
+        type_traits::prelude::*,
+        variable::InheritableVariable,
+        visitor::prelude::*,
+    },
+    scene::{collider::Collider, graph::Graph, node::Node},
+    script::{ScriptContext, ScriptTrait},
+};
+use std::collections::HashSet;
+use strum_macros::{AsRefStr, EnumString, VariantNames};
+
+#[derive(
+    Default,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Visit,
+    Reflect,
+    TypeUuidProvider,
+    AsRefStr,
+    EnumString,
+    VariantNames,
+)]
+#[type_uuid(id = "3e8f5d21-7b4c-4a09-8f62-d1c0a9b7e534")]
+pub enum PathMode {
+    /// The platform goes back to the first waypoint from the last one.
+    #[default]
+    Loop,
+    /// The platform goes through the waypoints in reverse order after the last one.
+    PingPong,
+}
+
+#[derive(Clone, Debug, Visit, Reflect, TypeUuidProvider, ComponentProvider)]
+#[type_uuid(id = "9a6b1c0d-2e47-4f3a-b8d5-6c7e0f1a2b93")]
+#[visit(optional)]
+pub struct MovingPlatform {
+    /// Points of the path. The initial position of the platform is the first point of the path.
+    waypoints: InheritableVariable<Vec<Handle<Node>>>,
+    mode: InheritableVariable<PathMode>,
+    /// Movement speed (in units per second).
+    speed: InheritableVariable<f32>,
+    /// Amount of time (in seconds) that the platform stands still at each waypoint.
+    pause: InheritableVariable<f32>,
+    /// Rotation speed (in degrees per second) around the vertical axis.
+    angular_speed: InheritableVariable<f32>,
+    #[reflect(hidden)]
+    #[visit(skip)]
+    path: Vec<Vector3<f32>>,
+    #[reflect(hidden)]
+    #[visit(skip)]
+    initial_rotation: UnitQuaternion<f32>,
+    /// Actors, that were carried by the platform at the previous frame.
+    #[reflect(hidden)]
+    #[visit(skip)]
+    carried_actors: HashSet<Handle<Node>>,
+}
+
+impl Default for MovingPlatform {
+    fn default() -> Self {
+        Self {
+            waypoints: Default::default(),
+            mode: Default::default(),
+            speed: 2.0.into(),
+            pause: 1.0.into(),
+            angular_speed: 0.0.into(),
+            path: Default::default(),
+            initial_rotation: Default::default(),
+            carried_actors: Default::default(),
+        }
+    }
+}
+
+impl MovingPlatform {
+    /// Returns a list of path legs (pairs of point indices) for a single cycle of movement.
+    fn legs(&self) -> Vec<(usize, usize)> {
+        let count = self.path.len();
+        if count < 2 {
+            return Vec::new();
+        }
+        match *self.mode {
+            PathMode::Loop => (0..count).map(|i| (i, (i + 1) % count)).collect(),
+            PathMode::PingPong => (0..count - 1)
+                .map(|i| (i, i + 1))
+                .chain((1..count).rev().map(|i| (i, i - 1)))
+                .collect(),
+        }
+    }
+
+    fn position_at(&self, time: f32) -> Vector3<f32> {
+        let Some(first) = self.path.first().cloned() else {
+            return Default::default();
+        };
+
+        let speed = self.speed.max(f32::EPSILON);
+        let pause = self.pause.max(0.0);
+        let legs = self
+            .legs()
+            .into_iter()
+            .map(|(from, to)| {
+                let (from, to) = (self.path[from], self.path[to]);
+                (from, to, from.metric_distance(&to) / speed)
+            })
+            .collect::<Vec<_>>();
+
+        let cycle_duration = legs
+            .iter()
+            .map(|(_, _, duration)| duration + pause)
+            .sum::<f32>();
+        if cycle_duration <= 0.0 {
+            return first;
+        }
+
+        let mut time = time.max(0.0) % cycle_duration;
+        for (from, to, duration) in legs {
+            if time < duration {
+                return from.lerp(&to, time / duration);
+            }
+            time -= duration;
+            if time < pause {
+                return to;
+            }
+            time -= pause;
+        }
+
+        first
+    }
+
+    fn rotation_at(&self, time: f32) -> UnitQuaternion<f32> {
+        UnitQuaternion::from_axis_angle(
+            &Vector3::y_axis(),
+            self.angular_speed.to_radians() * time.max(0.0),
+        ) * self.initial_rotation
+    }
+
+    /// Returns the global transform and the global rotation of the platform's parent. The path
+    /// is defined in the parent's space, because the platform is moved by its local transform.
+    fn parent_transform(
+        platform: Handle<Node>,
+        graph: &Graph,
+    ) -> Result<(Matrix4<f32>, UnitQuaternion<f32>), GameError> {
+        let parent = graph.try_get(platform)?.parent();
+        Ok(graph.try_get(parent).map_or_else(
+            |_| (Matrix4::identity(), UnitQuaternion::identity()),
+            |parent| (parent.global_transform(), parent.global_rotation()),
+        ))
+    }
+
+    /// Collects actors, that have contact with any collider of the platform.
+    fn find_carried_actors(
+        &self,
+        platform: Handle<Node>,
+        actors: &HashSet<Handle<Node>>,
+        graph: &Graph,
+    ) -> Result<HashSet<Handle<Node>>, GameError> {
+        let mut actor_colliders = Vec::new();
+        for actor in actors {
+            let actor_script = graph.try_get_script_component_of::<Actor>(*actor)?;
+            actor_colliders.push((*actor, actor_script.collider));
+        }
+
+        let mut carried = HashSet::new();
+        for child in graph.try_get(platform)?.children() {
+            let Ok(collider) = graph.try_get_of_type::<Collider>(*child) else {
+                continue;
+            };
+            for contact in collider.contacts(&graph.physics) {
+                if !contact.has_any_active_contact {
+                    continue;
+                }
+                for (actor, actor_collider) in actor_colliders.iter() {
+                    if contact.collider1 == *actor_collider || contact.collider2 == *actor_collider
+                    {
+                        carried.insert(*actor);
+                    }
+                }
+            }
+        }
+        Ok(carried)
+    }
+}
+
+impl ScriptTrait for MovingPlatform {
+    fn on_start(&mut self, ctx: &mut ScriptContext) -> GameResult {
+        let graph = &ctx.scene.graph;
+        let this = graph.try_get(ctx.handle)?;
+        self.initial_rotation = **this.local_transform().rotation();
+        self.path = vec![**this.local_transform().position()];
+        let (parent_transform, _) = Self::parent_transform(ctx.handle, graph)?;
+        let to_parent_space = parent_transform
+            .try_inverse()
+            .unwrap_or_else(Matrix4::identity);
+        for waypoint in self.waypoints.iter() {
+            let position = graph.try_get(*waypoint)?.global_position();
+            self.path.push(
+                to_parent_space
+                    .transform_point(&Point3::from(position))
+                    .coords,
+            );
+        }
+        Ok(())
+    }
+
+    fn on_update(&mut self, ctx: &mut ScriptContext) -> GameResult {
+        let game = ctx.plugins.get::<Game>();
+        let time = game.level.match_time;
+
+        let transform = ctx.scene.graph.try_get(ctx.handle)?.local_transform();
+        let prev_position = **transform.position();
+        let prev_rotation = **transform.rotation();
+
+        let position = self.position_at(time);
+        let rotation = self.rotation_at(time);
+        ctx.scene
+            .graph
+            .try_get_mut(ctx.handle)?
+            .local_transform_mut()
+            .set_position(position)
+            .set_rotation(rotation);
+
+        // Physics works on the server only, so there is nothing to carry on clients.
+        if game.is_client() || ctx.dt <= 0.0 {
+            return Ok(());
+        }
+
+        let carried_actors =
+            self.find_carried_actors(ctx.handle, &game.level.actors, &ctx.scene.graph)?;
+        // Actors are moved in the global space.
+        let (parent_transform, parent_rotation) =
+            Self::parent_transform(ctx.handle, &ctx.scene.graph)?;
+        let position = parent_transform
+            .transform_point(&Point3::from(position))
+            .coords;
+        let prev_position = parent_transform
+            .transform_point(&Point3::from(prev_position))
+            .coords;
+        let delta_rotation =
+            parent_rotation * rotation * prev_rotation.inverse() * parent_rotation.inverse();
+
+        for actor in carried_actors.iter() {
+            let actor_script = ctx
+                .scene
+                .graph
+                .try_get_script_component_of::<Actor>(*actor)?;
+            let actor_position = ctx
+                .scene
+                .graph
+                .try_get(actor_script.rigid_body)?
+                .global_position();
+            // A point of the platform under the actor moves along with the platform.
+            let new_position =
+                position + delta_rotation.transform_vector(&(actor_position - prev_position));
+            let velocity = (new_position - actor_position).scale(1.0 / ctx.dt);
+            ctx.scene
+                .graph
+                .try_get_script_component_of_mut::<Actor>(*actor)?
+                .platform_velocity = velocity;
+        }
+
+        for actor in self.carried_actors.difference(&carried_actors) {
+            if let Ok(actor_script) = ctx
+                .scene
+                .graph
+                .try_get_script_component_of_mut::<Actor>(*actor)
+            {
+                actor_script.platform_velocity = Vector3::default();
+            }
+        }
+        self.carried_actors = carried_actors;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn platform(mode: PathMode, path: Vec<Vector3<f32>>) -> MovingPlatform {
+        MovingPlatform {
+            mode: mode.into(),
+            speed: 1.0.into(),
+            pause: 1.0.into(),
+            path,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn position_at_loop() {
+        let platform = platform(
+            PathMode::Loop,
+            vec![
+                Vector3::new(0.0, 0.0, 0.0),
+                Vector3::new(2.0, 0.0, 0.0),
+                Vector3::new(2.0, 0.0, 2.0),
+            ],
+        );
+        // The legs take 2, 2 and 2.83 seconds, every leg is followed by a pause of 1 second.
+        assert_eq!(platform.position_at(0.0), Vector3::new(0.0, 0.0, 0.0));
+        assert_eq!(platform.position_at(1.0), Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(platform.position_at(2.5), Vector3::new(2.0, 0.0, 0.0));
+        assert_eq!(platform.position_at(4.0), Vector3::new(2.0, 0.0, 1.0));
+        let cycle = 9.0 + 8.0f32.sqrt();
+        assert!(
+            platform
+                .position_at(cycle + 1.0)
+                .metric_distance(&Vector3::x())
+                < 1.0e-4
+        );
+        assert_eq!(platform.position_at(-1.0), Vector3::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn position_at_ping_pong() {
+        let platform = platform(
+            PathMode::PingPong,
+            vec![Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 4.0, 0.0)],
+        );
+        assert_eq!(platform.position_at(2.0), Vector3::new(0.0, 2.0, 0.0));
+        assert_eq!(platform.position_at(4.5), Vector3::new(0.0, 4.0, 0.0));
+        assert_eq!(platform.position_at(7.0), Vector3::new(0.0, 2.0, 0.0));
+        assert_eq!(platform.position_at(9.5), Vector3::new(0.0, 0.0, 0.0));
+        assert_eq!(platform.position_at(11.0), Vector3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn position_at_without_path() {
+        let empty = platform(PathMode::Loop, Vec::new());
+        assert_eq!(empty.position_at(3.0), Vector3::default());
+
+        let single = platform(PathMode::PingPong, vec![Vector3::new(1.0, 2.0, 3.0)]);
+        assert_eq!(single.position_at(3.0), Vector3::new(1.0, 2.0, 3.0));
+    }
+}
@@ -1,5 +1,12 @@
-use crate::level::LeaderBoardEntry;
-use crate::{actor::ActorKind, player::InputController};
+use crate::crumble::TileState;
+use crate::game_mode::GameMode;
+use crate::level::{LeaderBoardEntry, MatchStage};
+use crate::pickup::PickupEffect;
+use crate::tournament::TournamentStandingsMessage;
+use crate::{
+    actor::{ActorKind, ActorState},
+    player::InputController,
+};
 use fyrox::{
     core::{
         algebra::{UnitQuaternion, Vector3},
@@ -24,6 +31,7 @@ pub struct InstanceDescriptor {
     pub position: Vector3<f32>,
     pub rotation: UnitQuaternion<f32>,
     pub velocity: Vector3<f32>, // Rigid body only.
+    pub mass: Option<f32>,      // Rigid body only.
     pub ids: FxHashMap<Handle<Node>, SceneNodeId>,
 }
 
@@ -31,6 +39,12 @@ pub struct InstanceDescriptor {
 pub struct PlayerDescriptor {
     pub instance: InstanceDescriptor,
     pub kind: ActorKind,
+    pub participant: usize,
+    pub spectator: bool,
+    pub team: Option<usize>,
+    /// Index of the local player on the receiving client, it is used only for
+    /// [`crate::actor::ActorKind::Player`].
+    pub local_player: usize,
 }
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
@@ -50,15 +64,49 @@ pub struct LeaderBoardMessage {
     pub players: Vec<LeaderBoardEntry>,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MatchStateMessage {
+    pub stage: MatchStage,
+    pub match_timer: f32,
+    pub match_time: f32,
+    pub game_mode: GameMode,
+}
+
 /// A message sent from the server to a client.
 #[derive(Serialize, Deserialize, Debug)]
 pub enum ServerMessage {
-    LoadLevel { path: PathBuf },
+    LoadLevel {
+        path: PathBuf,
+    },
     UpdateTick(UpdateTickMessage),
     AddPlayers(Vec<PlayerDescriptor>),
     Instantiate(Vec<InstanceDescriptor>),
+    Remove(Vec<SceneNodeId>),
     EndMatch,
     LeaderBoard(LeaderBoardMessage),
+    MatchState(MatchStateMessage),
+    TournamentStandings(TournamentStandingsMessage),
+    PickupCollected {
+        pickup: SceneNodeId,
+        actor: SceneNodeId,
+        effect: PickupEffect,
+        expires_at: f32,
+    },
+    PickupRespawned {
+        pickup: SceneNodeId,
+    },
+    InteractableState {
+        node: SceneNodeId,
+        active: bool,
+    },
+    TileState {
+        node: SceneNodeId,
+        state: TileState,
+    },
+    ActorState {
+        actor: SceneNodeId,
+        state: ActorState,
+    },
 }
 
 /// A message sent from a client to the server.
@@ -68,4 +116,6 @@ pub enum ClientMessage {
         player: SceneNodeId,
         input_state: InputController,
     },
+    /// A client has finished loading the level and is ready to start the match.
+    LevelLoaded,
 }
@@ -0,0 +1,164 @@
+//! Pickups give actors temporary power-ups. A collected pickup disappears and respawns after a
+//! delay.
+
+use crate::{actor::Actor, net::ServerMessage, Game};
+use fyrox::graph::SceneGraph;
+use fyrox::plugin::error::GameResult;
+use fyrox::{
+    core::{
+        math::aabb::AxisAlignedBoundingBox, pool::Handle, reflect::prelude::*,
+        type_traits::prelude::*, variable::InheritableVariable, visitor::prelude::*,
+    },
+    scene::sound::Sound,
+    script::{ScriptContext, ScriptTrait},
+};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use strum_macros::{AsRefStr, EnumString, VariantNames};
+
+#[derive(
+    Default,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Visit,
+    Reflect,
+    Serialize,
+    Deserialize,
+    TypeUuidProvider,
+    AsRefStr,
+    EnumString,
+    VariantNames,
+)]
+#[type_uuid(id = "0b3c1f4e-6a0f-4c57-9c1d-7e5d8a2b9f41")]
+pub enum PickupEffect {
+    /// Increases running speed of the actor.
+    #[default]
+    SpeedBoost,
+    /// Increases jump height of the actor.
+    SuperJump,
+    /// Hits of cannon balls (and other hard impacts) do not knock the actor down.
+    Shield,
+    /// The actor cannot be turned into a ragdoll at all.
+    RagdollImmunity,
+}
+
+impl PickupEffect {
+    pub const SPEED_BOOST_FACTOR: f32 = 1.6;
+    pub const SUPER_JUMP_FACTOR: f32 = 1.5;
+}
+
+impl Display for PickupEffect {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PickupEffect::SpeedBoost => write!(f, "Speed Boost"),
+            PickupEffect::SuperJump => write!(f, "Super Jump"),
+            PickupEffect::Shield => write!(f, "Shield"),
+            PickupEffect::RagdollImmunity => write!(f, "Immunity"),
+        }
+    }
+}
+
+/// An effect that is applied to an actor. Expiration time is measured by the match clock, so
+/// it is the same on every peer.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Visit, Reflect)]
+pub struct ActiveEffect {
+    pub effect: PickupEffect,
+    pub expires_at: f32,
+}
+
+#[derive(Clone, Debug, Visit, Reflect, TypeUuidProvider, ComponentProvider)]
+#[type_uuid(id = "5f0e2c4a-8d3b-4b1e-a6f7-2c9d0e1b3a58")]
+#[visit(optional)]
+pub struct Pickup {
+    effect: InheritableVariable<PickupEffect>,
+    /// Duration (in seconds) of the effect.
+    duration: InheritableVariable<f32>,
+    /// Amount of time (in seconds) after which a collected pickup appears again.
+    respawn_delay: InheritableVariable<f32>,
+    collect_sound: InheritableVariable<Handle<Sound>>,
+    #[reflect(hidden)]
+    respawn_time: Option<f32>,
+}
+
+impl Default for Pickup {
+    fn default() -> Self {
+        Self {
+            effect: Default::default(),
+            duration: 5.0.into(),
+            respawn_delay: 10.0.into(),
+            collect_sound: Default::default(),
+            respawn_time: None,
+        }
+    }
+}
+
+impl ScriptTrait for Pickup {
+    fn on_update(&mut self, ctx: &mut ScriptContext) -> GameResult {
+        let game = ctx.plugins.get_mut::<Game>();
+        if game.is_client() {
+            return Ok(());
+        }
+
+        let Some(server) = game.server.as_mut() else {
+            return Ok(());
+        };
+
+        let match_time = game.level.match_time;
+        let pickup_id = ctx.scene.graph.try_get(ctx.handle)?.instance_id();
+
+        if let Some(respawn_time) = self.respawn_time {
+            if match_time >= respawn_time {
+                self.respawn_time = None;
+                ctx.scene.graph[ctx.handle].set_visibility(true);
+                server.broadcast_message_to_clients(ServerMessage::PickupRespawned {
+                    pickup: pickup_id,
+                });
+            }
+            return Ok(());
+        }
+
+        if game.level.is_movement_locked() {
+            return Ok(());
+        }
+
+        let this = &ctx.scene.graph[ctx.handle];
+        let self_bounds = AxisAlignedBoundingBox::unit().transform(&this.global_transform());
+
+        for actor_handle in game.level.actors.iter() {
+            let graph = &ctx.scene.graph;
+            let actor_script = graph.try_get_script_component_of::<Actor>(*actor_handle)?;
+            let rigid_body = graph.try_get(actor_script.rigid_body)?;
+            if !self_bounds.is_contains_point(rigid_body.global_position()) {
+                continue;
+            }
+
+            let expires_at = match_time + *self.duration;
+            let actor_id = graph.try_get(*actor_handle)?.instance_id();
+            ctx.scene
+                .graph
+                .try_get_script_component_of_mut::<Actor>(*actor_handle)?
+                .set_effect(*self.effect, expires_at);
+
+            ctx.scene.graph[ctx.handle].set_visibility(false);
+            if let Ok(sound) = ctx.scene.graph.try_get_mut(*self.collect_sound) {
+                sound.set_playback_time(0.0);
+                sound.play();
+            }
+
+            self.respawn_time = Some(match_time + *self.respawn_delay);
+            server.broadcast_message_to_clients(ServerMessage::PickupCollected {
+                pickup: pickup_id,
+                actor: actor_id,
+                effect: *self.effect,
+                expires_at,
+            });
+
+            break;
+        }
+
+        Ok(())
+    }
+}
@@ -2,39 +2,204 @@
 
 use crate::actor::ActorKind;
 use crate::{
-    actor::{Actor, ActorMessage},
+    actor::{Actor, ActorMessage, ActorState},
+    camera::split_screen_viewport,
+    controls::ControlAction,
+    gamepad::{GamepadAction, GamepadPad},
     net::ClientMessage,
+    touch::TouchControls,
     CameraController, Event, Game,
 };
 use fyrox::plugin::error::GameResult;
 use fyrox::{
     core::{
-        algebra::Vector3, log::Log, math::SmoothAngle, pool::Handle, reflect::prelude::*,
-        type_traits::prelude::*, visitor::prelude::*,
+        algebra::{Vector2, Vector3},
+        log::Log,
+        math::SmoothAngle,
+        pool::Handle,
+        reflect::prelude::*,
+        type_traits::prelude::*,
+        visitor::prelude::*,
     },
     event::{DeviceEvent, ElementState, MouseButton, WindowEvent},
     graph::SceneGraph,
-    keyboard::{KeyCode, PhysicalKey},
+    keyboard::PhysicalKey,
     scene::node::Node,
     script::{
         ScriptContext, ScriptDeinitContext, ScriptMessageContext, ScriptMessagePayload, ScriptTrait,
     },
+    utils::translate_key_to_ui,
 };
 use serde::{Deserialize, Serialize};
 use std::ops::Range;
 
-#[derive(Clone, Default, Debug, Visit, Serialize, Deserialize)]
+#[derive(Clone, Default, Debug, PartialEq, Visit, Serialize, Deserialize)]
 pub struct InputController {
     pub move_forward: bool,
     pub move_backward: bool,
     pub move_left: bool,
     pub move_right: bool,
     pub jump: bool,
+    /// Total number of jump key presses. Unlike the key state, it cannot be lost when a short
+    /// press and a release arrive to the server at the same frame.
+    #[visit(optional)]
+    pub jump_presses: u32,
+    #[visit(optional)]
+    pub interact: bool,
+    #[visit(optional)]
+    pub grab: bool,
+    #[visit(optional)]
+    pub shove: bool,
+    #[visit(optional)]
+    pub dive: bool,
+    /// Total number of dive key presses, works the same way as `jump_presses`.
+    #[visit(optional)]
+    pub dive_presses: u32,
+    /// Analog movement from a gamepad stick (`-1..1`), positive values go left.
+    #[visit(optional)]
+    pub analog_left: f32,
+    /// Analog movement from a gamepad stick (`-1..1`), positive values go forward.
+    #[visit(optional)]
+    pub analog_forward: f32,
     pub target_pitch: f32,
     pub target_yaw: f32,
 }
 
 impl InputController {
+    /// Returns the movement direction in the local space of the actor, `x` goes left and `y` goes
+    /// forward. The length is in `[0; 1]` range, so a slightly tilted stick means slow walk.
+    pub fn movement(&self) -> Vector2<f32> {
+        let mut movement = Vector2::new(self.analog_left, self.analog_forward);
+        if self.move_forward {
+            movement.y += 1.0;
+        }
+        if self.move_backward {
+            movement.y -= 1.0;
+        }
+        if self.move_left {
+            movement.x += 1.0;
+        }
+        if self.move_right {
+            movement.x -= 1.0;
+        }
+        if movement.norm() > 1.0 {
+            movement.normalize()
+        } else {
+            movement
+        }
+    }
+
+    /// Applies a bound action. Returns `false` if the action does not control the player.
+    fn on_action(&mut self, action: ControlAction, state: bool, repeat: bool) -> bool {
+        match action {
+            ControlAction::MoveForward => self.move_forward = state,
+            ControlAction::MoveBackward => self.move_backward = state,
+            ControlAction::MoveLeft => self.move_left = state,
+            ControlAction::MoveRight => self.move_right = state,
+            ControlAction::Jump => {
+                if state && !self.jump && !repeat {
+                    self.jump_presses = self.jump_presses.wrapping_add(1);
+                }
+                self.jump = state;
+            }
+            ControlAction::Interact => self.interact = state,
+            ControlAction::Grab => self.grab = state,
+            ControlAction::Shove => self.shove = state,
+            ControlAction::Dive => {
+                if state && !self.dive && !repeat {
+                    self.dive_presses = self.dive_presses.wrapping_add(1);
+                }
+                self.dive = state;
+            }
+            ControlAction::Menu
+            | ControlAction::ShowPhysics
+            | ControlAction::ShowPaths
+            | ControlAction::DisableRagdoll
+            | ControlAction::ShortenMatch
+            | ControlAction::ShowImpacts => return false,
+        }
+        true
+    }
+
+    /// Rotates the camera by the mouse motion or by dragging a finger over the screen.
+    fn on_look(&mut self, delta: Vector2<f32>, pitch_range: &Range<f32>, dt: f32, mouse_sens: f32) {
+        self.target_yaw -= delta.x * mouse_sens * dt;
+        self.target_pitch = (self.target_pitch + delta.y * mouse_sens * dt)
+            .clamp(pitch_range.start.to_radians(), pitch_range.end.to_radians());
+    }
+
+    /// Applies the input of the player's gamepad in the current frame. The analog input is reset,
+    /// when the player has no gamepad.
+    pub fn on_gamepad(
+        &mut self,
+        pad: Option<&GamepadPad>,
+        pitch_range: &Range<f32>,
+        dt: f32,
+        sensitivity: f32,
+    ) {
+        let Some(pad) = pad else {
+            self.analog_left = 0.0;
+            self.analog_forward = 0.0;
+            return;
+        };
+
+        for event in pad.events() {
+            let state = event.pressed;
+            match event.action {
+                GamepadAction::Jump => {
+                    if state && !self.jump {
+                        self.jump_presses = self.jump_presses.wrapping_add(1);
+                    }
+                    self.jump = state;
+                }
+                GamepadAction::Interact => self.interact = state,
+                GamepadAction::Grab => self.grab = state,
+                GamepadAction::Shove => self.shove = state,
+                GamepadAction::Dive => {
+                    if state && !self.dive {
+                        self.dive_presses = self.dive_presses.wrapping_add(1);
+                    }
+                    self.dive = state;
+                }
+                GamepadAction::Menu => (),
+            }
+        }
+
+        let movement = pad.movement();
+        self.analog_left = -movement.x;
+        self.analog_forward = movement.y;
+
+        let camera = pad.camera();
+        self.target_yaw -= camera.x * sensitivity * dt;
+        self.target_pitch = (self.target_pitch - camera.y * sensitivity * dt)
+            .clamp(pitch_range.start.to_radians(), pitch_range.end.to_radians());
+    }
+
+    /// Applies the touch input of the current frame. It must be called after
+    /// [`Self::on_gamepad`], because the virtual joystick is added to the gamepad stick.
+    pub fn on_touch(
+        &mut self,
+        touch_controls: &mut TouchControls,
+        pitch_range: &Range<f32>,
+        dt: f32,
+        mouse_sens: f32,
+    ) {
+        for pressed in touch_controls.take_jump_changes() {
+            self.on_action(ControlAction::Jump, pressed, false);
+        }
+
+        let movement = touch_controls.movement();
+        self.analog_left -= movement.x;
+        self.analog_forward += movement.y;
+
+        self.on_look(
+            touch_controls.take_camera_delta(),
+            pitch_range,
+            dt,
+            mouse_sens,
+        );
+    }
+
     pub fn on_os_event(
         &mut self,
         event: &Event<()>,
@@ -49,28 +214,13 @@ impl InputController {
                 WindowEvent::KeyboardInput { event, .. } => {
                     if let PhysicalKey::Code(keycode) = event.physical_key {
                         let state = event.state == ElementState::Pressed;
-                        match keycode {
-                            KeyCode::KeyW => {
-                                self.move_forward = state;
-                                return true;
-                            }
-                            KeyCode::KeyS => {
-                                self.move_backward = state;
-                                return true;
-                            }
-                            KeyCode::KeyA => {
-                                self.move_left = state;
-                                return true;
-                            }
-                            KeyCode::KeyD => {
-                                self.move_right = state;
-                                return true;
-                            }
-                            KeyCode::Space => {
-                                self.jump = state;
-                                return true;
-                            }
-                            _ => (),
+                        let key = translate_key_to_ui(keycode);
+                        let mut handled = false;
+                        for action in game.settings.read().controls.actions(key) {
+                            handled |= self.on_action(action, state, event.repeat);
+                        }
+                        if handled {
+                            return true;
                         }
                     }
                 }
@@ -91,9 +241,12 @@ impl InputController {
             ..
         } = event
         {
-            self.target_yaw -= delta.0 as f32 * mouse_sens * dt;
-            self.target_pitch = (self.target_pitch + delta.1 as f32 * mouse_sens * dt)
-                .clamp(pitch_range.start.to_radians(), pitch_range.end.to_radians());
+            self.on_look(
+                Vector2::new(delta.0 as f32, delta.1 as f32),
+                pitch_range,
+                dt,
+                mouse_sens,
+            );
             return true;
         }
         false
@@ -122,6 +275,21 @@ pub struct Player {
     pitch: f32,
     #[reflect(hidden)]
     spectator_target: Handle<Node>,
+    #[reflect(hidden)]
+    #[visit(skip)]
+    prev_interact: bool,
+    #[reflect(hidden)]
+    #[visit(skip)]
+    prev_shove: bool,
+    #[reflect(hidden)]
+    #[visit(skip)]
+    prev_jump_presses: u32,
+    #[reflect(hidden)]
+    #[visit(skip)]
+    prev_dive_presses: u32,
+    /// Index of the player on this machine in split-screen mode.
+    #[reflect(hidden)]
+    pub local_player: usize,
 }
 
 impl Default for Player {
@@ -140,17 +308,30 @@ impl Default for Player {
             },
             pitch: 0.0,
             spectator_target: Default::default(),
+            prev_interact: false,
+            prev_shove: false,
+            prev_jump_presses: 0,
+            prev_dive_presses: 0,
+            local_player: 0,
         }
     }
 }
 
+impl Player {
+    pub fn model(&self) -> Handle<Node> {
+        self.model
+    }
+}
+
 impl ScriptTrait for Player {
     fn on_init(&mut self, ctx: &mut ScriptContext) -> GameResult {
-        ctx.plugins
-            .get_mut::<Game>()
-            .level
-            .actors
-            .insert(ctx.handle);
+        if !self.actor.spectator {
+            ctx.plugins
+                .get_mut::<Game>()
+                .level
+                .actors
+                .insert(ctx.handle);
+        }
 
         Log::info(format!(
             "Player {:?} created!",
@@ -169,18 +350,32 @@ impl ScriptTrait for Player {
         let camera_controller =
             graph.try_get_script_component_of_mut::<CameraController>(self.camera)?;
         let camera_handle = camera_controller.camera;
-        graph
-            .try_get_mut(camera_handle)?
-            .set_enabled(self.actor.kind == ActorKind::Player);
+        let camera = graph.try_get_mut(camera_handle)?;
+        camera.set_enabled(self.actor.kind == ActorKind::Player);
+        camera.set_viewport(split_screen_viewport(
+            self.local_player,
+            ctx.plugins.get::<Game>().level.local_players,
+        ));
+
+        if self.actor.spectator {
+            self.actor.make_spectator(graph)?;
+            graph.try_get_mut(self.model)?.set_visibility(false);
+            self.actor.set_state(
+                ActorState::Spectating,
+                ctx.handle,
+                &ctx.plugins.get::<Game>().level,
+            );
+        }
+        self.actor.apply_team_color(self.model, graph);
+        self.actor
+            .register_bodies(graph, &mut ctx.plugins.get_mut::<Game>().level)?;
         Ok(())
     }
 
     fn on_deinit(&mut self, ctx: &mut ScriptDeinitContext) -> GameResult {
-        ctx.plugins
-            .get_mut::<Game>()
-            .level
-            .actors
-            .remove(&ctx.node_handle);
+        let level = &mut ctx.plugins.get_mut::<Game>().level;
+        level.actors.remove(&ctx.node_handle);
+        self.actor.unregister_bodies(level);
         Log::info(format!(
             "Player {:?} destroyed!",
             ctx.scene.graph[ctx.node_handle].instance_id()
@@ -191,7 +386,9 @@ impl ScriptTrait for Player {
     fn on_os_event(&mut self, event: &Event<()>, ctx: &mut ScriptContext) -> GameResult {
         let game = ctx.plugins.get_mut::<Game>();
 
+        // Keyboard and mouse belong to the first local player, others use gamepads.
         if self.actor.kind == ActorKind::RemotePlayer
+            || self.local_player != 0
             || game
                 .menu
                 .as_ref()
@@ -224,15 +421,70 @@ impl ScriptTrait for Player {
     fn on_update(&mut self, ctx: &mut ScriptContext) -> GameResult {
         let game = ctx.plugins.get_mut::<Game>();
 
+        // Gamepads and touch controls are polled every frame, unlike the keyboard and mouse
+        // events.
+        if self.actor.kind == ActorKind::Player
+            && !game
+                .menu
+                .as_ref()
+                .is_some_and(|menu| menu.is_active(ctx.user_interfaces.first()))
+        {
+            let prev_input = self.input_controller.clone();
+            let settings = game.settings.read();
+            // A single player uses the first gamepad. In split-screen mode the first player uses
+            // the keyboard and mouse, and the rest of the players use the gamepads in the order
+            // of connection.
+            let pad = if game.level.local_players <= 1 {
+                game.gamepad.pad(0)
+            } else {
+                self.local_player
+                    .checked_sub(1)
+                    .and_then(|index| game.gamepad.pad(index))
+            };
+            self.input_controller.on_gamepad(
+                pad,
+                &self.pitch_range,
+                ctx.dt,
+                settings.gamepad_sensitivity,
+            );
+            if let Some(menu) = game.menu.as_mut().filter(|_| self.local_player == 0) {
+                self.input_controller.on_touch(
+                    &mut menu.touch_controls,
+                    &self.pitch_range,
+                    ctx.dt,
+                    settings.mouse_sensitivity,
+                );
+            }
+
+            if self.input_controller != prev_input
+                && !game.level.leaderboard.is_finished(ctx.handle)
+            {
+                if let Some(client) = game.client.as_mut() {
+                    client.send_message_to_server(ClientMessage::Input {
+                        player: ctx.scene.graph[ctx.handle].instance_id(),
+                        input_state: self.input_controller.clone(),
+                    })
+                }
+            }
+        }
+
         if game.is_client() {
             return Ok(());
         }
 
         let finished = game.level.leaderboard.is_finished(ctx.handle);
+        let spectating = finished || self.actor.spectator;
+        let movement_locked = game.level.is_movement_locked();
         let response_speed = (1.0 - game.settings.read().mouse_smoothness).clamp(0.1, 1.0);
         self.pitch += (self.input_controller.target_pitch - self.pitch) * response_speed;
         self.yaw += (self.input_controller.target_yaw - self.yaw) * response_speed;
 
+        if spectating && !game.level.actors.contains(&self.spectator_target) {
+            if let Some(actor) = game.level.actors.iter().next() {
+                self.spectator_target = *actor;
+            }
+        }
+
         let self_position = ctx.scene.graph[self.actor.rigid_body].global_position();
         let spectator_target_position = ctx
             .scene
@@ -253,89 +505,79 @@ impl ScriptTrait for Player {
             .try_get_script_component_of_mut::<CameraController>(self.camera)?;
         camera_controller.pitch = self.pitch;
         camera_controller.yaw = self.yaw;
-        if let (true, Some(spectator_target_position)) = (finished, spectator_target_position) {
+        if let (true, Some(spectator_target_position)) = (spectating, spectator_target_position) {
             // Spectate a player.
             camera_controller.target_position = spectator_target_position;
         } else {
             camera_controller.target_position = self_position;
         }
 
-        let has_ground_contact = self.actor.has_ground_contact(&ctx.scene.graph)?;
-        let is_in_jump_state = self.actor.is_in_jump_state(&ctx.scene.graph)?;
+        if self.actor.spectator {
+            return Ok(());
+        }
 
         self.actor.target_desired_velocity = Vector3::default();
 
-        let rigid_body = ctx.scene.graph.try_get_mut(self.actor.rigid_body)?;
-        if !finished {
+        let rigid_body = ctx.scene.graph.try_get(self.actor.rigid_body)?;
+        let movement = self.input_controller.movement();
+        if !finished && !movement_locked {
             let forward_vec = rigid_body.look_vector();
             let side_vec = rigid_body.side_vector();
 
-            if self.input_controller.move_forward {
-                self.actor.target_desired_velocity += forward_vec;
-            }
-            if self.input_controller.move_backward {
-                self.actor.target_desired_velocity -= forward_vec;
-            }
-            if self.input_controller.move_left {
-                self.actor.target_desired_velocity += side_vec;
-            }
-            if self.input_controller.move_right {
-                self.actor.target_desired_velocity -= side_vec;
-            }
+            self.actor.target_desired_velocity =
+                forward_vec.scale(movement.y) + side_vec.scale(movement.x);
         }
 
         self.actor.target_desired_velocity = self
             .actor
             .target_desired_velocity
             .try_normalize(f32::EPSILON)
-            .map(|v| v.scale(self.actor.speed))
+            .map(|v| v.scale(self.actor.current_speed() * movement.norm()))
             .unwrap_or_default();
 
-        if !finished
-            && self.input_controller.jump
-            && has_ground_contact
-            && !is_in_jump_state
-            && self.actor.jump_interval <= 0.0
-        {
-            self.actor.target_desired_velocity.y = self.actor.jump_vel;
-            self.input_controller.jump = false;
-            self.actor.jump();
+        if self.input_controller.jump_presses != self.prev_jump_presses {
+            self.prev_jump_presses = self.input_controller.jump_presses;
+            if !finished && !movement_locked {
+                self.actor.request_jump();
+            }
+        }
+
+        if !finished && !movement_locked && self.actor.try_jump(&mut ctx.scene.graph)? {
+            self.actor.target_desired_velocity.y = self.actor.current_jump_vel();
         } else {
             self.actor.target_desired_velocity.y = 0.0;
         }
 
-        let is_moving = self.input_controller.move_left
-            || self.input_controller.move_right
-            || self.input_controller.move_forward
-            || self.input_controller.move_backward;
+        // Interact only once per key press.
+        if !finished && !movement_locked && self.input_controller.interact && !self.prev_interact {
+            self.actor.interact();
+        }
+        self.prev_interact = self.input_controller.interact;
+
+        // Grab is held while the key is pressed, the grabbed actor is thrown on release.
+        self.actor.grab_requested = !finished && !movement_locked && self.input_controller.grab;
+        if !finished && !movement_locked && self.input_controller.shove && !self.prev_shove {
+            self.actor.request_shove();
+        }
+        self.prev_shove = self.input_controller.shove;
+
+        if self.input_controller.dive_presses != self.prev_dive_presses {
+            self.prev_dive_presses = self.input_controller.dive_presses;
+            if !finished && !movement_locked {
+                self.actor.request_dive();
+            }
+        }
+
+        let is_moving = movement.norm() > f32::EPSILON;
 
         if is_moving {
-            rigid_body.set_rotation_y(self.input_controller.target_yaw);
+            ctx.scene
+                .graph
+                .try_get_mut(self.actor.rigid_body)?
+                .set_rotation_y(self.input_controller.target_yaw);
 
             // Apply additional rotation to model - it will turn in front of walking direction.
-            let angle: f32 = if self.input_controller.move_left {
-                if self.input_controller.move_forward {
-                    45.0
-                } else if self.input_controller.move_backward {
-                    135.0
-                } else {
-                    90.0
-                }
-            } else if self.input_controller.move_right {
-                if self.input_controller.move_forward {
-                    -45.0
-                } else if self.input_controller.move_backward {
-                    -135.0
-                } else {
-                    -90.0
-                }
-            } else if self.input_controller.move_backward {
-                180.0
-            } else {
-                0.0
-            };
-
-            self.model_angle.set_target(angle.to_radians());
+            self.model_angle.set_target(movement.x.atan2(movement.y));
 
             ctx.scene.graph[self.model]
                 .set_rotation_y(180.0f32.to_radians() + self.model_angle.angle());
@@ -353,6 +595,15 @@ impl ScriptTrait for Player {
         message: &mut dyn ScriptMessagePayload,
         ctx: &mut ScriptMessageContext,
     ) -> GameResult {
-        self.actor.on_message(message, ctx)
+        self.actor.on_message(message, ctx)?;
+
+        if let Some(ActorMessage::Eliminate) = message.downcast_ref::<ActorMessage>() {
+            ctx.scene
+                .graph
+                .try_get_mut(self.model)?
+                .set_visibility(false);
+        }
+
+        Ok(())
     }
 }
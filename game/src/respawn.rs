@@ -5,7 +5,7 @@ use crate::{
     actor::{Actor, ActorMessage},
     Game,
 };
-use fyrox::plugin::error::GameResult;
+use fyrox::plugin::error::{GameError, GameResult};
 use fyrox::{
     core::{
         math::aabb::AxisAlignedBoundingBox, pool::Handle, reflect::prelude::*,
@@ -13,7 +13,7 @@ use fyrox::{
     },
     graph::SceneGraph,
     rand::{seq::SliceRandom, thread_rng},
-    scene::collider::Collider,
+    scene::{collider::Collider, graph::Graph},
     script::{ScriptContext, ScriptDeinitContext, ScriptTrait},
 };
 use strum_macros::{AsRefStr, EnumString, VariantNames};
@@ -46,6 +46,86 @@ pub struct Respawner {
     pub collider: InheritableVariable<Handle<Collider>>,
 }
 
+impl Respawner {
+    /// Checks whether the actor (or any of its ragdoll limbs) has touched the respawn zone.
+    pub fn is_touching(
+        &self,
+        self_bounds: &AxisAlignedBoundingBox,
+        actor: &Actor,
+        graph: &Graph,
+    ) -> Result<bool, GameError> {
+        match *self.mode {
+            RespawnMode::OnEnterBoundingBox => {
+                let mut ragdoll_inside = false;
+                graph
+                    .try_get(actor.ragdoll)?
+                    .root_limb
+                    .iterate_recursive(&mut |limb| {
+                        let rigid_body = graph.try_get(limb.physical_bone)?;
+                        if self_bounds.is_contains_point(rigid_body.global_position()) {
+                            ragdoll_inside = true;
+                        }
+                        Ok(())
+                    })?;
+                let rigid_body = graph.try_get(actor.rigid_body)?;
+                Ok(self_bounds.is_contains_point(rigid_body.global_position()) || ragdoll_inside)
+            }
+            RespawnMode::OnContact => {
+                let collider = graph.try_get(*self.collider)?;
+                Ok(collider.contacts(&graph.physics).any(|contact| {
+                    contact.has_any_active_contact
+                        && (contact.collider1 == actor.collider
+                            || contact.collider2 == actor.collider)
+                }))
+            }
+            RespawnMode::Disabled => Ok(false),
+        }
+    }
+
+    /// Respawns every actor that touches the zone, or eliminates it if the game mode does not
+    /// allow respawns.
+    pub fn respawn_touching_actors(
+        &self,
+        self_bounds: &AxisAlignedBoundingBox,
+        ctx: &mut ScriptContext,
+    ) -> GameResult {
+        let game = ctx.plugins.get_mut::<Game>();
+
+        let start_points = game
+            .level
+            .start_points
+            .iter()
+            .map(|p| ctx.scene.graph[*p].global_position())
+            .collect::<Vec<_>>();
+
+        let mut fallen_actors = Vec::new();
+        for actor_handle in game.level.actors.iter() {
+            let actor_script = ctx
+                .scene
+                .graph
+                .try_get_script_component_of::<Actor>(*actor_handle)?;
+
+            if self.is_touching(self_bounds, actor_script, &ctx.scene.graph)? {
+                fallen_actors.push(*actor_handle);
+            }
+        }
+
+        for actor_handle in fallen_actors {
+            if game.level.on_actor_fell(actor_handle) {
+                if let Some(start_point) = start_points.choose(&mut thread_rng()) {
+                    ctx.message_sender
+                        .send_to_target(actor_handle, ActorMessage::RespawnAt(*start_point));
+                }
+            } else {
+                ctx.message_sender
+                    .send_to_target(actor_handle, ActorMessage::Eliminate);
+            }
+        }
+
+        Ok(())
+    }
+}
+
 impl ScriptTrait for Respawner {
     fn on_start(&mut self, ctx: &mut ScriptContext) -> GameResult {
         ctx.plugins
@@ -66,7 +146,7 @@ impl ScriptTrait for Respawner {
     }
 
     fn on_update(&mut self, ctx: &mut ScriptContext) -> GameResult {
-        let game = ctx.plugins.get::<Game>();
+        let game = ctx.plugins.get_mut::<Game>();
         if game.is_client() {
             return Ok(());
         }
@@ -74,65 +154,6 @@ impl ScriptTrait for Respawner {
         let self_bounds = AxisAlignedBoundingBox::unit()
             .transform(&ctx.scene.graph[ctx.handle].global_transform());
 
-        let start_points = game
-            .level
-            .start_points
-            .iter()
-            .map(|p| ctx.scene.graph[*p].global_position())
-            .collect::<Vec<_>>();
-
-        for actor_handle in game.level.actors.iter() {
-            let actor_script = ctx
-                .scene
-                .graph
-                .try_get_script_component_of::<Actor>(*actor_handle)?;
-
-            match *self.mode {
-                RespawnMode::OnEnterBoundingBox => {
-                    let mut ragdoll_inside = false;
-                    ctx.scene
-                        .graph
-                        .try_get(actor_script.ragdoll)?
-                        .root_limb
-                        .iterate_recursive(&mut |limb| {
-                            let rigid_body = ctx.scene.graph.try_get(limb.physical_bone)?;
-                            if self_bounds.is_contains_point(rigid_body.global_position()) {
-                                ragdoll_inside = true;
-                            }
-                            Ok(())
-                        })?;
-                    let rigid_body = actor_script.rigid_body;
-                    let rigid_body = ctx.scene.graph.try_get(rigid_body)?;
-                    if self_bounds.is_contains_point(rigid_body.global_position()) || ragdoll_inside
-                    {
-                        if let Some(start_point) = start_points.choose(&mut thread_rng()) {
-                            ctx.message_sender.send_to_target(
-                                *actor_handle,
-                                ActorMessage::RespawnAt(*start_point),
-                            );
-                        }
-                    }
-                }
-                RespawnMode::OnContact => {
-                    let collider = ctx.scene.graph.try_get(*self.collider)?;
-                    for contact in collider.contacts(&ctx.scene.graph.physics) {
-                        if contact.has_any_active_contact
-                            && (contact.collider1 == actor_script.collider
-                                || contact.collider2 == actor_script.collider)
-                        {
-                            if let Some(start_point) = start_points.choose(&mut thread_rng()) {
-                                ctx.message_sender.send_to_target(
-                                    *actor_handle,
-                                    ActorMessage::RespawnAt(*start_point),
-                                );
-                            }
-                        }
-                    }
-                }
-                RespawnMode::Disabled => {}
-            }
-        }
-
-        Ok(())
+        self.respawn_touching_actors(&self_bounds, ctx)
     }
 }
@@ -1,18 +1,22 @@
 use crate::{
-    actor::ActorKind,
-    level::Level,
+    actor::{ActorKind, ActorStateEvent},
+    game_mode::GameMode,
+    level::{Level, MatchStage},
     net::{
-        ClientMessage, InstanceDescriptor, LeaderBoardMessage, NodeState, PlayerDescriptor,
-        ServerMessage, SoundState, UpdateTickMessage,
+        ClientMessage, InstanceDescriptor, LeaderBoardMessage, MatchStateMessage, NodeState,
+        PlayerDescriptor, ServerMessage, SoundState, UpdateTickMessage,
     },
     player::Player,
     start::StartPoint,
+    team::{self as teams, MAX_TEAMS},
+    tournament::{Tournament, TournamentEvent, TournamentRound},
 };
 use fyrox::graph::SceneGraphNode;
 use fyrox::plugin::error::GameResult;
 use fyrox::scene::graph::GraphError;
 use fyrox::{
     core::{
+        algebra::Vector3,
         futures::executor::block_on,
         log::Log,
         net::{NetListener, NetStream},
@@ -29,16 +33,39 @@ use fyrox::{
     },
 };
 use std::fmt::{Debug, Formatter};
+use std::mem::discriminant;
 use std::net::SocketAddr;
 use std::ops::Deref;
-use std::{io, net::ToSocketAddrs, path::Path};
+use std::{
+    collections::HashSet,
+    io,
+    net::ToSocketAddrs,
+    path::Path,
+    sync::mpsc::{self, Receiver, Sender},
+};
 
 pub struct Server {
     listener: NetListener,
     connections: Vec<NetStream>,
     previous_node_states: FxHashMap<Handle<Node>, NodeState>,
     previous_sound_states: FxHashMap<Handle<Node>, SoundState>,
+    /// Indices of connections that have loaded the current level.
+    loaded_connections: HashSet<usize>,
     pub add_bots: bool,
+    pub game_mode: GameMode,
+    pub tournament: Option<Tournament>,
+    /// Amount of teams, zero means that every actor plays alone.
+    teams: usize,
+    /// Teams, that were picked in the lobby.
+    connection_teams: FxHashMap<usize, usize>,
+    /// Amount of players, that share the host machine in split-screen mode.
+    local_players: usize,
+    actor_state_sender: Sender<ActorStateEvent>,
+    actor_state_receiver: Receiver<ActorStateEvent>,
+    /// The last stage of the match, that was sent to the clients.
+    sent_stage: Option<MatchStage>,
+    /// Time left until the next synchronization of the match clock.
+    match_state_sync_timer: f32,
 }
 
 impl Debug for Server {
@@ -47,19 +74,51 @@ impl Debug for Server {
     }
 }
 
+/// Picks a start point for the given team. Start points of the team have the priority, then
+/// the start points that do not belong to any team.
+fn take_start_point(
+    start_points: &mut Vec<(Vector3<f32>, Option<usize>)>,
+    team: Option<usize>,
+) -> Option<Vector3<f32>> {
+    let index = start_points
+        .iter()
+        .position(|(_, t)| team.is_some() && *t == team)
+        .or_else(|| start_points.iter().position(|(_, t)| t.is_none()))
+        .or_else(|| (!start_points.is_empty()).then_some(0))?;
+    Some(start_points.remove(index).0)
+}
+
 impl Server {
     pub const LOCALHOST: &'static str = "127.0.0.1:10001";
+    /// Clients advance the match clock on their own, the server corrects it from time to time.
+    pub const MATCH_STATE_SYNC_INTERVAL: f32 = 1.0;
 
     pub fn new<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let (actor_state_sender, actor_state_receiver) = mpsc::channel();
         Ok(Self {
             listener: NetListener::bind(addr)?,
             connections: Default::default(),
             previous_node_states: Default::default(),
             previous_sound_states: Default::default(),
+            loaded_connections: Default::default(),
             add_bots: true,
+            game_mode: Default::default(),
+            tournament: None,
+            teams: 0,
+            connection_teams: Default::default(),
+            local_players: 1,
+            actor_state_sender,
+            actor_state_receiver,
+            sent_stage: None,
+            match_state_sync_timer: 0.0,
         })
     }
 
+    /// Returns a sender, that should be subscribed to actor state changes on a level.
+    pub fn actor_state_sender(&self) -> Sender<ActorStateEvent> {
+        self.actor_state_sender.clone()
+    }
+
     pub fn broadcast_message_to_clients(&mut self, message: ServerMessage) {
         for client_connection in self.connections.iter_mut() {
             match client_connection.send_message(&message) {
@@ -69,24 +128,81 @@ impl Server {
         }
     }
 
+    pub fn start_tournament(&mut self, rounds: Vec<TournamentRound>) {
+        let tournament = Tournament::new(rounds);
+        if let Some(map) = tournament.current_map().map(|p| p.to_path_buf()) {
+            self.start_game(&map);
+            self.tournament = Some(tournament);
+        }
+    }
+
     pub fn start_game(&mut self, path: &Path) {
+        self.loaded_connections.clear();
+        self.sent_stage = None;
         self.broadcast_message_to_clients(ServerMessage::LoadLevel {
             path: path.to_path_buf(),
         });
     }
 
     pub fn update(&mut self, level: &mut Level, ctx: &mut PluginContext) -> GameResult {
+        if level.stage == MatchStage::WaitingForPlayers && self.is_everyone_loaded() {
+            level.start_countdown();
+        }
+
         level.update(ctx)?;
 
         if let Ok(scene) = ctx.scenes.try_get_mut(level.scene) {
-            if level.is_match_ended() {
+            if let Some(tournament) = self.tournament.as_mut() {
+                match tournament.update(level, &scene.graph, ctx.dt)? {
+                    Some(TournamentEvent::RoundEnded(standings)) => {
+                        level.stage = MatchStage::Finished;
+                        self.broadcast_message_to_clients(ServerMessage::TournamentStandings(
+                            standings,
+                        ));
+                    }
+                    Some(TournamentEvent::NextRound(map)) => self.start_game(&map),
+                    Some(TournamentEvent::Finished) => {
+                        self.tournament = None;
+                        self.broadcast_message_to_clients(ServerMessage::EndMatch);
+                    }
+                    None => (),
+                }
+            } else if level.is_match_ended() {
                 self.broadcast_message_to_clients(ServerMessage::EndMatch);
             }
 
+            self.match_state_sync_timer -= ctx.dt;
+            let is_stage_changed = self.sent_stage.map_or(true, |stage| {
+                discriminant(&stage) != discriminant(&level.stage)
+            });
+            if is_stage_changed || self.match_state_sync_timer <= 0.0 {
+                self.sent_stage = Some(level.stage);
+                self.match_state_sync_timer = Self::MATCH_STATE_SYNC_INTERVAL;
+                self.broadcast_message_to_clients(ServerMessage::MatchState(MatchStateMessage {
+                    stage: level.stage,
+                    match_timer: level.match_timer,
+                    match_time: level.match_time,
+                    game_mode: level.game_mode,
+                }));
+            }
+
             self.broadcast_message_to_clients(ServerMessage::LeaderBoard(LeaderBoardMessage {
                 players: level.leaderboard.entries.values().cloned().collect(),
             }));
 
+            while let Ok(event) = self.actor_state_receiver.try_recv() {
+                if !event.is_replicated() {
+                    continue;
+                }
+                if let Ok(actor) = scene.graph.try_get(event.actor) {
+                    let actor = actor.instance_id();
+                    self.broadcast_message_to_clients(ServerMessage::ActorState {
+                        actor,
+                        state: event.to,
+                    });
+                }
+            }
+
             let mut tick_data = UpdateTickMessage {
                 nodes: Default::default(),
                 sounds: Default::default(),
@@ -135,7 +251,7 @@ impl Server {
     }
 
     pub fn read_messages(&mut self, scene: Handle<Scene>, ctx: &mut PluginContext) -> GameResult {
-        for player in self.connections.iter_mut() {
+        for (connection_index, player) in self.connections.iter_mut().enumerate() {
             while let Some(msg) = player.pop_message::<ClientMessage>() {
                 match msg {
                     ClientMessage::Input {
@@ -152,6 +268,9 @@ impl Server {
                             })?
                             .input_controller = input_state;
                     }
+                    ClientMessage::LevelLoaded => {
+                        self.loaded_connections.insert(connection_index);
+                    }
                 }
             }
         }
@@ -160,13 +279,28 @@ impl Server {
 
     pub fn on_scene_loaded(&mut self, scene: Handle<Scene>, ctx: &mut PluginContext) {
         let scene = &mut ctx.scenes[scene];
-        let players_to_spawn = self.connections.len();
+
+        // The host connection spawns a player for every local player, other connections spawn
+        // one player each.
+        let slots = (0..self.connections.len())
+            .flat_map(|connection| {
+                let local_players = if connection == 0 {
+                    self.local_players
+                } else {
+                    1
+                };
+                (0..local_players).map(move |local_player| (connection, local_player))
+            })
+            .collect::<Vec<_>>();
+        let players_to_spawn = slots.len();
 
         let start_points = scene
             .graph
             .linear_iter()
-            .filter(|n| n.has_script::<StartPoint>())
-            .map(|n| n.global_position())
+            .filter_map(|n| {
+                n.try_get_script::<StartPoint>()
+                    .map(|start_point| (n.global_position(), *start_point.team))
+            })
             .collect::<Vec<_>>();
 
         let player_prefab = block_on(
@@ -175,25 +309,74 @@ impl Server {
         )
         .unwrap();
 
-        for player_num in 0..players_to_spawn {
+        if let Some(tournament) = self.tournament.as_mut() {
+            let bots_count = if self.add_bots {
+                start_points.len().saturating_sub(players_to_spawn)
+            } else {
+                0
+            };
+            tournament.register_participants(players_to_spawn, bots_count);
+        }
+
+        let mut free_start_points = start_points.clone();
+        let mut team_members = vec![0; self.teams];
+
+        for (player_num, &(player_connection, local_player)) in slots.iter().enumerate() {
             let ids = player_prefab.generate_ids();
 
-            if let Some(position) = start_points.get(player_num) {
+            let mut spectator = self
+                .tournament
+                .as_ref()
+                .is_some_and(|t| t.is_eliminated(player_num));
+
+            // Local players of the same connection are spread across the teams.
+            let team = self
+                .team_of_connection(player_connection)
+                .map(|team| (team + local_player) % self.teams);
+
+            let mut position = None;
+            if !spectator {
+                position = take_start_point(&mut free_start_points, team);
+                if position.is_none() {
+                    Log::warn(format!(
+                        "Not enough start points for player {player_num}, it will be a spectator."
+                    ));
+                    if let Some(tournament) = self.tournament.as_mut() {
+                        tournament.eliminate(player_num);
+                    }
+                    spectator = true;
+                }
+            }
+            // Spectators do not occupy start points.
+            if spectator {
+                position = start_points.first().map(|(position, _)| *position);
+            }
+
+            if let Some(team) = team.filter(|_| !spectator) {
+                team_members[team] += 1;
+            }
+
+            if let Some(position) = position {
                 for (connection_num, connection) in self.connections.iter_mut().enumerate() {
                     connection
                         .send_message(&ServerMessage::AddPlayers(vec![PlayerDescriptor {
                             instance: InstanceDescriptor {
                                 path: "data/models/player.rgs".into(),
-                                position: *position,
+                                position,
                                 rotation: Default::default(),
                                 velocity: Default::default(),
                                 ids: ids.clone(),
+                                ..Default::default()
                             },
-                            kind: if player_num != connection_num {
+                            kind: if player_connection != connection_num {
                                 ActorKind::RemotePlayer
                             } else {
                                 ActorKind::Player
                             },
+                            participant: player_num,
+                            spectator,
+                            team,
+                            local_player,
                         }]))
                         .unwrap();
                 }
@@ -204,29 +387,93 @@ impl Server {
             let bot_prefab =
                 block_on(ctx.resource_manager.request::<Model>("data/models/bot.rgs")).unwrap();
 
-            for i in players_to_spawn..start_points.len() {
+            let bots = match self.tournament.as_ref() {
+                Some(tournament) => tournament.active_bots().collect::<Vec<_>>(),
+                None => (players_to_spawn..start_points.len()).collect(),
+            };
+
+            for participant in bots {
                 let ids = bot_prefab.generate_ids();
 
-                if let Some(position) = start_points.get(i) {
-                    for connection in self.connections.iter_mut() {
-                        connection
-                            .send_message(&ServerMessage::AddPlayers(vec![PlayerDescriptor {
-                                instance: InstanceDescriptor {
-                                    path: "data/models/bot.rgs".into(),
-                                    position: *position,
-                                    rotation: Default::default(),
-                                    velocity: Default::default(),
-                                    ids: ids.clone(),
-                                },
-                                kind: ActorKind::Bot,
-                            }]))
-                            .unwrap();
+                // Spread bots evenly across the teams.
+                let team = teams::smallest_team(&team_members);
+
+                let Some(position) = take_start_point(&mut free_start_points, team) else {
+                    // The map of a later tournament round could have less start points than the
+                    // first one, the bots that do not fit are out of the tournament.
+                    Log::warn(format!(
+                        "Not enough start points for bot {participant}, it is eliminated."
+                    ));
+                    if let Some(tournament) = self.tournament.as_mut() {
+                        tournament.eliminate(participant);
                     }
+                    continue;
+                };
+                if let Some(team) = team {
+                    team_members[team] += 1;
+                }
+
+                for connection in self.connections.iter_mut() {
+                    connection
+                        .send_message(&ServerMessage::AddPlayers(vec![PlayerDescriptor {
+                            instance: InstanceDescriptor {
+                                path: "data/models/bot.rgs".into(),
+                                position,
+                                rotation: Default::default(),
+                                velocity: Default::default(),
+                                ids: ids.clone(),
+                                ..Default::default()
+                            },
+                            kind: ActorKind::Bot,
+                            participant,
+                            spectator: false,
+                            team,
+                            local_player: 0,
+                        }]))
+                        .unwrap();
                 }
             }
         }
     }
 
+    pub fn team_of_connection(&self, connection: usize) -> Option<usize> {
+        if self.teams < 2 {
+            return None;
+        }
+        Some(
+            self.connection_teams
+                .get(&connection)
+                .copied()
+                .unwrap_or(connection % self.teams),
+        )
+    }
+
+    /// Moves a player to the next team.
+    pub fn switch_team(&mut self, connection: usize) {
+        if let Some(team) = self.team_of_connection(connection) {
+            self.connection_teams
+                .insert(connection, (team + 1) % self.teams);
+        }
+    }
+
+    pub fn set_teams_count(&mut self, teams: usize) {
+        self.teams = if teams < 2 { 0 } else { teams.min(MAX_TEAMS) };
+        self.connection_teams.clear();
+    }
+
+    pub fn teams_count(&self) -> usize {
+        self.teams
+    }
+
+    /// Sets the amount of players on the host machine, up to four players can share the screen.
+    pub fn set_local_players(&mut self, local_players: usize) {
+        self.local_players = local_players.clamp(1, 4);
+    }
+
+    pub fn local_players(&self) -> usize {
+        self.local_players
+    }
+
     pub fn address(&self) -> SocketAddr {
         self.listener.local_address().unwrap()
     }
@@ -235,6 +482,10 @@ impl Server {
         &self.connections
     }
 
+    pub fn is_everyone_loaded(&self) -> bool {
+        !self.connections.is_empty() && self.loaded_connections.len() >= self.connections.len()
+    }
+
     pub fn is_single_player(&self) -> bool {
         self.connections.len() == 1
     }
@@ -1,3 +1,4 @@
+use crate::controls::Controls;
 use fyrox::{
     core::log::Log, core::visitor::prelude::*, engine::GraphicsContext, renderer::QualitySettings,
     scene::Scene,
@@ -60,6 +61,7 @@ impl<'a> Drop for SettingsDataRefMut<'a> {
 }
 
 #[derive(Debug, Serialize, Deserialize, Visit)]
+#[serde(default)]
 pub struct SettingsData {
     pub graphics_quality: usize,
     pub sound_volume: f32,
@@ -68,6 +70,14 @@ pub struct SettingsData {
     pub graphics_presets: Vec<(String, QualitySettings)>,
     pub mouse_sensitivity: f32,
     pub mouse_smoothness: f32,
+    /// Stick values below this threshold are ignored.
+    #[visit(optional)]
+    pub gamepad_deadzone: f32,
+    /// Camera rotation speed (in radians per second) with the right stick fully tilted.
+    #[visit(optional)]
+    pub gamepad_sensitivity: f32,
+    #[visit(skip)]
+    pub controls: Controls,
 }
 
 fn adjust_settings(mut settings: QualitySettings) -> QualitySettings {
@@ -96,6 +106,9 @@ impl Default for SettingsData {
             ],
             mouse_sensitivity: 0.5,
             mouse_smoothness: 0.75,
+            gamepad_deadzone: 0.15,
+            gamepad_sensitivity: 3.0,
+            controls: Default::default(),
         }
     }
 }
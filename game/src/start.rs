@@ -3,14 +3,21 @@
 use crate::Game;
 use fyrox::plugin::error::GameResult;
 use fyrox::{
-    core::{log::Log, reflect::prelude::*, type_traits::prelude::*, visitor::prelude::*},
+    core::{
+        log::Log, reflect::prelude::*, type_traits::prelude::*, variable::InheritableVariable,
+        visitor::prelude::*,
+    },
     script::{ScriptContext, ScriptDeinitContext, ScriptTrait},
 };
 
 #[derive(Clone, Default, Debug, Visit, Reflect, TypeUuidProvider, ComponentProvider)]
 #[type_uuid(id = "103ac5c1-f4e4-45d2-a9f1-0da98d74d64c")]
 #[visit(optional)]
-pub struct StartPoint {}
+pub struct StartPoint {
+    /// A team, that will be spawned at this point. `None` means that any actor can be spawned
+    /// here.
+    pub team: InheritableVariable<Option<usize>>,
+}
 
 impl ScriptTrait for StartPoint {
     fn on_init(&mut self, ctx: &mut ScriptContext) -> GameResult {
@@ -0,0 +1,188 @@
+//! Surface zones change the way actors (players or bots) move on them: slippery ice, slow mud,
+//! conveyors and bouncy trampolines. The script should be assigned to a collider or to its parent
+//! rigid body.
+
+use fyrox::{
+    core::{
+        algebra::{UnitQuaternion, Vector3},
+        reflect::prelude::*,
+        type_traits::prelude::*,
+        variable::InheritableVariable,
+        visitor::prelude::*,
+    },
+    script::ScriptTrait,
+};
+use strum_macros::{AsRefStr, EnumString, VariantNames};
+
+#[derive(
+    Default,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Visit,
+    Reflect,
+    TypeUuidProvider,
+    AsRefStr,
+    EnumString,
+    VariantNames,
+)]
+#[type_uuid(id = "5c1e8a3f-6d29-4b7e-a04f-2e9b7d3c1a65")]
+pub enum SurfaceKind {
+    /// Low friction, actors keep their momentum and need some time to change direction.
+    #[default]
+    Ice,
+    /// Slows actors down.
+    Mud,
+    /// Moves actors in the given direction.
+    Conveyor,
+    /// Bounces actors back up when they fall on it.
+    Bounce,
+}
+
+/// Movement properties of a surface under an actor.
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+pub enum Surface {
+    Ice {
+        grip: f32,
+    },
+    Mud {
+        speed_factor: f32,
+    },
+    Conveyor {
+        /// Velocity in world coordinates.
+        velocity: Vector3<f32>,
+    },
+    Bounce {
+        restitution: f32,
+        min_speed: f32,
+    },
+}
+
+#[derive(Clone, Debug, Visit, Reflect, TypeUuidProvider, ComponentProvider)]
+#[type_uuid(id = "b7d40e2a-93f1-4c58-8e6a-0f5c2d9b4e17")]
+#[visit(optional)]
+pub struct SurfaceZone {
+    kind: InheritableVariable<SurfaceKind>,
+    /// How fast (0..1) actors change their velocity on ice. Lower values make the ice slippier.
+    grip: InheritableVariable<f32>,
+    /// Movement speed multiplier in mud.
+    speed_factor: InheritableVariable<f32>,
+    /// Velocity of a conveyor in local coordinates of the zone.
+    conveyor_velocity: InheritableVariable<Vector3<f32>>,
+    /// Part of the falling speed, that is kept after a bounce.
+    restitution: InheritableVariable<f32>,
+    /// Actors that fall slower than this will not bounce.
+    min_bounce_speed: InheritableVariable<f32>,
+}
+
+impl Default for SurfaceZone {
+    fn default() -> Self {
+        Self {
+            kind: Default::default(),
+            grip: 0.02.into(),
+            speed_factor: 0.5.into(),
+            conveyor_velocity: Vector3::new(0.0, 0.0, 2.0).into(),
+            restitution: 0.8.into(),
+            min_bounce_speed: 4.0.into(),
+        }
+    }
+}
+
+impl SurfaceZone {
+    /// Returns movement properties of the zone. Rotation is used to transform the conveyor
+    /// velocity to world coordinates.
+    pub fn surface(&self, rotation: UnitQuaternion<f32>) -> Surface {
+        match *self.kind {
+            SurfaceKind::Ice => Surface::Ice {
+                grip: self.grip.clamp(0.001, 1.0),
+            },
+            SurfaceKind::Mud => Surface::Mud {
+                speed_factor: self.speed_factor.max(0.0),
+            },
+            SurfaceKind::Conveyor => Surface::Conveyor {
+                velocity: rotation.transform_vector(&self.conveyor_velocity),
+            },
+            SurfaceKind::Bounce => Surface::Bounce {
+                restitution: self.restitution.max(0.0),
+                min_speed: self.min_bounce_speed.max(0.0),
+            },
+        }
+    }
+}
+
+impl ScriptTrait for SurfaceZone {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn zone(kind: SurfaceKind) -> SurfaceZone {
+        SurfaceZone {
+            kind: kind.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn conveyor_velocity_follows_zone_rotation() {
+        let zone = zone(SurfaceKind::Conveyor);
+        assert_eq!(
+            zone.surface(UnitQuaternion::identity()),
+            Surface::Conveyor {
+                velocity: Vector3::new(0.0, 0.0, 2.0)
+            }
+        );
+
+        let rotation = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), 90.0f32.to_radians());
+        let Surface::Conveyor { velocity } = zone.surface(rotation) else {
+            panic!("conveyor expected");
+        };
+        assert!(velocity.metric_distance(&Vector3::new(2.0, 0.0, 0.0)) < 1.0e-5);
+    }
+
+    #[test]
+    fn surface_properties_are_clamped() {
+        let zone = SurfaceZone {
+            kind: SurfaceKind::Ice.into(),
+            grip: 0.0.into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            zone.surface(UnitQuaternion::identity()),
+            Surface::Ice { grip: 0.001 }
+        );
+
+        let zone = SurfaceZone {
+            kind: SurfaceKind::Mud.into(),
+            speed_factor: (-1.0).into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            zone.surface(UnitQuaternion::identity()),
+            Surface::Mud { speed_factor: 0.0 }
+        );
+
+        let zone = SurfaceZone {
+            kind: SurfaceKind::Bounce.into(),
+            restitution: (-0.5).into(),
+            min_bounce_speed: 3.0.into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            zone.surface(UnitQuaternion::identity()),
+            Surface::Bounce {
+                restitution: 0.0,
+                min_speed: 3.0
+            }
+        );
+    }
+
+    #[test]
+    fn default_zone_is_ice() {
+        assert_eq!(
+            zone(SurfaceKind::default()).surface(UnitQuaternion::identity()),
+            Surface::Ice { grip: 0.02 }
+        );
+    }
+}
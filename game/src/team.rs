@@ -0,0 +1,52 @@
+//! Teams of actors. Team is just an index, that is used to pick a color, a name and start points
+//! for actors.
+
+use fyrox::core::color::Color;
+
+pub const MAX_TEAMS: usize = 4;
+
+const TEAM_COLORS: [Color; MAX_TEAMS] = [
+    Color::opaque(220, 60, 60),
+    Color::opaque(60, 110, 230),
+    Color::opaque(70, 190, 80),
+    Color::opaque(240, 200, 50),
+];
+
+const TEAM_NAMES: [&str; MAX_TEAMS] = ["Red", "Blue", "Green", "Yellow"];
+
+pub fn team_color(team: usize) -> Color {
+    TEAM_COLORS[team % MAX_TEAMS]
+}
+
+pub fn team_name(team: usize) -> &'static str {
+    TEAM_NAMES[team % MAX_TEAMS]
+}
+
+/// Returns a team with the least amount of members. `members` must contain a number of members
+/// for every team.
+pub fn smallest_team(members: &[usize]) -> Option<usize> {
+    members
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, count)| **count)
+        .map(|(team, _)| team)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn smallest_team_prefers_first_of_equal_teams() {
+        assert_eq!(smallest_team(&[]), None);
+        assert_eq!(smallest_team(&[0, 0, 0]), Some(0));
+        assert_eq!(smallest_team(&[2, 1, 1]), Some(1));
+        assert_eq!(smallest_team(&[3, 2, 4, 0]), Some(3));
+    }
+
+    #[test]
+    fn team_index_wraps_around() {
+        assert_eq!(team_name(MAX_TEAMS + 1), team_name(1));
+        assert_eq!(team_color(MAX_TEAMS), team_color(0));
+    }
+}
@@ -0,0 +1,334 @@
+//! On-screen controls for touch screens.
+
+use fyrox::{
+    asset::manager::ResourceManager,
+    core::{algebra::Vector2, color::Color, math::Rect, pool::Handle},
+    event::{Event, Touch, TouchPhase, WindowEvent},
+    fxhash::FxHashMap,
+    gui::{
+        border::BorderBuilder,
+        brush::Brush,
+        canvas::CanvasBuilder,
+        font::Font,
+        text::TextBuilder,
+        widget::{WidgetBuilder, WidgetMessage},
+        HorizontalAlignment, Thickness, UiNode, UserInterface, VerticalAlignment,
+    },
+};
+
+const JOYSTICK_SIZE: f32 = 220.0;
+const KNOB_SIZE: f32 = 90.0;
+const JUMP_BUTTON_SIZE: f32 = 150.0;
+const MENU_BUTTON_SIZE: f32 = 90.0;
+const MARGIN: f32 = 40.0;
+
+#[derive(Debug, Clone, Copy)]
+enum TouchRole {
+    Joystick,
+    Jump,
+    Camera { last_position: Vector2<f32> },
+}
+
+/// A virtual joystick, a jump button and camera dragging on the rest of the screen, and a menu
+/// button, that stays on the screen while the menu is opened. The widgets are shown only after the
+/// first touch, so they do not get in the way of keyboard players. Touches are matched against
+/// the layout of the widgets computed from the screen size, so the first touch works before the
+/// widgets are shown.
+#[derive(Debug, Default, Clone)]
+pub struct TouchControls {
+    root: Handle<UiNode>,
+    knob: Handle<UiNode>,
+    menu_button: Handle<UiNode>,
+    is_touch_detected: bool,
+    /// A level is loaded.
+    is_on_level: bool,
+    is_menu_active: bool,
+    is_visible: bool,
+    is_menu_button_visible: bool,
+    touches: FxHashMap<u64, TouchRole>,
+    movement: Vector2<f32>,
+    camera_delta: Vector2<f32>,
+    jump_changes: Vec<bool>,
+    is_menu_requested: bool,
+}
+
+impl TouchControls {
+    pub fn new(ui: &mut UserInterface, resource_manager: &ResourceManager) -> Self {
+        let ctx = &mut ui.build_ctx();
+        let knob = BorderBuilder::new(
+            WidgetBuilder::new()
+                .with_width(KNOB_SIZE)
+                .with_height(KNOB_SIZE)
+                .with_desired_position(Self::knob_position(Vector2::default()))
+                .with_background(Brush::Solid(Color::from_rgba(255, 255, 255, 140)).into()),
+        )
+        .build(ctx);
+        let joystick = BorderBuilder::new(
+            WidgetBuilder::new()
+                .with_width(JOYSTICK_SIZE)
+                .with_height(JOYSTICK_SIZE)
+                .with_margin(Thickness::uniform(MARGIN))
+                .with_horizontal_alignment(HorizontalAlignment::Left)
+                .with_vertical_alignment(VerticalAlignment::Bottom)
+                .with_background(Brush::Solid(Color::from_rgba(255, 255, 255, 40)).into())
+                .with_child(CanvasBuilder::new(WidgetBuilder::new().with_child(knob)).build(ctx)),
+        )
+        .build(ctx);
+        let jump_button = BorderBuilder::new(
+            WidgetBuilder::new()
+                .with_width(JUMP_BUTTON_SIZE)
+                .with_height(JUMP_BUTTON_SIZE)
+                .with_margin(Thickness::uniform(MARGIN))
+                .with_horizontal_alignment(HorizontalAlignment::Right)
+                .with_vertical_alignment(VerticalAlignment::Bottom)
+                .with_background(Brush::Solid(Color::from_rgba(255, 255, 255, 60)).into())
+                .with_child(
+                    TextBuilder::new(WidgetBuilder::new())
+                        .with_horizontal_text_alignment(HorizontalAlignment::Center)
+                        .with_vertical_text_alignment(VerticalAlignment::Center)
+                        .with_text("Jump")
+                        .with_font(resource_manager.request::<Font>("data/font.ttf"))
+                        .with_font_size(32.0.into())
+                        .build(ctx),
+                ),
+        )
+        .build(ctx);
+        let menu_button = BorderBuilder::new(
+            WidgetBuilder::new()
+                .with_visibility(false)
+                .with_hit_test_visibility(false)
+                .with_width(MENU_BUTTON_SIZE)
+                .with_height(MENU_BUTTON_SIZE)
+                .with_margin(Thickness::uniform(MARGIN))
+                .with_horizontal_alignment(HorizontalAlignment::Right)
+                .with_vertical_alignment(VerticalAlignment::Top)
+                .with_background(Brush::Solid(Color::from_rgba(255, 255, 255, 60)).into())
+                .with_child(
+                    TextBuilder::new(WidgetBuilder::new())
+                        .with_horizontal_text_alignment(HorizontalAlignment::Center)
+                        .with_vertical_text_alignment(VerticalAlignment::Center)
+                        .with_text("Menu")
+                        .with_font(resource_manager.request::<Font>("data/font.ttf"))
+                        .with_font_size(24.0.into())
+                        .build(ctx),
+                ),
+        )
+        .build(ctx);
+        let root = BorderBuilder::new(
+            WidgetBuilder::new()
+                .with_visibility(false)
+                .with_hit_test_visibility(false)
+                .with_background(Brush::Solid(Color::TRANSPARENT).into())
+                .with_child(joystick)
+                .with_child(jump_button),
+        )
+        .build(ctx);
+
+        Self {
+            root,
+            knob,
+            menu_button,
+            ..Default::default()
+        }
+    }
+
+    fn joystick_bounds(screen_size: Vector2<f32>) -> Rect<f32> {
+        Rect::new(
+            MARGIN,
+            screen_size.y - MARGIN - JOYSTICK_SIZE,
+            JOYSTICK_SIZE,
+            JOYSTICK_SIZE,
+        )
+    }
+
+    fn jump_button_bounds(screen_size: Vector2<f32>) -> Rect<f32> {
+        Rect::new(
+            screen_size.x - MARGIN - JUMP_BUTTON_SIZE,
+            screen_size.y - MARGIN - JUMP_BUTTON_SIZE,
+            JUMP_BUTTON_SIZE,
+            JUMP_BUTTON_SIZE,
+        )
+    }
+
+    fn menu_button_bounds(screen_size: Vector2<f32>) -> Rect<f32> {
+        Rect::new(
+            screen_size.x - MARGIN - MENU_BUTTON_SIZE,
+            MARGIN,
+            MENU_BUTTON_SIZE,
+            MENU_BUTTON_SIZE,
+        )
+    }
+
+    fn knob_position(movement: Vector2<f32>) -> Vector2<f32> {
+        let center = Vector2::repeat((JOYSTICK_SIZE - KNOB_SIZE) * 0.5);
+        // Screen Y axis goes down.
+        center + Vector2::new(movement.x, -movement.y).scale(JOYSTICK_SIZE * 0.5)
+    }
+
+    fn joystick_movement(screen_size: Vector2<f32>, position: Vector2<f32>) -> Vector2<f32> {
+        let center = Self::joystick_bounds(screen_size).center();
+        let offset = (position - center).scale(2.0 / JOYSTICK_SIZE);
+        let movement = Vector2::new(offset.x, -offset.y);
+        if movement.norm() > 1.0 {
+            movement.normalize()
+        } else {
+            movement
+        }
+    }
+
+    fn set_movement(&mut self, ui: &UserInterface, movement: Vector2<f32>) {
+        self.movement = movement;
+        ui.send(
+            self.knob,
+            WidgetMessage::DesiredPosition(Self::knob_position(movement)),
+        );
+    }
+
+    pub fn on_os_event(&mut self, event: &Event<()>, ui: &UserInterface) {
+        let Event::WindowEvent {
+            event:
+                WindowEvent::Touch(Touch {
+                    phase,
+                    location,
+                    id,
+                    ..
+                }),
+            ..
+        } = event
+        else {
+            return;
+        };
+
+        // The controls will be shown at the next update, but the touch is handled right away.
+        self.is_touch_detected = true;
+
+        let position = Vector2::new(location.x as f32, location.y as f32);
+        let screen_size = ui.screen_size();
+        if *phase == TouchPhase::Started
+            && self.is_on_level
+            && Self::menu_button_bounds(screen_size).contains(position)
+        {
+            self.is_menu_requested = true;
+            return;
+        }
+
+        if !self.is_on_level || self.is_menu_active {
+            // Do not leave the player running or jumping, when the menu is opened.
+            if self.touches.values().any(|r| matches!(r, TouchRole::Jump)) {
+                self.jump_changes.push(false);
+            }
+            self.touches.clear();
+            self.movement = Vector2::default();
+            return;
+        }
+
+        match phase {
+            TouchPhase::Started => {
+                let role = if Self::joystick_bounds(screen_size).contains(position) {
+                    self.set_movement(ui, Self::joystick_movement(screen_size, position));
+                    TouchRole::Joystick
+                } else if Self::jump_button_bounds(screen_size).contains(position) {
+                    self.jump_changes.push(true);
+                    TouchRole::Jump
+                } else {
+                    TouchRole::Camera {
+                        last_position: position,
+                    }
+                };
+                self.touches.insert(*id, role);
+            }
+            TouchPhase::Moved => match self.touches.get_mut(id) {
+                Some(TouchRole::Joystick) => {
+                    self.set_movement(ui, Self::joystick_movement(screen_size, position));
+                }
+                Some(TouchRole::Camera { last_position }) => {
+                    self.camera_delta += position - *last_position;
+                    *last_position = position;
+                }
+                Some(TouchRole::Jump) | None => (),
+            },
+            TouchPhase::Ended | TouchPhase::Cancelled => match self.touches.remove(id) {
+                Some(TouchRole::Joystick) => self.set_movement(ui, Vector2::default()),
+                Some(TouchRole::Jump) => self.jump_changes.push(false),
+                Some(TouchRole::Camera { .. }) | None => (),
+            },
+        }
+    }
+
+    /// Shows the controls, when touch input is detected and the player is on a level. The menu
+    /// button is shown with the menu too, so it can close the menu.
+    pub fn update(&mut self, ui: &UserInterface, is_on_level: bool, is_menu_active: bool) {
+        self.is_on_level = is_on_level;
+        self.is_menu_active = is_menu_active;
+
+        let is_visible = self.is_touch_detected && is_on_level && !is_menu_active;
+        if self.is_visible != is_visible {
+            self.is_visible = is_visible;
+            ui.send(self.root, WidgetMessage::Visibility(is_visible));
+        }
+        let is_menu_button_visible = self.is_touch_detected && is_on_level;
+        if self.is_menu_button_visible != is_menu_button_visible {
+            self.is_menu_button_visible = is_menu_button_visible;
+            ui.send(
+                self.menu_button,
+                WidgetMessage::Visibility(is_menu_button_visible),
+            );
+        }
+    }
+
+    /// Checks whether the menu button was pressed since the last call.
+    pub fn take_menu_request(&mut self) -> bool {
+        std::mem::take(&mut self.is_menu_requested)
+    }
+
+    /// Virtual joystick, `x` goes right and `y` goes forward.
+    pub fn movement(&self) -> Vector2<f32> {
+        self.movement
+    }
+
+    /// Returns the distance (in pixels), that the camera was dragged since the last call.
+    pub fn take_camera_delta(&mut self) -> Vector2<f32> {
+        std::mem::take(&mut self.camera_delta)
+    }
+
+    /// Returns the presses (`true`) and the releases (`false`) of the jump button since the last
+    /// call.
+    pub fn take_jump_changes(&mut self) -> Vec<bool> {
+        std::mem::take(&mut self.jump_changes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn screen_size() -> Vector2<f32> {
+        Vector2::new(1280.0, 720.0)
+    }
+
+    #[test]
+    fn buttons_are_in_the_corners() {
+        let screen_size = screen_size();
+        let joystick = TouchControls::joystick_bounds(screen_size);
+        assert!(joystick.contains(Vector2::new(MARGIN + 1.0, screen_size.y - MARGIN - 1.0)));
+        let jump_button = TouchControls::jump_button_bounds(screen_size);
+        assert!(jump_button.contains(screen_size - Vector2::repeat(MARGIN + 1.0)));
+        let menu_button = TouchControls::menu_button_bounds(screen_size);
+        assert!(menu_button.contains(Vector2::new(screen_size.x - MARGIN - 1.0, MARGIN + 1.0)));
+        assert!(!menu_button.contains(screen_size.scale(0.5)));
+    }
+
+    #[test]
+    fn joystick_movement_is_clamped() {
+        let screen_size = screen_size();
+        let center = TouchControls::joystick_bounds(screen_size).center();
+        let movement =
+            |offset: Vector2<f32>| TouchControls::joystick_movement(screen_size, center + offset);
+        assert_eq!(movement(Vector2::default()), Vector2::default());
+        // Screen Y axis goes down, but the movement goes forward.
+        let forward = movement(Vector2::new(0.0, -JOYSTICK_SIZE * 0.25));
+        assert!((forward - Vector2::new(0.0, 0.5)).norm() < 1.0e-5);
+        let right = movement(Vector2::new(JOYSTICK_SIZE * 2.0, 0.0));
+        assert!((right - Vector2::new(1.0, 0.0)).norm() < 1.0e-5);
+    }
+}
@@ -0,0 +1,375 @@
+//! Tournament is a series of rounds on different maps. Only the best participants of a round go
+//! to the next one, the rest become spectators until the end of the tournament.
+
+use crate::{
+    actor::Actor,
+    level::{Level, MatchStage},
+};
+use fyrox::plugin::error::GameError;
+use fyrox::{graph::SceneGraph, scene::graph::Graph};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{Display, Formatter},
+    path::{Path, PathBuf},
+};
+
+/// Defines how many participants of a round go to the next round.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Qualification {
+    Everyone,
+    /// A fraction (0..1 range) of the best participants.
+    TopFraction(f32),
+    /// Fixed amount of the best participants.
+    TopCount(usize),
+}
+
+impl Qualification {
+    pub const PRESETS: [Qualification; 6] = [
+        Qualification::Everyone,
+        Qualification::TopFraction(0.75),
+        Qualification::TopFraction(0.6),
+        Qualification::TopFraction(0.5),
+        Qualification::TopFraction(0.25),
+        Qualification::TopCount(3),
+    ];
+
+    pub fn qualified_count(&self, participants: usize) -> usize {
+        let count = match self {
+            Qualification::Everyone => participants,
+            Qualification::TopFraction(fraction) => {
+                (participants as f32 * fraction.clamp(0.0, 1.0)).ceil() as usize
+            }
+            Qualification::TopCount(count) => *count,
+        };
+        count.clamp(1, participants.max(1))
+    }
+}
+
+impl Display for Qualification {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Qualification::Everyone => write!(f, "Everyone"),
+            Qualification::TopFraction(fraction) => write!(f, "Top {}%", (fraction * 100.0) as u32),
+            Qualification::TopCount(count) => write!(f, "Top {count}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TournamentRound {
+    pub map: PathBuf,
+    pub qualification: Qualification,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParticipantKind {
+    /// Players are numbered in the spawn order, a connection with several local players has a
+    /// slot for each of them.
+    Player {
+        slot: usize,
+    },
+    Bot,
+}
+
+#[derive(Debug, Clone)]
+pub struct Participant {
+    pub name: String,
+    pub kind: ParticipantKind,
+    pub points: usize,
+    pub eliminated: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Standing {
+    pub name: String,
+    pub points: usize,
+    pub eliminated: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TournamentStandingsMessage {
+    /// Index of the round that has just ended.
+    pub round: usize,
+    pub total_rounds: usize,
+    pub standings: Vec<Standing>,
+}
+
+impl TournamentStandingsMessage {
+    pub fn is_final(&self) -> bool {
+        self.round + 1 >= self.total_rounds
+    }
+}
+
+pub enum TournamentEvent {
+    RoundEnded(TournamentStandingsMessage),
+    NextRound(PathBuf),
+    Finished,
+}
+
+#[derive(Debug)]
+pub struct Tournament {
+    pub rounds: Vec<TournamentRound>,
+    pub current_round: usize,
+    pub participants: Vec<Participant>,
+    intermission_timer: Option<f32>,
+}
+
+impl Tournament {
+    /// Amount of time (in seconds) during which the cumulative results are shown.
+    pub const INTERMISSION_DURATION: f32 = 8.0;
+
+    pub fn new(rounds: Vec<TournamentRound>) -> Self {
+        Self {
+            rounds,
+            current_round: 0,
+            participants: Default::default(),
+            intermission_timer: None,
+        }
+    }
+
+    pub fn current_map(&self) -> Option<&Path> {
+        self.rounds
+            .get(self.current_round)
+            .map(|round| round.map.as_path())
+    }
+
+    pub fn is_last_round(&self) -> bool {
+        self.current_round + 1 >= self.rounds.len()
+    }
+
+    /// Creates participants at the first round, all the next rounds use the same participants.
+    pub fn register_participants(&mut self, players: usize, bots: usize) {
+        if !self.participants.is_empty() {
+            return;
+        }
+        for slot in 0..players {
+            self.participants.push(Participant {
+                name: format!("Player {}", slot + 1),
+                kind: ParticipantKind::Player { slot },
+                points: 0,
+                eliminated: false,
+            });
+        }
+        for bot in 0..bots {
+            self.participants.push(Participant {
+                name: format!("Bot {}", bot + 1),
+                kind: ParticipantKind::Bot,
+                points: 0,
+                eliminated: false,
+            });
+        }
+    }
+
+    /// Removes the participant from the tournament, for example when the map of the current round
+    /// has not enough start points for everyone.
+    pub fn eliminate(&mut self, participant: usize) {
+        if let Some(participant) = self.participants.get_mut(participant) {
+            participant.eliminated = true;
+        }
+    }
+
+    pub fn is_eliminated(&self, participant: usize) -> bool {
+        self.participants
+            .get(participant)
+            .is_some_and(|p| p.eliminated)
+    }
+
+    /// Returns indices of the bots that still participate in the tournament.
+    pub fn active_bots(&self) -> impl Iterator<Item = usize> + '_ {
+        self.participants
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.kind == ParticipantKind::Bot && !p.eliminated)
+            .map(|(i, _)| i)
+    }
+
+    fn competitors_count(&self) -> usize {
+        self.participants.iter().filter(|p| !p.eliminated).count()
+    }
+
+    /// Amount of participants that go through the current round. Only the winner of the last
+    /// round stays in the game.
+    pub fn qualified_count(&self) -> usize {
+        if self.is_last_round() {
+            1
+        } else {
+            self.rounds
+                .get(self.current_round)
+                .map_or(Qualification::Everyone, |r| r.qualification)
+                .qualified_count(self.competitors_count())
+        }
+    }
+
+    fn is_round_over(&self, level: &Level) -> bool {
+        if level.stage != MatchStage::Running {
+            return false;
+        }
+        level.is_match_ended()
+            || level
+                .game_mode
+                .is_quota_reached(&level.leaderboard, self.qualified_count())
+    }
+
+    /// Gives points to the participants and eliminates the ones, that did not qualify. `ranking`
+    /// contains indices of the participants from the best to the worst.
+    fn finish_round(&mut self, ranking: &[usize]) {
+        let competitors = self.competitors_count();
+        let qualified = self.qualified_count();
+        for (rank, participant) in ranking.iter().enumerate() {
+            if let Some(participant) = self.participants.get_mut(*participant) {
+                participant.points += competitors.saturating_sub(rank);
+                if rank >= qualified {
+                    participant.eliminated = true;
+                }
+            }
+        }
+    }
+
+    pub fn standings(&self) -> Vec<Standing> {
+        let mut standings = self
+            .participants
+            .iter()
+            .map(|p| Standing {
+                name: p.name.clone(),
+                points: p.points,
+                eliminated: p.eliminated,
+            })
+            .collect::<Vec<_>>();
+        standings.sort_by(|a, b| {
+            a.eliminated
+                .cmp(&b.eliminated)
+                .then_with(|| b.points.cmp(&a.points))
+        });
+        standings
+    }
+
+    pub fn update(
+        &mut self,
+        level: &Level,
+        graph: &Graph,
+        dt: f32,
+    ) -> Result<Option<TournamentEvent>, GameError> {
+        if let Some(timer) = self.intermission_timer.as_mut() {
+            *timer -= dt;
+            if *timer > 0.0 {
+                return Ok(None);
+            }
+
+            self.intermission_timer = None;
+            if self.is_last_round() {
+                return Ok(Some(TournamentEvent::Finished));
+            }
+            self.current_round += 1;
+            return Ok(self
+                .current_map()
+                .map(|map| TournamentEvent::NextRound(map.to_path_buf())));
+        }
+
+        if !self.is_round_over(level) {
+            return Ok(None);
+        }
+
+        let ranking = level
+            .game_mode
+            .ranking(&level.leaderboard)
+            .into_iter()
+            .map(|actor| {
+                graph
+                    .try_get_script_component_of::<Actor>(actor)
+                    .map(|actor| actor.participant)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.finish_round(&ranking);
+        self.intermission_timer = Some(Self::INTERMISSION_DURATION);
+
+        Ok(Some(TournamentEvent::RoundEnded(
+            TournamentStandingsMessage {
+                round: self.current_round,
+                total_rounds: self.rounds.len(),
+                standings: self.standings(),
+            },
+        )))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tournament(qualifications: &[Qualification]) -> Tournament {
+        Tournament::new(
+            qualifications
+                .iter()
+                .map(|qualification| TournamentRound {
+                    map: PathBuf::from("data/maps/test.rgs"),
+                    qualification: *qualification,
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn qualified_count() {
+        assert_eq!(Qualification::Everyone.qualified_count(5), 5);
+        assert_eq!(Qualification::TopFraction(0.5).qualified_count(5), 3);
+        assert_eq!(Qualification::TopFraction(0.25).qualified_count(8), 2);
+        assert_eq!(Qualification::TopFraction(0.0).qualified_count(8), 1);
+        assert_eq!(Qualification::TopFraction(2.0).qualified_count(8), 8);
+        assert_eq!(Qualification::TopCount(3).qualified_count(8), 3);
+        assert_eq!(Qualification::TopCount(3).qualified_count(2), 2);
+        assert_eq!(Qualification::TopCount(0).qualified_count(2), 1);
+        assert_eq!(Qualification::Everyone.qualified_count(0), 1);
+    }
+
+    #[test]
+    fn finish_round_gives_points_and_eliminates() {
+        let mut tournament = tournament(&[Qualification::TopCount(2), Qualification::Everyone]);
+        tournament.register_participants(1, 3);
+        tournament.finish_round(&[2, 0, 3, 1]);
+
+        let points = tournament
+            .participants
+            .iter()
+            .map(|p| p.points)
+            .collect::<Vec<_>>();
+        assert_eq!(points, [3, 1, 4, 2]);
+        let eliminated = tournament
+            .participants
+            .iter()
+            .map(|p| p.eliminated)
+            .collect::<Vec<_>>();
+        assert_eq!(eliminated, [false, true, false, true]);
+        assert_eq!(tournament.active_bots().collect::<Vec<_>>(), [2]);
+    }
+
+    #[test]
+    fn last_round_has_single_winner() {
+        let mut tournament = tournament(&[Qualification::Everyone, Qualification::Everyone]);
+        tournament.register_participants(2, 1);
+        tournament.finish_round(&[0, 1, 2]);
+        assert!(tournament.participants.iter().all(|p| !p.eliminated));
+
+        tournament.current_round = 1;
+        assert_eq!(tournament.qualified_count(), 1);
+        tournament.finish_round(&[1, 0, 2]);
+        let standings = tournament.standings();
+        assert_eq!(standings[0].name, "Player 2");
+        assert!(!standings[0].eliminated);
+        assert!(standings[1..].iter().all(|s| s.eliminated));
+    }
+
+    #[test]
+    fn eliminated_participants_do_not_compete() {
+        let mut tournament =
+            tournament(&[Qualification::TopFraction(0.5), Qualification::Everyone]);
+        tournament.register_participants(1, 3);
+        tournament.eliminate(3);
+        // Three competitors left, so the best two qualify and the winner gets three points.
+        assert_eq!(tournament.qualified_count(), 2);
+        tournament.finish_round(&[1, 0, 2]);
+        assert_eq!(tournament.participants[1].points, 3);
+        assert!(tournament.participants[2].eliminated);
+        assert!(tournament.participants[3].eliminated);
+        assert!(!tournament.participants[0].eliminated);
+    }
+}
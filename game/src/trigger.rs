@@ -51,7 +51,11 @@ impl ScriptTrait for Trigger {
             let rigid_body = graph.try_get(actor_script.rigid_body)?;
             if self_bounds.is_contains_point(rigid_body.global_position()) {
                 match *self.action {
-                    Action::Finish => game.level.leaderboard.finish(*actor_handle),
+                    Action::Finish => {
+                        if game.level.game_mode.has_finish_line() {
+                            game.level.leaderboard.finish(*actor_handle)
+                        }
+                    }
                 }
             }
         }
@@ -5,16 +5,31 @@ use fyrox::{
     scene::{collider::Collider, graph::Graph,  sound::Sound},
 };
 
-pub fn has_ground_contact(collider: Handle<Collider>, graph: &Graph) -> Result<bool, GameError> {
-    let collider = graph.try_get(collider)?;
-    for contact in collider.contacts(&graph.physics) {
-        for manifold in contact.manifolds.iter() {
-            if manifold.local_n1.y.abs() > 0.7 || manifold.local_n2.y.abs() > 0.7 {
-                return Ok(true);
+/// Returns colliders, that the given collider stands on.
+pub fn ground_contacts(
+    collider: Handle<Collider>,
+    graph: &Graph,
+) -> Result<impl Iterator<Item = Handle<Collider>> + '_, GameError> {
+    Ok(graph
+        .try_get(collider)?
+        .contacts(&graph.physics)
+        .filter(|contact| {
+            contact
+                .manifolds
+                .iter()
+                .any(|manifold| manifold.local_n1.y.abs() > 0.7 || manifold.local_n2.y.abs() > 0.7)
+        })
+        .map(move |contact| {
+            if contact.collider1 == collider {
+                contact.collider2
+            } else {
+                contact.collider1
             }
-        }
-    }
-    Ok(false)
+        }))
+}
+
+pub fn has_ground_contact(collider: Handle<Collider>, graph: &Graph) -> Result<bool, GameError> {
+    Ok(ground_contacts(collider, graph)?.next().is_some())
 }
 
 pub fn try_play_sound(sound: Handle<Sound>, graph: &mut Graph) -> GameResult {